@@ -3,7 +3,8 @@
 use crate::{
     poa::{PoA, ShouldIssueBlock},
     produce_block::{produce_block, ProduceBlockParam, ProduceBlockResult},
-    rpc_client::{DepositInfo, RPCClient},
+    rpc_client::{sort_deposits, DepositInfo, RPCClient},
+    submission::{self, SubmissionStatus, SubmissionTracker},
     test_mode_control::TestModeControl,
     transaction_skeleton::TransactionSkeleton,
     types::ChainEvent,
@@ -16,7 +17,7 @@ use ckb_types::prelude::Unpack as CKBUnpack;
 use futures::{future::select_all, FutureExt};
 use gw_chain::chain::{Chain, SyncEvent};
 use gw_common::{h256_ext::H256Ext, CKB_SUDT_SCRIPT_ARGS, H256};
-use gw_config::BlockProducerConfig;
+use gw_config::{BlockProducerConfig, IdleBlockMode};
 use gw_generator::{Generator, RollupContext};
 use gw_jsonrpc_types::test_mode::TestModePayload;
 use gw_mem_pool::pool::MemPool;
@@ -26,11 +27,12 @@ use gw_types::{
     core::{DepType, ScriptHashType, Status},
     packed::{
         Byte32, CellDep, CellInput, CellOutput, CustodianLockArgs, DepositLockArgs, GlobalState,
-        L2Block, OutPoint, OutPointVec, RollupAction, RollupActionUnion, RollupSubmitBlock, Script,
-        Transaction, WitnessArgs,
+        L2Block, L2Transaction, OutPoint, OutPointVec, RollupAction, RollupActionUnion,
+        RollupSubmitBlock, Script, Transaction, WithdrawalRequest, WitnessArgs,
     },
     prelude::*,
 };
+use gw_web3_indexer::indexer::ROLLUP_WITNESS_INDEX;
 use parking_lot::Mutex;
 use std::{
     collections::{HashMap, HashSet},
@@ -41,6 +43,59 @@ use std::{
 
 const TRANSACTION_SRIPT_ERROR: &str = "TransactionScriptError";
 
+/// Nothing is waiting to be submitted in the next block
+fn is_idle_block(
+    txs: &[L2Transaction],
+    withdrawal_requests: &[WithdrawalRequest],
+    deposit_cells: &[DepositInfo],
+    pending_revert_blocks: &[L2Block],
+) -> bool {
+    txs.is_empty()
+        && withdrawal_requests.is_empty()
+        && deposit_cells.is_empty()
+        && pending_revert_blocks.is_empty()
+}
+
+/// Whether to produce a block at `now`, both timestamps are in milliseconds
+fn should_produce_block(mode: IdleBlockMode, is_idle: bool, now: u64, tip_timestamp: u64) -> bool {
+    if !is_idle {
+        return true;
+    }
+    match mode {
+        IdleBlockMode::Always => true,
+        IdleBlockMode::Pending => false,
+        IdleBlockMode::KeepAlive { interval_secs } => {
+            now.saturating_sub(tip_timestamp) >= interval_secs.saturating_mul(1000)
+        }
+    }
+}
+
+/// Attach submit block action as the rollup cell witness, so that the rollup
+/// script and the web3 indexer read layer2 block from the same witness
+pub fn attach_rollup_block_witness(
+    tx_skeleton: &mut TransactionSkeleton,
+    submit_block: RollupSubmitBlock,
+) -> Result<RollupAction> {
+    let witness_count = tx_skeleton.witnesses().len();
+    if ROLLUP_WITNESS_INDEX != witness_count {
+        return Err(anyhow!(
+            "rollup witness index {} mismatch, {} witnesses attached",
+            ROLLUP_WITNESS_INDEX,
+            witness_count
+        ));
+    }
+
+    let rollup_action = RollupAction::new_builder()
+        .set(RollupActionUnion::RollupSubmitBlock(submit_block))
+        .build();
+    tx_skeleton.witnesses_mut().push(
+        WitnessArgs::new_builder()
+            .output_type(Some(rollup_action.as_bytes()).pack())
+            .build(),
+    );
+    Ok(rollup_action)
+}
+
 fn generate_custodian_cells(
     rollup_context: &RollupContext,
     block: &L2Block,
@@ -161,6 +216,7 @@ pub struct BlockProducer {
     rpc_client: RPCClient,
     ckb_genesis_info: CKBGenesisInfo,
     tests_control: Option<TestModeControl>,
+    submission_tracker: SubmissionTracker,
 }
 
 impl BlockProducer {
@@ -184,6 +240,7 @@ impl BlockProducer {
             config.poa_state_dep.clone().into(),
         );
 
+        let submission_tracker = SubmissionTracker::new(config.submission_confirmation_depth);
         let block_producer = BlockProducer {
             rollup_config_hash,
             store,
@@ -196,6 +253,7 @@ impl BlockProducer {
             ckb_genesis_info,
             config,
             tests_control,
+            submission_tracker,
         };
         Ok(block_producer)
     }
@@ -240,6 +298,14 @@ impl BlockProducer {
             return Ok(());
         }
 
+        // wait for the last submission to be confirmed on layer1
+        if self.submission_tracker.is_pending() {
+            match self.submission_tracker.poll(&self.rpc_client).await? {
+                SubmissionStatus::Confirmed | SubmissionStatus::Dropped => (),
+                SubmissionStatus::Pending | SubmissionStatus::Resubmitted => return Ok(()),
+            }
+        }
+
         let median_time = self.rpc_client.get_block_median_time(tip_hash).await?;
         let poa_cell_input = InputCellInfo {
             input: CellInput::new_builder()
@@ -280,8 +346,9 @@ impl BlockProducer {
 
         // get deposit cells
         // check deposit cells again to prevent upstream components errors.
-        let deposit_cells =
+        let mut deposit_cells =
             self.sanitize_deposit_cells(self.rpc_client.query_deposit_cells().await?);
+        sort_deposits(&mut deposit_cells, self.config.deposit_order);
 
         // get txs & withdrawal requests from mem pool
         let mut txs = Vec::new();
@@ -296,7 +363,26 @@ impl BlockProducer {
                 }
             }
         };
-        let parent_block = self.chain.lock().local_state().tip().clone();
+        let (parent_block, is_idle) = {
+            let chain = self.chain.lock();
+            let is_idle = is_idle_block(
+                &txs,
+                &withdrawal_requests,
+                &deposit_cells,
+                chain.pending_revert_blocks(),
+            );
+            (chain.local_state().tip().clone(), is_idle)
+        };
+        let tip_timestamp = parent_block.raw().timestamp().unpack();
+        if !should_produce_block(
+            self.config.idle_block_mode,
+            is_idle,
+            timestamp,
+            tip_timestamp,
+        ) {
+            log::debug!("skip producing empty block");
+            return Ok(());
+        }
         let max_withdrawal_capacity = std::u128::MAX;
 
         let available_custodians = if withdrawal_requests.is_empty() {
@@ -415,21 +501,21 @@ impl BlockProducer {
             .complete_tx_skeleton(deposit_cells, block, global_state, median_time, rollup_cell)
             .await?;
 
-        utils::dry_run_transaction(
-            &self.rpc_client,
-            tx.clone(),
-            format!("L2 block {}", number).as_str(),
-        )
-        .await;
+        let action = format!("L2 block {}", number);
+        let dry_run = self.config.dry_run_before_submit;
+        if !dry_run {
+            utils::dry_run_transaction(&self.rpc_client, tx.clone(), &action).await;
+        }
 
         // send transaction
-        match self.rpc_client.send_transaction(tx.clone()).await {
+        match submission::submit_transaction(&self.rpc_client, tx.clone(), dry_run, &action).await {
             Ok(tx_hash) => {
                 log::info!(
                     "\nSubmitted l2 block {} in tx {}\n",
                     number,
                     hex::encode(tx_hash.as_slice())
                 );
+                self.submission_tracker.track(number, tx, tx_hash);
             }
             Err(err) => {
                 log::error!("Submitting l2 block error: {}", err);
@@ -440,6 +526,8 @@ impl BlockProducer {
                     // dumping failed tx
                     utils::dump_transaction(
                         &self.config.debug_tx_dump_path,
+                        self.config.debug_tx_dump_max_files,
+                        &self.config.debug_tx_dump_formats,
                         &self.rpc_client,
                         tx.clone(),
                     )
@@ -491,7 +579,7 @@ impl BlockProducer {
             .push(self.ckb_genesis_info.sighash_dep());
 
         // rollup action
-        let rollup_action = {
+        let submit_block = {
             let revert_block = {
                 let chain = self.chain.lock();
                 let first_block = chain.pending_revert_blocks().first();
@@ -521,19 +609,11 @@ impl BlockProducer {
                 None => RollupSubmitBlock::new_builder(),
             };
 
-            let submit_block = submit_builder.block(block.clone()).build();
-
-            RollupAction::new_builder()
-                .set(RollupActionUnion::RollupSubmitBlock(submit_block))
-                .build()
+            submit_builder.block(block.clone()).build()
         };
 
         // witnesses
-        tx_skeleton.witnesses_mut().push(
-            WitnessArgs::new_builder()
-                .output_type(Some(rollup_action.as_bytes()).pack())
-                .build(),
-        );
+        let rollup_action = attach_rollup_block_witness(&mut tx_skeleton, submit_block)?;
         // output
         let output = rollup_cell.output.clone();
         let output_data = global_state.as_bytes();
@@ -843,3 +923,93 @@ impl BlockProducer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gw_types::packed::RawL2Block;
+    use gw_web3_indexer::indexer::extract_l2_block;
+
+    #[test]
+    fn test_attach_rollup_block_witness() {
+        let block = L2Block::new_builder()
+            .raw(RawL2Block::new_builder().number(3u64.pack()).build())
+            .build();
+        let submit_block = RollupSubmitBlock::new_builder()
+            .block(block.clone())
+            .build();
+
+        let mut tx_skeleton = TransactionSkeleton::default();
+        tx_skeleton.inputs_mut().push(InputCellInfo {
+            input: CellInput::default(),
+            cell: CellInfo {
+                out_point: OutPoint::default(),
+                output: CellOutput::default(),
+                data: Bytes::default(),
+            },
+        });
+        attach_rollup_block_witness(&mut tx_skeleton, submit_block.clone()).unwrap();
+
+        let entries = tx_skeleton.signature_entries();
+        let signatures = vec![[0u8; 65]; entries.len()];
+        let tx = tx_skeleton.seal(&entries, signatures).unwrap().transaction;
+        let extracted = extract_l2_block(&tx).unwrap().expect("l2 block");
+        assert_eq!(extracted.as_slice(), block.as_slice());
+
+        // Already attached
+        assert!(attach_rollup_block_witness(&mut tx_skeleton, submit_block).is_err());
+    }
+
+    // Produce blocks every second over an idle minute, return timestamps
+    fn simulate_idle_period(mode: IdleBlockMode) -> Vec<u64> {
+        let mut tip_timestamp = 0;
+        let mut produced = vec![];
+        for now in (1..=60).map(|secs| secs * 1000) {
+            if should_produce_block(mode, true, now, tip_timestamp) {
+                tip_timestamp = now;
+                produced.push(now);
+            }
+        }
+        produced
+    }
+
+    #[test]
+    fn test_should_produce_block() {
+        assert_eq!(simulate_idle_period(IdleBlockMode::Always).len(), 60);
+        assert!(simulate_idle_period(IdleBlockMode::Pending).is_empty());
+        assert_eq!(
+            simulate_idle_period(IdleBlockMode::KeepAlive { interval_secs: 20 }),
+            vec![20_000, 40_000, 60_000]
+        );
+
+        // Pending work is always packed
+        for mode in [
+            IdleBlockMode::Always,
+            IdleBlockMode::Pending,
+            IdleBlockMode::KeepAlive { interval_secs: 20 },
+        ]
+        .iter()
+        {
+            assert!(should_produce_block(*mode, false, 1000, 1000));
+        }
+    }
+
+    #[test]
+    fn test_produce_block_for_pending_revert_blocks() {
+        assert!(is_idle_block(&[], &[], &[], &[]));
+
+        // Reverted blocks are only submitted along with a new block
+        let pending_revert_blocks = vec![L2Block::default()];
+        let is_idle = is_idle_block(&[], &[], &[], &pending_revert_blocks);
+        assert!(!is_idle);
+        for mode in [
+            IdleBlockMode::Pending,
+            IdleBlockMode::KeepAlive { interval_secs: 20 },
+        ]
+        .iter()
+        {
+            assert!(should_produce_block(*mode, is_idle, 1000, 1000));
+        }
+    }
+}
@@ -15,7 +15,7 @@ use gw_chain::challenge::{RevertContext, VerifyContext};
 use gw_common::H256;
 use gw_config::BlockProducerConfig;
 use gw_generator::{ChallengeContext, RollupContext};
-use gw_jsonrpc_types::test_mode::TestModePayload;
+use gw_jsonrpc_types::{h256::to_h256, test_mode::TestModePayload};
 use gw_types::bytes::Bytes;
 use gw_types::core::{ChallengeTargetType, Status};
 use gw_types::packed::{
@@ -30,11 +30,13 @@ use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 mod cancel_challenge;
+mod cost;
 mod enter_challenge;
 mod revert;
 
 use cancel_challenge::CancelChallengeOutput;
-use enter_challenge::EnterChallenge;
+pub use cost::{estimate_challenge_cost, ChallengeCost};
+use enter_challenge::{build_challenge_tx_skeleton, push_challenge_cell, EnterChallenge};
 use revert::Revert;
 
 pub struct Challenger {
@@ -108,7 +110,10 @@ impl Challenger {
 
                 for (script_type_hash, dep) in allowed_scripts {
                     if let Some(cell_info) = rpc_client
-                        .query_verifier_cell(script_type_hash.0, owner_lock_hash)
+                        .query_verifier_cell(
+                            to_h256(script_type_hash.clone()).into(),
+                            owner_lock_hash,
+                        )
                         .await?
                     {
                         let cell_dep: CellDep = dep.to_owned().into();
@@ -137,6 +142,10 @@ impl Challenger {
 
         match last_sync_event {
             SyncEvent::Success => Ok(()),
+            SyncEvent::Halted { reorg } => {
+                log::warn!("chain halted by unsafe reorg {:?}, skip challenger", reorg);
+                Ok(())
+            }
             SyncEvent::BadBlock { context } => {
                 if let Some(ref tests_control) = self.tests_control {
                     match tests_control.payload().await {
@@ -196,6 +205,33 @@ impl Challenger {
         }
 
         let block_numer = context.witness.raw_l2block().number().unpack();
+        let tx_skeleton = self
+            .build_challenge_tx(&rollup_state, context, media_time)
+            .await?;
+        let tx = self.wallet.sign_tx_skeleton(tx_skeleton)?;
+
+        utils::dry_run_transaction(&self.rpc_client, tx.clone(), "challenge block").await;
+        utils::dump_transaction(
+            &self.config.debug_tx_dump_path,
+            self.config.debug_tx_dump_max_files,
+            &self.config.debug_tx_dump_formats,
+            &self.rpc_client,
+            tx.clone(),
+        )
+        .await;
+
+        let tx_hash = self.rpc_client.send_transaction(tx).await?;
+        log::info!("Challenge block {} in tx {}", block_numer, to_hex(&tx_hash));
+        Ok(())
+    }
+
+    /// Build challenge transaction with PoA and fee filled, ready to sign
+    async fn build_challenge_tx(
+        &self,
+        rollup_state: &RollupState,
+        context: ChallengeContext,
+        media_time: Duration,
+    ) -> Result<TransactionSkeleton> {
         let rewards_lock = {
             let challenger_config = &self.config.challenger_config;
             challenger_config.rewards_receiver_lock.clone().into()
@@ -205,24 +241,12 @@ impl Challenger {
             EnterChallenge::new(prev_state, &self.rollup_context, context, rewards_lock);
         let challenge_output = enter_challenge.build_output();
 
-        // Build challenge transaction
-        let mut tx_skeleton = TransactionSkeleton::default();
-
-        // Rollup
-        let rollup_deps = vec![
-            self.config.rollup_cell_type_dep.clone().into(),
-            self.config.rollup_config_cell_dep.clone().into(),
-        ];
-        let rollup_output = (
-            rollup_state.rollup_output(),
-            challenge_output.post_global_state.as_bytes(),
+        let mut tx_skeleton = build_challenge_tx_skeleton(
+            &self.config,
+            &rollup_state.rollup_cell,
+            &challenge_output.post_global_state,
+            challenge_output.rollup_witness,
         );
-        let rollup_witness = challenge_output.rollup_witness;
-
-        tx_skeleton.cell_deps_mut().extend(rollup_deps);
-        tx_skeleton.inputs_mut().push(rollup_state.rollup_input());
-        tx_skeleton.outputs_mut().push(rollup_output);
-        tx_skeleton.witnesses_mut().push(rollup_witness);
 
         // Poa
         {
@@ -231,27 +255,16 @@ impl Challenger {
         }
 
         // Challenge
-        let challenge_cell = challenge_output.challenge_cell;
-        tx_skeleton.outputs_mut().push(challenge_cell);
-
         let challenger_lock_dep = self.ckb_genesis_info.sighash_dep();
+        push_challenge_cell(
+            &mut tx_skeleton,
+            challenge_output.challenge_cell,
+            challenger_lock_dep,
+        );
+
         let challenger_lock = self.wallet.lock_script().to_owned();
-        tx_skeleton.cell_deps_mut().push(challenger_lock_dep);
         fill_tx_fee(&mut tx_skeleton, &self.rpc_client, challenger_lock).await?;
-
-        let tx = self.wallet.sign_tx_skeleton(tx_skeleton)?;
-
-        utils::dry_run_transaction(&self.rpc_client, tx.clone(), "challenge block").await;
-        utils::dump_transaction(
-            &self.config.debug_tx_dump_path,
-            &self.rpc_client,
-            tx.clone(),
-        )
-        .await;
-
-        let tx_hash = self.rpc_client.send_transaction(tx).await?;
-        log::info!("Challenge block {} in tx {}", block_numer, to_hex(&tx_hash));
-        Ok(())
+        Ok(tx_skeleton)
     }
 
     async fn cancel_challenge(
@@ -279,6 +292,34 @@ impl Challenger {
         let verifier_tx_hash = self.rpc_client.send_transaction(tx).await?;
         log::info!("Create verifier in tx {}", to_hex(&verifier_tx_hash));
 
+        // Inputs of the verifier tx stay live in the indexer until it's
+        // committed, don't pay the cancel or reclaim tx fee with them
+        let spent_inputs = verifier_spent_inputs.iter().cloned();
+        self.rpc_client.reserve_payment_cells(spent_inputs);
+        let result = self
+            .cancel_challenge_with_verifier(
+                rollup_state,
+                challenge_cell,
+                cancel_output,
+                verifier_tx_hash,
+                verifier_spent_inputs.clone(),
+                media_time,
+            )
+            .await;
+        self.rpc_client
+            .release_payment_cells(&verifier_spent_inputs);
+        result
+    }
+
+    async fn cancel_challenge_with_verifier(
+        &self,
+        rollup_state: RollupState,
+        challenge_cell: CellInfo,
+        cancel_output: CancelChallengeOutput,
+        verifier_tx_hash: H256,
+        verifier_spent_inputs: HashSet<OutPoint>,
+        media_time: Duration,
+    ) -> Result<()> {
         self.wait_tx_proposed(verifier_tx_hash).await?;
 
         // Build cancellation transaction
@@ -303,6 +344,8 @@ impl Challenger {
         utils::dry_run_transaction(&self.rpc_client, tx.clone(), "cancel challenge").await;
         utils::dump_transaction(
             &self.config.debug_tx_dump_path,
+            self.config.debug_tx_dump_max_files,
+            &self.config.debug_tx_dump_formats,
             &self.rpc_client,
             tx.clone(),
         )
@@ -439,6 +482,8 @@ impl Challenger {
         utils::dry_run_transaction(&self.rpc_client, tx.clone(), "revert block").await;
         utils::dump_transaction(
             &self.config.debug_tx_dump_path,
+            self.config.debug_tx_dump_max_files,
+            &self.config.debug_tx_dump_formats,
             &self.rpc_client,
             tx.clone(),
         )
@@ -6,6 +6,7 @@ use gw_chain::challenge::{VerifyContext, VerifyWitness};
 use gw_common::H256;
 use gw_config::BlockProducerConfig;
 use gw_generator::RollupContext;
+use gw_jsonrpc_types::h256::to_h256;
 use gw_types::core::Status;
 use gw_types::packed::{
     CellDep, CellInput, CellOutput, GlobalState, OutPoint, RollupAction, RollupActionUnion,
@@ -55,12 +56,16 @@ impl CancelChallengeOutput {
     }
 
     pub fn verifier_dep(&self, block_producer_config: &BlockProducerConfig) -> Result<CellDep> {
-        let lock_code_hash: [u8; 32] = self.verifier_cell.0.lock().code_hash().unpack();
+        let lock_code_hash: H256 = {
+            let code_hash: [u8; 32] = self.verifier_cell.0.lock().code_hash().unpack();
+            code_hash.into()
+        };
         let mut allowed_script_deps = {
             let eoa = block_producer_config.allowed_eoa_deps.iter();
             eoa.chain(block_producer_config.allowed_contract_deps.iter())
         };
-        let has_dep = allowed_script_deps.find(|(code_hash, _)| code_hash.0 == lock_code_hash);
+        let has_dep =
+            allowed_script_deps.find(|(code_hash, _)| to_h256(code_hash.clone()) == lock_code_hash);
         let to_dep = has_dep.map(|(_, dep)| dep.clone().into());
         to_dep.ok_or_else(|| anyhow!("verifier lock dep not found"))
     }
@@ -0,0 +1,306 @@
+use super::cancel_challenge::{self, CancelChallengeOutput};
+use crate::transaction_skeleton::TransactionSkeleton;
+use crate::types::{CellInfo, InputCellInfo};
+
+use anyhow::Result;
+use ckb_types::prelude::{Builder, Entity};
+use gw_chain::challenge::{build_verify_context_with_checks, VerifyContext};
+use gw_common::H256;
+use gw_generator::{Generator, RollupContext};
+use gw_store::transaction::StoreTransaction;
+use gw_types::bytes::Bytes;
+use gw_types::packed::{
+    CellDep, CellInput, CellOutput, ChallengeTarget, GlobalState, OutPoint, Script,
+};
+use gw_types::prelude::{Pack, Unpack};
+
+use std::sync::Arc;
+
+// Same as change cell in utils::fill_tx_fee
+const CHANGE_CELL_CAPACITY: u64 = 61_00000000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChallengeCost {
+    /// Estimated in block size of the verification transaction
+    pub tx_size: usize,
+    /// Fee for the verification transaction
+    pub tx_fee: u64,
+    /// Stake capacity rewarded to challenger
+    pub reward: u128,
+}
+
+impl ChallengeCost {
+    pub fn is_profitable(&self) -> bool {
+        self.reward > self.tx_fee as u128
+    }
+}
+
+/// Estimate the verification transaction fee of challenge target under
+/// `fee_rate` (shannons per KB), along with the reward from rollup config.
+/// Target block checkpoints are checked if `verify_checkpoints` is set.
+#[allow(clippy::too_many_arguments)]
+pub fn estimate_challenge_cost(
+    generator: Arc<Generator>,
+    db: &StoreTransaction,
+    target: &ChallengeTarget,
+    rollup_cell: &CellInfo,
+    challenge_cell: &CellInfo,
+    owner_lock: Script,
+    fee_rate: u64,
+    verify_checkpoints: bool,
+) -> Result<ChallengeCost> {
+    let rollup_context = generator.rollup_context().to_owned();
+    let context = build_verify_context_with_checks(generator, db, target, verify_checkpoints)?;
+    estimate_cost(
+        &rollup_context,
+        context,
+        rollup_cell,
+        challenge_cell,
+        owner_lock,
+        fee_rate,
+    )
+}
+
+fn estimate_cost(
+    rollup_context: &RollupContext,
+    context: VerifyContext,
+    rollup_cell: &CellInfo,
+    challenge_cell: &CellInfo,
+    owner_lock: Script,
+    fee_rate: u64,
+) -> Result<ChallengeCost> {
+    let prev_state = GlobalState::from_slice(&rollup_cell.data)?;
+    let cancel_output =
+        cancel_challenge::build_output(rollup_context, prev_state, owner_lock.clone(), context)?;
+
+    let tx_skeleton =
+        build_verification_tx_skeleton(rollup_cell, challenge_cell, cancel_output, owner_lock);
+    let tx_size = tx_skeleton.tx_in_block_size()?;
+
+    Ok(ChallengeCost {
+        tx_size,
+        tx_fee: calculate_tx_fee(tx_size, fee_rate),
+        reward: expected_reward(rollup_context),
+    })
+}
+
+// Round up so the fee rate is never below `fee_rate`
+fn calculate_tx_fee(tx_size: usize, fee_rate: u64) -> u64 {
+    (tx_size as u64)
+        .saturating_mul(fee_rate)
+        .saturating_add(999)
+        / 1000
+}
+
+// Same as revert::Rewards
+fn expected_reward(rollup_context: &RollupContext) -> u128 {
+    let config = &rollup_context.rollup_config;
+    let stake_capacity: u64 = config.required_staking_capacity().unpack();
+    let reward_burn_rate: u8 = config.reward_burn_rate().into();
+    (stake_capacity as u128).saturating_mul(reward_burn_rate.into()) / 100
+}
+
+// Cell deps are fixed size, so default deps are used. Verifier tx hash and
+// poa are unknown before submission and ignored.
+fn build_verification_tx_skeleton(
+    rollup_cell: &CellInfo,
+    challenge_cell: &CellInfo,
+    cancel_output: CancelChallengeOutput,
+    owner_lock: Script,
+) -> TransactionSkeleton {
+    let mut tx_skeleton = TransactionSkeleton::default();
+
+    // Rollup
+    let rollup_output = (
+        rollup_cell.output.clone(),
+        cancel_output.post_global_state.as_bytes(),
+    );
+    tx_skeleton
+        .cell_deps_mut()
+        .extend(vec![CellDep::default(); 2]);
+    tx_skeleton
+        .inputs_mut()
+        .push(to_input_cell_info(rollup_cell));
+    tx_skeleton.outputs_mut().push(rollup_output);
+    tx_skeleton
+        .witnesses_mut()
+        .push(cancel_output.rollup_witness);
+
+    // Challenge
+    tx_skeleton.cell_deps_mut().push(CellDep::default());
+    tx_skeleton
+        .inputs_mut()
+        .push(to_input_cell_info(challenge_cell));
+    tx_skeleton
+        .witnesses_mut()
+        .push(cancel_output.challenge_witness);
+
+    // Verifier
+    let verifier_input = cancel_output.verifier_input(H256::zero(), 0);
+    tx_skeleton.cell_deps_mut().push(CellDep::default());
+    tx_skeleton.inputs_mut().push(verifier_input);
+    if let Some(verifier_witness) = cancel_output.verifier_witness {
+        tx_skeleton.witnesses_mut().push(verifier_witness);
+    }
+
+    // Owner cell pays fee and receives change
+    let owner_cell = CellInfo {
+        out_point: OutPoint::default(),
+        output: CellOutput::new_builder()
+            .lock(owner_lock)
+            .capacity(CHANGE_CELL_CAPACITY.pack())
+            .build(),
+        data: Bytes::default(),
+    };
+    let change_cell = owner_cell.output.clone();
+    tx_skeleton.cell_deps_mut().push(CellDep::default());
+    tx_skeleton
+        .inputs_mut()
+        .push(to_input_cell_info(&owner_cell));
+    tx_skeleton
+        .outputs_mut()
+        .push((change_cell, Default::default()));
+
+    tx_skeleton
+}
+
+fn to_input_cell_info(cell_info: &CellInfo) -> InputCellInfo {
+    InputCellInfo {
+        input: CellInput::new_builder()
+            .previous_output(cell_info.out_point.clone())
+            .build(),
+        cell: cell_info.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gw_chain::challenge::VerifyWitness;
+    use gw_types::packed::{RollupConfig, VerifyWithdrawalWitness};
+
+    #[test]
+    fn test_estimate_challenge_cost() {
+        let rollup_context = RollupContext {
+            rollup_script_hash: [1u8; 32].into(),
+            rollup_config: RollupConfig::new_builder()
+                .required_staking_capacity(1000_00000000u64.pack())
+                .reward_burn_rate(50u8.into())
+                .build(),
+        };
+        let rollup_cell = CellInfo {
+            out_point: OutPoint::default(),
+            output: CellOutput::default(),
+            data: GlobalState::default().as_bytes(),
+        };
+        let challenge_cell = CellInfo {
+            out_point: OutPoint::default(),
+            output: CellOutput::default(),
+            data: Bytes::default(),
+        };
+        let sender_script = Script::new_builder().args(vec![2u8; 52].pack()).build();
+        let owner_lock = Script::new_builder().args(vec![3u8; 20].pack()).build();
+        let context = VerifyContext {
+            sender_script,
+            receiver_script: None,
+            verify_witness: VerifyWitness::Withdrawal(VerifyWithdrawalWitness::default()),
+        };
+
+        let fee_rate = 1000;
+        let cost = estimate_cost(
+            &rollup_context,
+            context.clone(),
+            &rollup_cell,
+            &challenge_cell,
+            owner_lock.clone(),
+            fee_rate,
+        )
+        .unwrap();
+        assert_eq!(cost.reward, 500_00000000);
+        assert_eq!(cost.tx_fee, calculate_tx_fee(cost.tx_size, fee_rate));
+        assert!(cost.is_profitable());
+
+        // Build the signed transaction like Challenger::build_cancel_tx, with
+        // deps and out points of real values
+        let prev_state = GlobalState::from_slice(&rollup_cell.data).unwrap();
+        let cancel_output = cancel_challenge::build_output(
+            &rollup_context,
+            prev_state,
+            owner_lock.clone(),
+            context,
+        )
+        .unwrap();
+        let cell_dep = |index: u32| {
+            let out_point = OutPoint::new_builder()
+                .tx_hash([index as u8; 32].pack())
+                .index(index.pack())
+                .build();
+            CellDep::new_builder().out_point(out_point).build()
+        };
+        let input = |cell: CellInfo| InputCellInfo {
+            input: CellInput::new_builder()
+                .previous_output(cell.out_point.clone())
+                .build(),
+            cell,
+        };
+        let mut tx_skeleton = TransactionSkeleton::default();
+        tx_skeleton
+            .cell_deps_mut()
+            .extend(vec![cell_dep(1), cell_dep(2), cell_dep(3)]);
+        tx_skeleton.inputs_mut().push(input(rollup_cell.clone()));
+        tx_skeleton.outputs_mut().push((
+            rollup_cell.output.clone(),
+            cancel_output.post_global_state.as_bytes(),
+        ));
+        tx_skeleton
+            .witnesses_mut()
+            .push(cancel_output.rollup_witness.clone());
+        tx_skeleton.inputs_mut().push(input(challenge_cell.clone()));
+        tx_skeleton
+            .witnesses_mut()
+            .push(cancel_output.challenge_witness.clone());
+        tx_skeleton.cell_deps_mut().push(cell_dep(4));
+        tx_skeleton
+            .inputs_mut()
+            .push(cancel_output.verifier_input([9u8; 32].into(), 0));
+        if let Some(verifier_witness) = cancel_output.verifier_witness.clone() {
+            tx_skeleton.witnesses_mut().push(verifier_witness);
+        }
+        let owner_cell = CellInfo {
+            out_point: OutPoint::new_builder()
+                .tx_hash([8u8; 32].pack())
+                .index(3u32.pack())
+                .build(),
+            output: CellOutput::new_builder()
+                .lock(owner_lock.clone())
+                .capacity(1000_00000000u64.pack())
+                .build(),
+            data: Bytes::default(),
+        };
+        tx_skeleton.cell_deps_mut().push(cell_dep(5));
+        tx_skeleton.inputs_mut().push(input(owner_cell));
+        let change_output = CellOutput::new_builder()
+            .lock(owner_lock)
+            .capacity((1000_00000000u64 - cost.tx_fee).pack())
+            .build();
+        tx_skeleton
+            .outputs_mut()
+            .push((change_output, Default::default()));
+
+        let entries = tx_skeleton.signature_entries();
+        let signatures = vec![[1u8; 65]; entries.len()];
+        let tx = tx_skeleton.seal(&entries, signatures).unwrap().transaction;
+        assert_eq!(cost.tx_size, tx.as_slice().len() + 4);
+    }
+    #[test]
+    fn test_calculate_tx_fee() {
+        // shannons per KB, rounded up
+        assert_eq!(calculate_tx_fee(1000, 1000), 1000);
+        assert_eq!(calculate_tx_fee(1001, 1000), 1001);
+        assert_eq!(calculate_tx_fee(1500, 3), 5);
+        assert_eq!(calculate_tx_fee(1, 1), 1);
+        assert_eq!(calculate_tx_fee(0, 1000), 0);
+        assert_eq!(calculate_tx_fee(usize::MAX, u64::MAX), u64::MAX / 1000);
+    }
+}
@@ -1,9 +1,14 @@
+use super::to_input_cell_info;
+use crate::transaction_skeleton::TransactionSkeleton;
+use crate::types::CellInfo;
+
 use ckb_types::prelude::{Builder, Entity};
 use gw_common::H256;
+use gw_config::BlockProducerConfig;
 use gw_generator::{ChallengeContext, RollupContext};
 use gw_types::core::{ScriptHashType, Status};
 use gw_types::packed::{
-    Byte32, CellOutput, ChallengeLockArgs, ChallengeTarget, ChallengeWitness, GlobalState,
+    Byte32, CellDep, CellOutput, ChallengeLockArgs, ChallengeTarget, ChallengeWitness, GlobalState,
     RollupAction, RollupActionUnion, RollupEnterChallenge, Script, WitnessArgs,
 };
 use gw_types::{bytes::Bytes, prelude::Pack};
@@ -98,3 +103,151 @@ impl EnterChallenge {
         }
     }
 }
+
+/// Assemble rollup part of challenge transaction. PoA cells must be filled
+/// before `push_challenge_cell`, they sit between rollup and challenge cell.
+pub fn build_challenge_tx_skeleton(
+    config: &BlockProducerConfig,
+    rollup_cell: &CellInfo,
+    post_global_state: &GlobalState,
+    rollup_witness: WitnessArgs,
+) -> TransactionSkeleton {
+    let mut tx_skeleton = TransactionSkeleton::default();
+
+    // Rollup
+    let rollup_deps = vec![
+        config.rollup_cell_type_dep.clone().into(),
+        config.rollup_config_cell_dep.clone().into(),
+    ];
+    let rollup_output = (rollup_cell.output.clone(), post_global_state.as_bytes());
+
+    tx_skeleton.cell_deps_mut().extend(rollup_deps);
+    tx_skeleton
+        .inputs_mut()
+        .push(to_input_cell_info(rollup_cell.clone()));
+    tx_skeleton.outputs_mut().push(rollup_output);
+    tx_skeleton.witnesses_mut().push(rollup_witness);
+
+    tx_skeleton
+}
+
+/// Push challenge cell after PoA cells, fee isn't filled, `challenger_lock_dep`
+/// is for the fee cells.
+pub fn push_challenge_cell(
+    tx_skeleton: &mut TransactionSkeleton,
+    challenge_cell: (CellOutput, Bytes),
+    challenger_lock_dep: CellDep,
+) {
+    tx_skeleton.outputs_mut().push(challenge_cell);
+    tx_skeleton.cell_deps_mut().push(challenger_lock_dep);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gw_types::packed::{OutPoint, RollupConfig};
+    use gw_types::prelude::Unpack;
+
+    #[test]
+    fn test_build_challenge_tx_skeleton() {
+        let rollup_context = RollupContext {
+            rollup_script_hash: [1u8; 32].into(),
+            rollup_config: RollupConfig::new_builder()
+                .challenge_script_type_hash([2u8; 32].pack())
+                .build(),
+        };
+        let rollup_cell = CellInfo {
+            out_point: OutPoint::new_builder().index(1u32.pack()).build(),
+            output: CellOutput::new_builder()
+                .capacity(1000_00000000u64.pack())
+                .build(),
+            data: GlobalState::default().as_bytes(),
+        };
+        let target = ChallengeTarget::new_builder()
+            .block_hash([3u8; 32].pack())
+            .target_index(1u32.pack())
+            .build();
+        let context = ChallengeContext {
+            target: target.clone(),
+            witness: ChallengeWitness::default(),
+        };
+        let rewards_lock = Script::new_builder().args(vec![4u8; 20].pack()).build();
+        let challenge_output = EnterChallenge::new(
+            GlobalState::default(),
+            &rollup_context,
+            context,
+            rewards_lock,
+        )
+        .build_output();
+        let challenge_cell = challenge_output.challenge_cell.clone();
+
+        let config = BlockProducerConfig::default();
+        let challenger_lock_dep = CellDep::new_builder()
+            .out_point(OutPoint::new_builder().index(5u32.pack()).build())
+            .build();
+        let mut tx_skeleton = build_challenge_tx_skeleton(
+            &config,
+            &rollup_cell,
+            &challenge_output.post_global_state,
+            challenge_output.rollup_witness,
+        );
+
+        // Simulate PoA
+        let poa_dep = CellDep::new_builder()
+            .out_point(OutPoint::new_builder().index(6u32.pack()).build())
+            .build();
+        let poa_cell = (
+            CellOutput::new_builder()
+                .capacity(100_00000000u64.pack())
+                .build(),
+            Bytes::new(),
+        );
+        tx_skeleton.cell_deps_mut().push(poa_dep.clone());
+        tx_skeleton.outputs_mut().push(poa_cell.clone());
+
+        push_challenge_cell(
+            &mut tx_skeleton,
+            challenge_output.challenge_cell,
+            challenger_lock_dep.clone(),
+        );
+
+        let cell_deps = tx_skeleton.cell_deps();
+        assert_eq!(cell_deps.len(), 4);
+        assert_eq!(
+            cell_deps[0].as_slice(),
+            CellDep::from(config.rollup_cell_type_dep).as_slice()
+        );
+        assert_eq!(
+            cell_deps[1].as_slice(),
+            CellDep::from(config.rollup_config_cell_dep).as_slice()
+        );
+        assert_eq!(cell_deps[2].as_slice(), poa_dep.as_slice());
+        assert_eq!(cell_deps[3].as_slice(), challenger_lock_dep.as_slice());
+
+        let inputs = tx_skeleton.inputs();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(
+            inputs[0].input.previous_output().as_slice(),
+            rollup_cell.out_point.as_slice()
+        );
+
+        // Rollup, PoA, challenge
+        let outputs = tx_skeleton.outputs();
+        assert_eq!(outputs.len(), 3);
+        let post_global_state = GlobalState::from_slice(&outputs[0].1).unwrap();
+        let status: u8 = post_global_state.status().into();
+        assert_eq!(status, Status::Halting as u8);
+        assert_eq!(outputs[1].0.as_slice(), poa_cell.0.as_slice());
+        assert_eq!(outputs[2].0.as_slice(), challenge_cell.0.as_slice());
+
+        let challenge_lock = &outputs[2].0.lock();
+        let code_hash: [u8; 32] = challenge_lock.code_hash().unpack();
+        assert_eq!(code_hash, [2u8; 32]);
+        let lock_args =
+            ChallengeLockArgs::from_slice(&challenge_lock.args().raw_data()[32..]).unwrap();
+        assert_eq!(lock_args.target(), target);
+
+        assert_eq!(tx_skeleton.witnesses().len(), 1);
+    }
+}
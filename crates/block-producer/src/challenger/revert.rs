@@ -20,6 +20,8 @@ use gw_types::packed::{
 use gw_types::prelude::Unpack;
 use gw_types::{bytes::Bytes, prelude::Pack};
 
+use std::convert::TryFrom;
+
 pub struct Revert<'a> {
     finality_blocks: u64,
     reward_burn_rate: u8,
@@ -75,6 +77,11 @@ impl<'a> Revert<'a> {
 
         let rewards = Rewards::new(self.stake_cells, self.challenge_cell, self.reward_burn_rate);
         let rewards_output = rewards.build_output(reward_lock, self.burn_lock);
+        check_burned(
+            &rewards_output.burn_cells,
+            self.stake_cells,
+            self.reward_burn_rate,
+        )?;
 
         // Post global state
         let first_reverted_block = {
@@ -139,6 +146,47 @@ impl<'a> Revert<'a> {
     }
 }
 
+/// Sum of capacity in burn cells, saturated at u64::MAX
+pub fn total_burned(burn_cells: &[(CellOutput, Bytes)]) -> u64 {
+    let to_capacity = |(output, _): &(CellOutput, Bytes)| output.capacity().unpack();
+    burn_cells
+        .iter()
+        .map(to_capacity)
+        .fold(0, u64::saturating_add)
+}
+
+/// Stake capacity not rewarded. Rewarded capacity is rounded down, so burned
+/// capacity is rounded up.
+fn expected_burned(stake_cells: &[CellInfo], reward_burn_rate: u8) -> Result<u64> {
+    let to_capacity = |c: &CellInfo| c.output.capacity().unpack() as u128;
+    let total_stake_capacity: u128 = stake_cells.iter().map(to_capacity).sum();
+
+    let burn_rate = 100u128.saturating_sub(reward_burn_rate.into());
+    let burned = total_stake_capacity
+        .saturating_mul(burn_rate)
+        .saturating_add(99)
+        / 100;
+    u64::try_from(burned).map_err(|_| anyhow!("expected burned capacity {} overflow", burned))
+}
+
+/// Ensure burn cells burn exactly the stake capacity not rewarded
+fn check_burned(
+    burn_cells: &[(CellOutput, Bytes)],
+    stake_cells: &[CellInfo],
+    reward_burn_rate: u8,
+) -> Result<()> {
+    let expected_burned = expected_burned(stake_cells, reward_burn_rate)?;
+    let burned = total_burned(burn_cells);
+    if burned != expected_burned {
+        return Err(anyhow!(
+            "burned capacity {} mismatch expected {}",
+            burned,
+            expected_burned
+        ));
+    }
+    Ok(())
+}
+
 struct Rewards {
     receive_capacity: u128,
     burn_capacity: u128,
@@ -214,3 +262,66 @@ impl Rewards {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gw_types::packed::OutPoint;
+
+    fn cell(capacity: u64) -> CellInfo {
+        CellInfo {
+            out_point: OutPoint::default(),
+            output: CellOutput::new_builder().capacity(capacity.pack()).build(),
+            data: Bytes::default(),
+        }
+    }
+
+    #[test]
+    fn test_check_burned() {
+        let burn_lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let challenge_cell = cell(100_00000000);
+        let stake_cells = vec![cell(500_00000000), cell(500_00000000)];
+
+        // 1000 CKB stake, 30% rewarded and 70% burned
+        let rewards = Rewards::new(&stake_cells, &challenge_cell, 30);
+        let output = rewards.build_output(Script::default(), burn_lock.clone());
+        assert_eq!(total_burned(&output.burn_cells), 700_00000000);
+        assert!(check_burned(&output.burn_cells, &stake_cells, 30).is_ok());
+
+        // 101 shannons stake, 30 rewarded and 71 burned
+        let stake_cells = vec![cell(101)];
+        let rewards = Rewards::new(&stake_cells, &challenge_cell, 30);
+        let output = rewards.build_output(Script::default(), burn_lock);
+        assert_eq!(total_burned(&output.burn_cells), 71);
+        assert!(check_burned(&output.burn_cells, &stake_cells, 30).is_ok());
+    }
+
+    #[test]
+    fn test_check_burned_mismatch() {
+        let burn_lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let challenge_cell = cell(100_00000000);
+        let stake_cells = vec![cell(1000_00000000)];
+
+        let rewards = Rewards::new(&stake_cells, &challenge_cell, 30);
+        let mut output = rewards.build_output(Script::default(), burn_lock.clone());
+        let (burn_cell, data) = output.burn_cells.remove(0);
+        let less_burned = burn_cell
+            .as_builder()
+            .capacity(600_00000000u64.pack())
+            .build();
+        output.burn_cells.push((less_burned, data));
+        assert_eq!(total_burned(&output.burn_cells), 600_00000000);
+        assert!(check_burned(&output.burn_cells, &stake_cells, 30).is_err());
+
+        assert!(check_burned(&[], &stake_cells, 30).is_err());
+
+        // Burned capacity must fit u64
+        let stake_cells = vec![cell(u64::MAX), cell(u64::MAX)];
+        let rewards = Rewards::new(&stake_cells, &challenge_cell, 0);
+        let output = rewards.build_output(Script::default(), burn_lock);
+        assert!(output.burn_cells.len() > 1);
+        assert_eq!(total_burned(&output.burn_cells), u64::MAX);
+        assert!(check_burned(&output.burn_cells, &stake_cells, 0).is_err());
+    }
+}
@@ -1,11 +1,13 @@
 use std::{
-    fs::{create_dir_all, write},
+    fs::{create_dir_all, read_dir, remove_file, write},
     path::{Path, PathBuf},
+    time::SystemTime,
 };
 
 use anyhow::{anyhow, Result};
 use ckb_types::prelude::Entity;
 use gw_common::H256;
+use gw_config::DumpFormat;
 use gw_jsonrpc_types::{
     ckb_jsonrpc_types,
     debugger::{ReprMockCellDep, ReprMockInfo, ReprMockInput, ReprMockTransaction},
@@ -18,8 +20,14 @@ use gw_types::{
 
 use crate::rpc_client::RPCClient;
 
+const DUMP_FILE_SUFFIX: &str = "-tx.json";
+const MOLECULE_DUMP_FILE_SUFFIX: &str = "-tx.mol";
+
+/// Dump transaction into `dir` once for each of `formats`
 pub async fn dump_transaction<P: AsRef<Path>>(
     dir: P,
+    max_files: usize,
+    formats: &[DumpFormat],
     rpc_client: &RPCClient,
     tx: Transaction,
 ) -> Result<()> {
@@ -27,31 +35,100 @@ pub async fn dump_transaction<P: AsRef<Path>>(
     create_dir_all(&dir)?;
 
     let tx_hash: ckb_types::H256 = tx.hash().into();
-    log::info!("Build mock transaction {}", tx_hash);
-
-    let mut dump_path = PathBuf::new();
-    dump_path.push(dir);
-    let json_content;
-    match build_mock_transaction(rpc_client, tx.clone()).await {
-        Ok(mock_tx) => {
-            dump_path.push(format!("{}-mock-tx.json", tx_hash));
-            json_content = serde_json::to_string_pretty(&mock_tx)?;
-        }
-        Err(err) => {
-            log::error!(
-                "Failed to build mock transaction {}, error: {}",
-                tx_hash,
-                err
-            );
-            log::error!("Fallback to raw tx...");
-            dump_path.push(format!("{}-raw-tx.json", tx_hash));
-            let json_tx: ckb_jsonrpc_types::Transaction =
-                { ckb_types::packed::Transaction::new_unchecked(tx.as_bytes()).into() };
-            json_content = serde_json::to_string_pretty(&json_tx)?;
+    for format in formats {
+        let (file_name, content) = match format {
+            DumpFormat::Json => {
+                log::info!("Build mock transaction {}", tx_hash);
+                match build_mock_transaction(rpc_client, tx.clone()).await {
+                    Ok(mock_tx) => (
+                        format!("{}-mock-tx.json", tx_hash),
+                        serde_json::to_string_pretty(&mock_tx)?.into_bytes(),
+                    ),
+                    Err(err) => {
+                        log::error!(
+                            "Failed to build mock transaction {}, error: {}",
+                            tx_hash,
+                            err
+                        );
+                        log::error!("Fallback to raw tx...");
+                        (
+                            format!("{}-raw-tx.json", tx_hash),
+                            encode_raw_tx(&tx)?.into_bytes(),
+                        )
+                    }
+                }
+            }
+            DumpFormat::CkbRpcTx => (
+                format!("{}-ckb-cli-tx.json", tx_hash),
+                encode_ckb_cli_tx(&tx)?.into_bytes(),
+            ),
+            DumpFormat::Molecule => (
+                format!("{}{}", tx_hash, MOLECULE_DUMP_FILE_SUFFIX),
+                tx.as_slice().to_vec(),
+            ),
+        };
+
+        let mut dump_path = PathBuf::new();
+        dump_path.push(&dir);
+        dump_path.push(file_name);
+        log::info!("Dump transaction {} to {:?}", tx_hash, dump_path);
+        write(dump_path, content)?;
+    }
+    prune_dump_files(dir, max_files)?;
+    Ok(())
+}
+
+fn to_ckb_json_tx(tx: &Transaction) -> ckb_jsonrpc_types::Transaction {
+    ckb_types::packed::Transaction::new_unchecked(tx.as_bytes()).into()
+}
+
+fn encode_raw_tx(tx: &Transaction) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&to_ckb_json_tx(tx))?)
+}
+
+// Same layout as the transaction file of `ckb-cli tx`, so dumped transactions
+// can be inspected or sent by ckb-cli directly
+fn encode_ckb_cli_tx(tx: &Transaction) -> Result<String> {
+    let ckb_cli_tx = serde_json::json!({
+        "transaction": to_ckb_json_tx(tx),
+        "multisig_configs": {},
+        "signatures": {},
+    });
+    Ok(serde_json::to_string_pretty(&ckb_cli_tx)?)
+}
+
+/// Remove oldest dumped transactions, keep at most `max_files`, 0 means unlimited
+fn prune_dump_files<P: AsRef<Path>>(dir: P, max_files: usize) -> Result<()> {
+    if 0 == max_files {
+        return Ok(());
+    }
+
+    let mut dump_files: Vec<(SystemTime, PathBuf)> = Vec::new();
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_dump_file = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| {
+                name.ends_with(DUMP_FILE_SUFFIX) || name.ends_with(MOLECULE_DUMP_FILE_SUFFIX)
+            })
+            .unwrap_or(false);
+        let metadata = entry.metadata()?;
+        if is_dump_file && metadata.is_file() {
+            dump_files.push((metadata.modified()?, path));
         }
     }
-    log::info!("Dump transaction {} to {:?}", tx_hash, dump_path);
-    write(dump_path, json_content)?;
+    if dump_files.len() <= max_files {
+        return Ok(());
+    }
+
+    dump_files.sort();
+    let prune_count = dump_files.len() - max_files;
+    for (_, path) in dump_files.into_iter().take(prune_count) {
+        log::info!("Remove old dumped transaction {:?}", path);
+        remove_file(path)?;
+    }
     Ok(())
 }
 
@@ -183,3 +260,78 @@ pub async fn build_mock_transaction(
     };
     Ok(mock_tx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gw_types::bytes::Bytes;
+    use std::{thread::sleep, time::Duration};
+
+    #[test]
+    fn test_prune_dump_files() {
+        let dir = tempfile::Builder::new().tempdir().unwrap();
+        let dump_file = |i: usize| dir.path().join(format!("{}{}", i, DUMP_FILE_SUFFIX));
+        for i in 0..5 {
+            write(dump_file(i), "{}").unwrap();
+            // Ensure distinct modified time
+            sleep(Duration::from_millis(10));
+        }
+        let other_file = dir.path().join("notes.txt");
+        write(&other_file, "").unwrap();
+
+        prune_dump_files(dir.path(), 3).unwrap();
+        for i in 0..2 {
+            assert!(!dump_file(i).exists());
+        }
+        for i in 2..5 {
+            assert!(dump_file(i).exists());
+        }
+        assert!(other_file.exists());
+
+        // Unlimited
+        prune_dump_files(dir.path(), 0).unwrap();
+        assert!(dump_file(2).exists());
+    }
+
+    #[test]
+    fn test_encode_dump_formats() {
+        let tx = Transaction::new_builder()
+            .witnesses(vec![Bytes::from(vec![1u8; 8]).pack()].pack())
+            .build();
+        let tx_hash = tx.hash();
+        let to_tx_hash = |json_tx: ckb_jsonrpc_types::Transaction| {
+            let tx: ckb_types::packed::Transaction = json_tx.into();
+            Transaction::new_unchecked(tx.as_bytes()).hash()
+        };
+
+        let raw_tx = encode_raw_tx(&tx).unwrap();
+        let json_tx: ckb_jsonrpc_types::Transaction = serde_json::from_str(&raw_tx).unwrap();
+        assert_eq!(to_tx_hash(json_tx), tx_hash);
+
+        let ckb_cli_tx: serde_json::Value =
+            serde_json::from_str(&encode_ckb_cli_tx(&tx).unwrap()).unwrap();
+        let json_tx: ckb_jsonrpc_types::Transaction =
+            serde_json::from_value(ckb_cli_tx["transaction"].clone()).unwrap();
+        assert_eq!(to_tx_hash(json_tx), tx_hash);
+        assert!(ckb_cli_tx["multisig_configs"].is_object());
+        assert!(ckb_cli_tx["signatures"].is_object());
+
+        let mol_tx = Transaction::from_slice(tx.as_slice()).unwrap();
+        assert_eq!(mol_tx.hash(), tx_hash);
+    }
+
+    #[test]
+    fn test_prune_molecule_dump_files() {
+        let dir = tempfile::Builder::new().tempdir().unwrap();
+        let json_file = dir.path().join(format!("0{}", DUMP_FILE_SUFFIX));
+        write(&json_file, "{}").unwrap();
+        sleep(Duration::from_millis(10));
+        let mol_file = dir.path().join(format!("1{}", MOLECULE_DUMP_FILE_SUFFIX));
+        write(&mol_file, [0u8; 4]).unwrap();
+
+        prune_dump_files(dir.path(), 1).unwrap();
+        assert!(!json_file.exists());
+        assert!(mol_file.exists());
+    }
+}
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use crate::rpc_client::RPCClient;
 use crate::types::InputCellInfo;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use ckb_types::prelude::Entity;
 use gw_config::BlockProducerConfig;
 use gw_generator::RollupContext;
@@ -43,19 +43,30 @@ pub async fn revert(
         .map(|h| h.unpack())
         .collect();
 
-    let revert_custodian_cells = rpc_client
+    let revert_custodians = rpc_client
         .query_custodian_cells_by_block_hashes(&reverted_block_hashes)
         .await?;
-    if revert_custodian_cells.is_empty() {
+    if revert_custodians.cells_info.is_empty() {
         return Ok(None);
     }
+    log::info!(
+        "revert {} custodians, capacity: {}, sudt: {:?}",
+        revert_custodians.cells_info.len(),
+        revert_custodians.value.capacity,
+        revert_custodians
+            .value
+            .sudt
+            .iter()
+            .map(|(hash, amount)| (hex::encode(hash), amount))
+            .collect::<Vec<_>>()
+    );
 
     let mut custodian_inputs = vec![];
     let mut custodian_witness = vec![];
     let mut deposit_outputs = vec![];
 
     let rollup_type_hash = rollup_context.rollup_script_hash.as_slice().iter();
-    for revert_custodian in revert_custodian_cells.into_iter() {
+    for revert_custodian in revert_custodians.cells_info.into_iter() {
         let deposit_lock = {
             let args: Bytes = revert_custodian.output.lock().args().unpack();
             let custodian_lock_args = CustodianLockArgs::from_slice(&args.slice(32..))?;
@@ -75,10 +86,18 @@ pub async fn revert(
                 .build()
         };
 
-        let deposit_output = {
-            let output_builder = revert_custodian.output.clone().as_builder();
-            output_builder.lock(deposit_lock.clone()).build()
-        };
+        let deposit_output = generate_reverted_deposit_output(
+            &revert_custodian.output,
+            deposit_lock.clone(),
+            &revert_custodian.data,
+        )
+        .map_err(|min_capacity| {
+            anyhow!(
+                "{} minimal capacity for reverted deposit from custodian {}",
+                min_capacity,
+                revert_custodian.out_point
+            )
+        })?;
 
         let custodian_input = {
             let input = CellInput::new_builder()
@@ -121,3 +140,79 @@ pub async fn revert(
         witness_args: custodian_witness,
     }))
 }
+
+// Deposit lock replaces custodian lock, verify the output still holds its
+// occupied capacity
+fn generate_reverted_deposit_output(
+    custodian_output: &CellOutput,
+    deposit_lock: Script,
+    data: &Bytes,
+) -> std::result::Result<CellOutput, u64> {
+    let output = custodian_output
+        .clone()
+        .as_builder()
+        .lock(deposit_lock)
+        .build();
+
+    // Same as ckb occupied capacity: capacity, data, and code hash, hash type
+    // and args of lock and type scripts
+    let script_size = |script: &Script| 32 + 1 + script.args().raw_data().len();
+    let size = 8
+        + data.len()
+        + script_size(&output.lock())
+        + output.type_().to_opt().as_ref().map_or(0, script_size);
+    let min_capacity = size as u64 * 100_000_000;
+
+    let capacity: u64 = output.capacity().unpack();
+    if capacity < min_capacity {
+        return Err(min_capacity);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reverted_deposit_output_min_capacity() {
+        let deposit_lock = Script::new_builder()
+            .hash_type(ScriptHashType::Type.into())
+            .args(vec![1u8; 32 + 72].pack())
+            .build();
+        let sudt_type = Script::new_builder().args(vec![2u8; 32].pack()).build();
+        let data = 100u128.pack().as_bytes();
+        // capacity 8, data 16, lock 32 + 1 + 104, type 32 + 1 + 32
+        let min_capacity: u64 = 226 * 100_000_000;
+
+        let custodian_output = CellOutput::new_builder()
+            .capacity((min_capacity - 1).pack())
+            .type_(Some(sudt_type).pack())
+            .build();
+        let err = generate_reverted_deposit_output(&custodian_output, deposit_lock.clone(), &data)
+            .unwrap_err();
+        assert_eq!(err, min_capacity);
+
+        let custodian_output = custodian_output
+            .as_builder()
+            .capacity(min_capacity.pack())
+            .build();
+        let deposit_output =
+            generate_reverted_deposit_output(&custodian_output, deposit_lock.clone(), &data)
+                .unwrap();
+        assert_eq!(deposit_output.lock(), deposit_lock);
+        assert_eq!(
+            deposit_output.type_().as_slice(),
+            custodian_output.type_().as_slice()
+        );
+        let capacity: u64 = deposit_output.capacity().unpack();
+        assert_eq!(capacity, min_capacity);
+
+        // Without type script, capacity 8, data 16, lock 32 + 1 + 104
+        let custodian_output = CellOutput::new_builder().capacity(0u64.pack()).build();
+        let err =
+            generate_reverted_deposit_output(&custodian_output, deposit_lock, &data).unwrap_err();
+        assert_eq!(err, 161 * 100_000_000);
+    }
+}
@@ -9,6 +9,7 @@ pub mod produce_block;
 pub mod rpc_client;
 pub mod runner;
 pub mod stake;
+pub mod submission;
 pub mod test_mode_control;
 pub mod transaction_skeleton;
 pub mod types;
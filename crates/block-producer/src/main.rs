@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::{crate_version, App, Arg, SubCommand};
 use gw_block_producer::runner;
 use gw_config::Config;
@@ -8,15 +8,9 @@ const COMMAND_RUN: &str = "run";
 const COMMAND_EXAMPLE_CONFIG: &str = "generate-example-config";
 const ARG_OUTPUT_PATH: &str = "output-path";
 const ARG_CONFIG: &str = "config";
+const ARG_OVERRIDE_CONFIG: &str = "override-config";
 const ARG_SKIP_CONFIG_CHECK: &str = "skip-config-check";
 
-fn read_config<P: AsRef<Path>>(path: P) -> Result<Config> {
-    let content = fs::read(&path)
-        .with_context(|| format!("read config file from {}", path.as_ref().to_string_lossy()))?;
-    let config = toml::from_slice(&content).with_context(|| "parse config file")?;
-    Ok(config)
-}
-
 fn generate_example_config<P: AsRef<Path>>(path: P) -> Result<()> {
     let mut config = Config::default();
     config.backends.push(Default::default());
@@ -41,6 +35,12 @@ fn run_cli() -> Result<()> {
                         .default_value("./config.toml")
                         .help("The config file path"),
                 )
+                .arg(
+                    Arg::with_name(ARG_OVERRIDE_CONFIG)
+                        .long(ARG_OVERRIDE_CONFIG)
+                        .takes_value(true)
+                        .help("The config file overriding fields of the base config"),
+                )
                 .arg(
                     Arg::with_name(ARG_SKIP_CONFIG_CHECK)
                         .long(ARG_SKIP_CONFIG_CHECK)
@@ -67,7 +67,7 @@ fn run_cli() -> Result<()> {
     match matches.subcommand() {
         (COMMAND_RUN, Some(m)) => {
             let config_path = m.value_of(ARG_CONFIG).unwrap();
-            let config = read_config(&config_path)?;
+            let config = Config::load_layered(config_path, m.value_of(ARG_OVERRIDE_CONFIG))?;
             runner::run(config, m.is_present(ARG_SKIP_CONFIG_CHECK))?;
         }
         (COMMAND_EXAMPLE_CONFIG, Some(m)) => {
@@ -77,7 +77,7 @@ fn run_cli() -> Result<()> {
         _ => {
             // default command: start a Godwoken node
             let config_path = "./config.toml";
-            let config = read_config(&config_path)?;
+            let config = Config::load_layered(config_path, None)?;
             runner::run(config, false)?;
         }
     };
@@ -86,6 +86,9 @@ fn run_cli() -> Result<()> {
 
 /// Godwoken entry
 fn main() {
+    // No tracing subscriber is installed, chain and generator spans are
+    // emitted as untimed debug and trace log records through the tracing
+    // `log` feature, so they stay out of the default info logs
     env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
     run_cli().expect("run cli");
 }
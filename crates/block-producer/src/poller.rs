@@ -4,25 +4,143 @@ use crate::{
 };
 use crate::{types::ChainEvent, utils::to_result};
 use anyhow::{anyhow, Result};
+use async_channel::Sender;
 use async_jsonrpc_client::{Params as ClientParams, Transport};
+use async_trait::async_trait;
 use ckb_fixed_hash::H256;
 use gw_chain::chain::{Chain, ChallengeCell, L1Action, L1ActionContext, SyncParam};
 use gw_generator::RollupContext;
-use gw_jsonrpc_types::ckb_jsonrpc_types::{BlockNumber, HeaderView, TransactionWithStatus, Uint32};
+use gw_jsonrpc_types::{
+    ckb_jsonrpc_types::{BlockNumber, HeaderView, TransactionWithStatus, Uint32},
+    h256::to_h256,
+};
 use gw_types::{
     bytes::Bytes,
     core::ScriptHashType,
     packed::{
-        CellInput, CellOutput, ChallengeLockArgs, ChallengeLockArgsReader, DepositLockArgs,
-        DepositRequest, L2BlockCommittedInfo, OutPoint, RollupAction, RollupActionUnion, Script,
-        Transaction, WitnessArgs, WitnessArgsReader,
+        Block, CellInput, CellOutput, ChallengeLockArgs, ChallengeLockArgsReader, DepositLockArgs,
+        DepositRequest, L2BlockCommittedInfo, NumberHash, OutPoint, RollupAction,
+        RollupActionUnion, Script, Transaction, WitnessArgs, WitnessArgsReader,
     },
     prelude::*,
 };
 use gw_web3_indexer::indexer::Web3Indexer;
 use parking_lot::Mutex;
 use serde_json::json;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+
+/// Adaptive layer1 poll interval, halves down to a quarter of base interval
+/// while syncing blocks, and doubles up to four times of it at tip
+pub struct PollInterval {
+    min: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl PollInterval {
+    pub fn new(base: Duration) -> Self {
+        PollInterval {
+            min: base / 4,
+            max: base * 4,
+            current: base,
+        }
+    }
+
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+
+    /// New layer1 block found, the node is lagging
+    pub fn on_lagging(&mut self) {
+        self.current = std::cmp::max(self.current / 2, self.min);
+    }
+
+    /// No new layer1 block, the node is at tip
+    pub fn on_caught_up(&mut self) {
+        self.current = std::cmp::min(self.current * 2, self.max);
+    }
+}
+
+#[async_trait]
+pub trait Layer1BlockSource {
+    async fn get_tip(&self) -> Result<NumberHash>;
+    async fn get_block_by_number(&self, number: u64) -> Result<Option<Block>>;
+}
+
+#[async_trait]
+impl Layer1BlockSource for RPCClient {
+    async fn get_tip(&self) -> Result<NumberHash> {
+        RPCClient::get_tip(self).await
+    }
+
+    async fn get_block_by_number(&self, number: u64) -> Result<Option<Block>> {
+        RPCClient::get_block_by_number(self, number).await
+    }
+}
+
+/// Poll layer1 blocks from tip into `event_sender`. Polling waits while the
+/// bounded channel is full, so it never runs ahead of block application by
+/// more than the channel capacity. Return once the receiver is dropped.
+pub async fn poll_layer1_events<S: Layer1BlockSource>(
+    source: &S,
+    poll_interval: Duration,
+    event_sender: Sender<ChainEvent>,
+) -> Result<()> {
+    let mut poll_interval = PollInterval::new(poll_interval);
+    // get tip
+    let (mut tip_number, mut tip_hash) = {
+        let tip = source.get_tip().await?;
+        let tip_number: u64 = tip.number().unpack();
+        let tip_hash: [u8; 32] = tip.block_hash().unpack();
+        (tip_number, tip_hash)
+    };
+    loop {
+        if let Some(block) = source.get_block_by_number(tip_number + 1).await? {
+            let raw_header = block.header().raw();
+            let event = if raw_header.parent_hash().as_slice() == tip_hash {
+                // received new layer1 block
+                log::info!(
+                    "received new layer1 block {}, {}",
+                    tip_number,
+                    hex::encode(tip_hash)
+                );
+                ChainEvent::NewBlock {
+                    block: block.clone(),
+                }
+            } else {
+                // layer1 reverted
+                log::info!(
+                    "layer1 reverted {}, {:?}",
+                    tip_number,
+                    hex::encode(tip_hash)
+                );
+                ChainEvent::Reverted {
+                    old_tip: NumberHash::new_builder()
+                        .number(tip_number.pack())
+                        .block_hash(tip_hash.pack())
+                        .build(),
+                    new_block: block.clone(),
+                }
+            };
+            if event_sender.send(event).await.is_err() {
+                log::info!("layer1 event receiver closed, stop polling");
+                return Ok(());
+            }
+            // update tip
+            tip_number = raw_header.number().unpack();
+            tip_hash = block.header().hash();
+            poll_interval.on_lagging();
+        } else {
+            log::debug!(
+                "Not found layer1 block #{} sleep {}ms then retry",
+                tip_number + 1,
+                poll_interval.current().as_millis()
+            );
+            async_std::task::sleep(poll_interval.current()).await;
+            poll_interval.on_caught_up();
+        }
+    }
+}
 
 pub struct ChainUpdater {
     chain: Arc<Mutex<Chain>>,
@@ -153,7 +271,7 @@ impl ChainUpdater {
             header_view.ok_or_else(|| anyhow::anyhow!("Cannot locate block: {:x}", block_hash))?;
         let l2block_committed_info = L2BlockCommittedInfo::new_builder()
             .number(header_view.inner.number.value().pack())
-            .block_hash(block_hash.0.pack())
+            .block_hash(to_h256(block_hash).pack())
             .transaction_hash(tx_hash.pack())
             .build();
 
@@ -378,3 +496,120 @@ fn try_parse_deposit_request(
         .build();
     Some(deposit_request)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{poll_layer1_events, Layer1BlockSource, PollInterval};
+    use crate::types::ChainEvent;
+
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use futures::future::{select, Either};
+    use gw_types::{
+        packed::{Block, Header, NumberHash, RawHeader},
+        prelude::*,
+    };
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+
+    // Chain of layer1 blocks, more than the poller ever catches up
+    struct MockBlockSource {
+        blocks: Vec<Block>,
+        requests: AtomicU64,
+    }
+
+    impl MockBlockSource {
+        fn new(count: u64) -> Self {
+            let mut blocks: Vec<Block> = Vec::new();
+            for number in 0..count {
+                let parent_hash = blocks
+                    .last()
+                    .map(|block| block.header().hash())
+                    .unwrap_or_default();
+                let raw = RawHeader::new_builder()
+                    .number(number.pack())
+                    .parent_hash(parent_hash.pack())
+                    .build();
+                let header = Header::new_builder().raw(raw).build();
+                blocks.push(Block::new_builder().header(header).build());
+            }
+            MockBlockSource {
+                blocks,
+                requests: AtomicU64::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Layer1BlockSource for MockBlockSource {
+        async fn get_tip(&self) -> Result<NumberHash> {
+            let tip = &self.blocks[0];
+            Ok(NumberHash::new_builder()
+                .number(0u64.pack())
+                .block_hash(tip.header().hash().pack())
+                .build())
+        }
+
+        async fn get_block_by_number(&self, number: u64) -> Result<Option<Block>> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            Ok(self.blocks.get(number as usize).cloned())
+        }
+    }
+
+    #[test]
+    fn test_adaptive_poll_interval() {
+        let base = Duration::from_secs(4);
+        let mut interval = PollInterval::new(base);
+        assert_eq!(interval.current(), base);
+
+        interval.on_lagging();
+        assert_eq!(interval.current(), Duration::from_secs(2));
+        interval.on_lagging();
+        interval.on_lagging();
+        assert_eq!(interval.current(), Duration::from_secs(1));
+
+        interval.on_caught_up();
+        assert_eq!(interval.current(), Duration::from_secs(2));
+        for _ in 0..10 {
+            interval.on_caught_up();
+        }
+        assert_eq!(interval.current(), Duration::from_secs(16));
+    }
+
+    #[test]
+    fn test_poll_layer1_events_backpressure() {
+        let capacity = 2;
+        let source = MockBlockSource::new(100);
+        let (sender, receiver) = async_channel::bounded(capacity);
+
+        smol::block_on(async {
+            let poller = poll_layer1_events(&source, Duration::from_millis(10), sender);
+            futures::pin_mut!(poller);
+            let wait = || Box::pin(async_std::task::sleep(Duration::from_millis(100)));
+
+            // Nothing applied, poller waits once channel is full
+            let polled = select(poller.as_mut(), wait()).await;
+            assert!(matches!(polled, Either::Right(_)));
+            assert_eq!(receiver.len(), capacity);
+            // The block waiting to be sent
+            assert_eq!(source.requests.load(Ordering::SeqCst), capacity as u64 + 1);
+
+            // Slow applier handles one event, poller sends only one more
+            match receiver.recv().await.unwrap() {
+                ChainEvent::NewBlock { block } => {
+                    let number: u64 = block.header().raw().number().unpack();
+                    assert_eq!(number, 1);
+                }
+                event => panic!("unexpected event {:?}", event),
+            }
+            let polled = select(poller.as_mut(), wait()).await;
+            assert!(matches!(polled, Either::Right(_)));
+            assert_eq!(receiver.len(), capacity);
+            assert_eq!(source.requests.load(Ordering::SeqCst), capacity as u64 + 2);
+
+            // Stop polling once the applier is gone
+            drop(receiver);
+            poller.await.unwrap();
+        });
+    }
+}
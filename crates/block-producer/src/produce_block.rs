@@ -83,6 +83,7 @@ pub fn produce_block(param: ProduceBlockParam<'_>) -> Result<ProduceBlockResult>
             CheckPoint::from_block_hash(&db, tip_block_hash, SubState::Block)?,
             StateDBMode::ReadOnly,
         )?
+        .with_discardable_writes()
     };
     let mut state = state_db.account_state_tree()?;
     // track state changes
@@ -1,13 +1,19 @@
 #![allow(clippy::clippy::mutable_key_type)]
 
 use crate::indexer_types::{Cell, Order, Pagination, ScriptType, SearchKey, SearchKeyFilter};
-use crate::types::{CellInfo, TxStatus};
+use crate::types::{CellInfo, CellValueAggregator, TxStatus};
+use crate::utils::{fetch_capacity_and_sudt_value, to_result};
 use anyhow::{anyhow, Result};
-use async_jsonrpc_client::{HttpClient, Output, Params as ClientParams, Transport};
+use async_jsonrpc_client::{HttpClient, Params as ClientParams, Transport};
+use async_trait::async_trait;
 use ckb_types::prelude::Entity;
 use gw_common::{CKB_SUDT_SCRIPT_ARGS, H256};
+use gw_config::{DepositOrder, NodeMode, PaymentCellsConfig};
 use gw_generator::RollupContext;
-use gw_jsonrpc_types::ckb_jsonrpc_types::{self, BlockNumber, Uint32};
+use gw_jsonrpc_types::{
+    ckb_jsonrpc_types::{self, BlockNumber, JsonBytes, Uint32},
+    h256::{to_h256, to_jsonh256},
+};
 use gw_types::{
     bytes::Bytes,
     core::ScriptHashType,
@@ -18,12 +24,14 @@ use gw_types::{
     },
     prelude::*,
 };
-use serde::de::DeserializeOwned;
-use serde_json::{from_value, json};
+use parking_lot::Mutex;
+use serde_json::json;
 
 use std::{
     collections::{HashMap, HashSet},
-    time::Duration,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 const DEFAULT_QUERY_LIMIT: usize = 1000;
@@ -42,25 +50,201 @@ lazy_static::lazy_static! {
 pub struct DepositInfo {
     pub request: DepositRequest,
     pub cell: CellInfo,
+    /// Since of deposit lock after which the owner can cancel it
+    pub cancel_timeout: u64,
 }
 
 type JsonH256 = ckb_fixed_hash::H256;
 
-fn to_h256(v: JsonH256) -> H256 {
-    let h: [u8; 32] = v.into();
-    h.into()
+fn collect_custodian_cells_by_block_hashes(
+    collected: &mut CollectedCustodianCells,
+    cells: impl Iterator<Item = CellInfo>,
+    block_hashes: &HashSet<[u8; 32]>,
+) {
+    for cell in cells {
+        let args: Bytes = cell.output.lock().args().unpack();
+        if args.len() < 32 {
+            continue;
+        }
+        let custodian_lock_args = match CustodianLockArgsReader::verify(&args[32..], false) {
+            Ok(()) => CustodianLockArgs::new_unchecked(args.slice(32..)),
+            Err(_) => continue,
+        };
+
+        let deposit_block_hash: [u8; 32] = custodian_lock_args.deposit_block_hash().unpack();
+        if !block_hashes.contains(&deposit_block_hash) {
+            continue;
+        }
+
+        if cell.output.type_().is_some() && cell.data.len() < 16 {
+            log::error!("invalid sudt amount, out_point: {}", cell.out_point);
+        }
+        collected.push(cell);
+    }
+}
+
+// collect custodian cells of `block_hashes` through all pages of cells
+// under `custodian_lock`
+async fn query_custodian_cells_by_block_hashes(
+    indexer: &(impl IndexerCellsQuery + Sync),
+    custodian_lock: Script,
+    block_hashes: &HashSet<[u8; 32]>,
+) -> Result<CollectedCustodianCells> {
+    let search_key = SearchKey {
+        script: ckb_types::packed::Script::new_unchecked(custodian_lock.as_bytes()).into(),
+        script_type: ScriptType::Lock,
+        filter: None,
+    };
+    let order = Order::Desc;
+    let limit = Uint32::from(DEFAULT_QUERY_LIMIT as u32);
+
+    let mut collected = CollectedCustodianCells::default();
+    let mut cursor = None;
+
+    loop {
+        let cells = indexer
+            .get_cells(&search_key, &order, limit, cursor.take())
+            .await?;
+
+        if cells.last_cursor.is_empty() {
+            break;
+        }
+        cursor = Some(cells.last_cursor);
+
+        let cells = cells.objects.into_iter().map(to_cell_info);
+        collect_custodian_cells_by_block_hashes(&mut collected, cells, block_hashes);
+    }
+
+    Ok(collected)
+}
+
+/// Paged `get_cells` query of the indexer
+#[async_trait]
+pub trait IndexerCellsQuery {
+    async fn get_cells(
+        &self,
+        search_key: &SearchKey,
+        order: &Order,
+        limit: Uint32,
+        cursor: Option<JsonBytes>,
+    ) -> Result<Pagination<Cell>>;
+}
+
+#[async_trait]
+impl IndexerCellsQuery for HttpClient {
+    async fn get_cells(
+        &self,
+        search_key: &SearchKey,
+        order: &Order,
+        limit: Uint32,
+        cursor: Option<JsonBytes>,
+    ) -> Result<Pagination<Cell>> {
+        to_result(
+            self.request(
+                "get_cells",
+                Some(ClientParams::Array(vec![
+                    json!(search_key),
+                    json!(order),
+                    json!(limit),
+                    json!(cursor),
+                ])),
+            )
+            .await?,
+        )
+    }
 }
 
-fn to_jsonh256(v: H256) -> JsonH256 {
-    let h: [u8; 32] = v.into();
-    h.into()
+struct PaymentCellsQuery<TipNumber, IsExcluded> {
+    lock: Script,
+    required_capacity: u64,
+    /// Only awaited if the cached cells aren't enough
+    tip_number: TipNumber,
+    is_excluded: IsExcluded,
 }
 
-fn to_result<T: DeserializeOwned>(output: Output) -> anyhow::Result<T> {
-    match output {
-        Output::Success(success) => Ok(from_value(success.result)?),
-        Output::Failure(failure) => Err(anyhow::anyhow!("JSONRPC error: {}", failure.error)),
+async fn query_payment_cells_with_cache(
+    indexer: &(impl IndexerCellsQuery + Sync),
+    cache: &Mutex<PaymentCellsCache>,
+    config: &PaymentCellsConfig,
+    query: PaymentCellsQuery<impl Future<Output = Result<u64>>, impl Fn(&OutPoint) -> bool>,
+    now: Instant,
+) -> Result<Vec<CellInfo>> {
+    let PaymentCellsQuery {
+        lock,
+        required_capacity,
+        tip_number,
+        is_excluded,
+    } = query;
+
+    let cached_cells = {
+        let cache = cache.lock();
+        cache.get(&lock, now).map(|cells| cells.to_vec())
+    };
+    // Cached cells are already mature
+    if let Some(cached_cells) = cached_cells {
+        let cells = cached_cells
+            .into_iter()
+            .filter(|cell| !is_excluded(&cell.out_point));
+        let mut collected_cells = Vec::new();
+        let mut collected_capacity = 0u64;
+        select_payment_cells(
+            cells,
+            config,
+            required_capacity,
+            &mut collected_cells,
+            &mut collected_capacity,
+        );
+        if collected_capacity >= required_capacity || is_max_cells(config, &collected_cells) {
+            return Ok(collected_cells);
+        }
+    }
+
+    let search_key = SearchKey {
+        script: {
+            let lock = ckb_types::packed::Script::new_unchecked(lock.as_bytes());
+            lock.into()
+        },
+        script_type: ScriptType::Lock,
+        filter: None,
+    };
+    let order = Order::Desc;
+    let limit = Uint32::from(DEFAULT_QUERY_LIMIT as u32);
+    let maturity_blocks = config.maturity_blocks;
+    let tip_number = tip_number.await?;
+
+    let mut collected_cells = Vec::new();
+    let mut collected_capacity = 0u64;
+    let mut queried_cells = Vec::new();
+    let mut cursor = None;
+    while collected_capacity < required_capacity && !is_max_cells(config, &collected_cells) {
+        let cells = indexer
+            .get_cells(&search_key, &order, limit, cursor.take())
+            .await?;
+
+        if cells.last_cursor.is_empty() {
+            return Err(anyhow!("no enough payment cells"));
+        }
+        cursor = Some(cells.last_cursor);
+
+        let cells = cells
+            .objects
+            .into_iter()
+            .filter_map(|cell| to_payment_cell(cell, tip_number, maturity_blocks, &is_excluded));
+        let cells: Vec<_> = cells.collect();
+        queried_cells.extend(cells.iter().cloned());
+
+        select_payment_cells(
+            cells.into_iter(),
+            config,
+            required_capacity,
+            &mut collected_cells,
+            &mut collected_capacity,
+        );
     }
+
+    let mut cache = cache.lock();
+    cache.insert(&lock, queried_cells, now);
+    Ok(collected_cells)
 }
 
 fn to_cell_info(cell: Cell) -> CellInfo {
@@ -81,6 +265,46 @@ fn to_cell_info(cell: Cell) -> CellInfo {
     }
 }
 
+/// Sort deposits by `order`, the sort is stable so deposits with equal keys
+/// keep index order
+pub fn sort_deposits(deposits: &mut [DepositInfo], order: DepositOrder) {
+    match order {
+        DepositOrder::Index => (),
+        DepositOrder::Capacity => deposits.sort_by_key(|deposit| {
+            let capacity: u64 = deposit.cell.output.capacity().unpack();
+            std::cmp::Reverse(capacity)
+        }),
+    }
+}
+
+fn parse_deposit_info(cell: CellInfo) -> Option<DepositInfo> {
+    let args: Bytes = cell.output.lock().args().unpack();
+    if args.len() < 32 {
+        log::debug!("invalid deposit cell args: \n{:#x}", args);
+        return None;
+    }
+    let deposit_lock_args = match DepositLockArgsReader::verify(&args[32..], false) {
+        Ok(()) => DepositLockArgs::new_unchecked(args.slice(32..)),
+        Err(_) => {
+            log::debug!("invalid deposit cell args: \n{:#x}", args);
+            return None;
+        }
+    };
+    let request = match parse_deposit_request(&cell.output, &cell.data, &deposit_lock_args) {
+        Some(r) => r,
+        None => {
+            log::debug!("invalid deposit cell: \n{:?}", cell);
+            return None;
+        }
+    };
+
+    Some(DepositInfo {
+        cell,
+        request,
+        cancel_timeout: deposit_lock_args.cancel_timeout().unpack(),
+    })
+}
+
 fn parse_deposit_request(
     output: &CellOutput,
     output_data: &Bytes,
@@ -112,6 +336,136 @@ fn parse_deposit_request(
     Some(request)
 }
 
+// skip cells with data or type, excluded cells and cells committed within
+// `maturity_blocks` of tip
+fn to_payment_cell(
+    cell: Cell,
+    tip_number: u64,
+    maturity_blocks: u64,
+    is_excluded: impl Fn(&OutPoint) -> bool,
+) -> Option<CellInfo> {
+    let out_point = {
+        let out_point: ckb_types::packed::OutPoint = cell.out_point.into();
+        OutPoint::new_unchecked(out_point.as_bytes())
+    };
+    if !cell.output_data.is_empty() || cell.output.type_.is_some() || is_excluded(&out_point) {
+        return None;
+    }
+    if tip_number.saturating_sub(cell.block_number.value()) < maturity_blocks {
+        return None;
+    }
+    let output = {
+        let output: ckb_types::packed::CellOutput = cell.output.into();
+        CellOutput::new_unchecked(output.as_bytes())
+    };
+    let data = cell.output_data.into_bytes();
+    Some(CellInfo {
+        out_point,
+        output,
+        data,
+    })
+}
+
+fn to_withdrawal_cell(
+    cell: Cell,
+    block_hashes: &HashSet<[u8; 32]>,
+    owner_lock_hash: Option<&[u8; 32]>,
+) -> Option<CellInfo> {
+    let args = cell.output.lock.args.clone().into_bytes();
+    if args.len() < 32 {
+        return None;
+    }
+    let withdrawal_lock_args = match WithdrawalLockArgsReader::verify(&args[32..], false) {
+        Ok(()) => WithdrawalLockArgs::new_unchecked(args.slice(32..)),
+        Err(_) => return None,
+    };
+
+    let withdrawal_block_hash: [u8; 32] = withdrawal_lock_args.withdrawal_block_hash().unpack();
+    if !block_hashes.contains(&withdrawal_block_hash) {
+        return None;
+    }
+    if let Some(owner_lock_hash) = owner_lock_hash {
+        let withdrawal_owner_lock_hash: [u8; 32] = withdrawal_lock_args.owner_lock_hash().unpack();
+        if &withdrawal_owner_lock_hash != owner_lock_hash {
+            return None;
+        }
+    }
+
+    let out_point = {
+        let out_point: ckb_types::packed::OutPoint = cell.out_point.into();
+        OutPoint::new_unchecked(out_point.as_bytes())
+    };
+    let output = {
+        let output: ckb_types::packed::CellOutput = cell.output.into();
+        CellOutput::new_unchecked(output.as_bytes())
+    };
+    Some(CellInfo {
+        out_point,
+        output,
+        data: cell.output_data.into_bytes(),
+    })
+}
+
+// collect least cells, skip dust cells, stop at `max_cells` even if the
+// collected capacity is less than required
+fn select_payment_cells(
+    cells: impl Iterator<Item = CellInfo>,
+    config: &PaymentCellsConfig,
+    required_capacity: u64,
+    collected_cells: &mut Vec<CellInfo>,
+    collected_capacity: &mut u64,
+) {
+    let cells = cells.filter(|cell| cell.output.capacity().unpack() >= config.min_capacity);
+    for cell in cells {
+        if *collected_capacity >= required_capacity || is_max_cells(config, collected_cells) {
+            break;
+        }
+        *collected_capacity = collected_capacity.saturating_add(cell.output.capacity().unpack());
+        collected_cells.push(cell);
+    }
+}
+
+fn is_max_cells(config: &PaymentCellsConfig, collected_cells: &[CellInfo]) -> bool {
+    Some(collected_cells.len()) == config.max_cells
+}
+
+/// Payment cells of recent queries keyed on lock script. A cell set is reused
+/// until `ttl` expires or one of its cells is consumed by a sent transaction.
+pub struct PaymentCellsCache {
+    ttl: Duration,
+    entries: HashMap<Bytes, (Instant, Vec<CellInfo>)>,
+}
+
+impl PaymentCellsCache {
+    pub fn new(ttl: Duration) -> Self {
+        PaymentCellsCache {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, lock: &Script, now: Instant) -> Option<&[CellInfo]> {
+        let (cached_at, cells) = self.entries.get(&lock.as_bytes())?;
+        if now.saturating_duration_since(*cached_at) >= self.ttl {
+            return None;
+        }
+        Some(cells)
+    }
+
+    pub fn insert(&mut self, lock: &Script, cells: Vec<CellInfo>, now: Instant) {
+        if self.ttl == Duration::from_secs(0) {
+            return;
+        }
+        self.entries.insert(lock.as_bytes(), (now, cells));
+    }
+
+    /// Drop cell sets containing any of the consumed cells
+    pub fn invalidate(&mut self, consumed: &HashSet<OutPoint>) {
+        self.entries
+            .retain(|_, (_, cells)| !cells.iter().any(|cell| consumed.contains(&cell.out_point)));
+    }
+}
+
 #[derive(Debug)]
 pub struct WithdrawalsAmount {
     pub capacity: u128,
@@ -130,17 +484,33 @@ impl Default for WithdrawalsAmount {
 #[derive(Debug)]
 pub struct CollectedCustodianCells {
     pub cells_info: Vec<CellInfo>,
-    pub capacity: u128,
-    pub sudt: HashMap<[u8; 32], (u128, Script)>,
+    /// Total capacity and sudt amounts of `cells_info`
+    pub value: CellValueAggregator,
+    /// Sudt type scripts by type script hash
+    pub sudt_scripts: HashMap<[u8; 32], Script>,
 }
 
 impl Default for CollectedCustodianCells {
     fn default() -> Self {
         CollectedCustodianCells {
             cells_info: Default::default(),
-            capacity: 0,
-            sudt: Default::default(),
+            value: Default::default(),
+            sudt_scripts: Default::default(),
+        }
+    }
+}
+
+impl CollectedCustodianCells {
+    fn push(&mut self, cell: CellInfo) {
+        let value = fetch_capacity_and_sudt_value(&cell);
+        self.value.add(&value);
+        if let Some(type_script) = cell.output.type_().to_opt() {
+            if CKB_SUDT_SCRIPT_ARGS != value.sudt_script_hash {
+                self.sudt_scripts
+                    .insert(value.sudt_script_hash, type_script);
+            }
         }
+        self.cells_info.push(cell);
     }
 }
 
@@ -150,9 +520,25 @@ pub struct RPCClient {
     pub ckb_client: HttpClient,
     pub rollup_type_script: ckb_types::packed::Script,
     pub rollup_context: RollupContext,
+    pub payment_cells_config: PaymentCellsConfig,
+    pub payment_cells_cache: Arc<Mutex<PaymentCellsCache>>,
+    /// Cells held by pending work of other subsystems, never selected as
+    /// payment cells
+    pub reserved_payment_cells: Arc<Mutex<HashSet<OutPoint>>>,
+    /// Transactions are never broadcast in `NodeMode::ReadOnly`
+    pub node_mode: NodeMode,
 }
 
 impl RPCClient {
+    pub fn reserve_payment_cells(&self, out_points: impl IntoIterator<Item = OutPoint>) {
+        self.reserved_payment_cells.lock().extend(out_points);
+    }
+
+    pub fn release_payment_cells(&self, out_points: &HashSet<OutPoint>) {
+        let mut reserved = self.reserved_payment_cells.lock();
+        reserved.retain(|out_point| !out_points.contains(out_point));
+    }
+
     /// query lived rollup cell
     pub async fn query_rollup_cell(&self) -> Result<Option<CellInfo>> {
         let search_key = SearchKey {
@@ -314,82 +700,42 @@ impl RPCClient {
     }
 
     /// query payment cells, the returned cells should provide at least required_capacity fee,
-    /// and the remained fees should be enough to cover a charge cell
+    /// and the remained fees should be enough to cover a charge cell. Cells capped by
+    /// `max_cells` are returned even if they provide less.
     pub async fn query_payment_cells(
         &self,
         lock: Script,
         required_capacity: u64,
         taken_outpoints: &HashSet<OutPoint>,
     ) -> Result<Vec<CellInfo>> {
-        let search_key = SearchKey {
-            script: {
-                let lock = ckb_types::packed::Script::new_unchecked(lock.as_bytes());
-                lock.into()
-            },
-            script_type: ScriptType::Lock,
-            filter: None,
+        let reserved_outpoints = self.reserved_payment_cells.lock().clone();
+        let is_excluded = |out_point: &OutPoint| {
+            taken_outpoints.contains(out_point) || reserved_outpoints.contains(out_point)
         };
-        let order = Order::Desc;
-        let limit = Uint32::from(DEFAULT_QUERY_LIMIT as u32);
-
-        let mut collected_cells = Vec::new();
-        let mut collected_capacity = 0u64;
-        let mut cursor = None;
-        while collected_capacity < required_capacity {
-            let cells: Pagination<Cell> = to_result(
-                self.indexer_client
-                    .request(
-                        "get_cells",
-                        Some(ClientParams::Array(vec![
-                            json!(search_key),
-                            json!(order),
-                            json!(limit),
-                            json!(cursor),
-                        ])),
-                    )
-                    .await?,
-            )?;
-
-            if cells.last_cursor.is_empty() {
-                return Err(anyhow!("no enough payment cells"));
-            }
-            cursor = Some(cells.last_cursor);
-
-            let cells = cells.objects.into_iter().filter_map(|cell| {
-                let out_point = {
-                    let out_point: ckb_types::packed::OutPoint = cell.out_point.into();
-                    OutPoint::new_unchecked(out_point.as_bytes())
-                };
-                // delete cells with data & type
-                if !cell.output_data.is_empty()
-                    || cell.output.type_.is_some()
-                    || taken_outpoints.contains(&out_point)
-                {
-                    return None;
-                }
-                let output = {
-                    let output: ckb_types::packed::CellOutput = cell.output.into();
-                    CellOutput::new_unchecked(output.as_bytes())
-                };
-                let data = cell.output_data.into_bytes();
-                Some(CellInfo {
-                    out_point,
-                    output,
-                    data,
-                })
-            });
+        let maturity_blocks = self.payment_cells_config.maturity_blocks;
+
+        query_payment_cells_with_cache(
+            &self.indexer_client,
+            &self.payment_cells_cache,
+            &self.payment_cells_config,
+            PaymentCellsQuery {
+                lock,
+                required_capacity,
+                tip_number: self.get_maturity_tip_number(maturity_blocks),
+                is_excluded,
+            },
+            Instant::now(),
+        )
+        .await
+    }
 
-            // collect least cells
-            for cell in cells {
-                collected_capacity =
-                    collected_capacity.saturating_add(cell.output.capacity().unpack());
-                collected_cells.push(cell);
-                if collected_capacity >= required_capacity {
-                    break;
-                }
-            }
+    // tip number to check payment cells maturity against, no query if no
+    // maturity is required
+    async fn get_maturity_tip_number(&self, maturity_blocks: u64) -> Result<u64> {
+        if maturity_blocks == 0 {
+            return Ok(0);
         }
-        Ok(collected_cells)
+        Ok(self.get_tip().await?.number().unpack())
     }
 
     pub async fn get_cell(&self, out_point: OutPoint) -> Result<Option<CellInfo>> {
@@ -536,27 +882,7 @@ impl RPCClient {
             }
         });
 
-        for cell in cells {
-            let args: Bytes = cell.output.lock().args().unpack();
-            let deposit_lock_args = match DepositLockArgsReader::verify(&args[32..], false) {
-                Ok(()) => DepositLockArgs::new_unchecked(args.slice(32..)),
-                Err(_) => {
-                    log::debug!("invalid deposit cell args: \n{:#x}", args);
-                    continue;
-                }
-            };
-            let request = match parse_deposit_request(&cell.output, &cell.data, &deposit_lock_args)
-            {
-                Some(r) => r,
-                None => {
-                    log::debug!("invalid deposit cell: \n{:?}", cell);
-                    continue;
-                }
-            };
-
-            let info = DepositInfo { cell, request };
-            deposit_infos.push(info);
-        }
+        deposit_infos.extend(cells.filter_map(parse_deposit_info));
 
         Ok(deposit_infos)
     }
@@ -724,10 +1050,12 @@ impl RPCClient {
         Ok(collected_cells)
     }
 
+    /// Query all custodian cells of deposits in reverted blocks, along with
+    /// their total capacity and sudt amount
     pub async fn query_custodian_cells_by_block_hashes(
         &self,
         block_hashes: &HashSet<[u8; 32]>,
-    ) -> Result<Vec<CellInfo>> {
+    ) -> Result<CollectedCustodianCells> {
         let rollup_context = &self.rollup_context;
 
         let custodian_lock = Script::new_builder()
@@ -736,56 +1064,8 @@ impl RPCClient {
             .args(rollup_context.rollup_script_hash.as_slice().pack())
             .build();
 
-        let search_key = SearchKey {
-            script: ckb_types::packed::Script::new_unchecked(custodian_lock.as_bytes()).into(),
-            script_type: ScriptType::Lock,
-            filter: None,
-        };
-        let order = Order::Desc;
-        let limit = Uint32::from(DEFAULT_QUERY_LIMIT as u32);
-
-        let mut collected = vec![];
-        let mut cursor = None;
-
-        while collected.is_empty() {
-            let cells: Pagination<Cell> = to_result(
-                self.indexer_client
-                    .request(
-                        "get_cells",
-                        Some(ClientParams::Array(vec![
-                            json!(search_key),
-                            json!(order),
-                            json!(limit),
-                            json!(cursor),
-                        ])),
-                    )
-                    .await?,
-            )?;
-
-            if cells.last_cursor.is_empty() {
-                return Ok(vec![]);
-            }
-            cursor = Some(cells.last_cursor);
-
-            for cell in cells.objects.into_iter() {
-                let args = cell.output.lock.args.clone().into_bytes();
-                let custodian_lock_args = match CustodianLockArgsReader::verify(&args[32..], false)
-                {
-                    Ok(()) => CustodianLockArgs::new_unchecked(args.slice(32..)),
-                    Err(_) => continue,
-                };
-
-                let deposit_block_hash: [u8; 32] =
-                    custodian_lock_args.deposit_block_hash().unpack();
-                if !block_hashes.contains(&deposit_block_hash) {
-                    continue;
-                }
-
-                collected.push(to_cell_info(cell));
-            }
-        }
-
-        Ok(collected)
+        query_custodian_cells_by_block_hashes(&self.indexer_client, custodian_lock, block_hashes)
+            .await
     }
 
     pub async fn query_finalized_custodian_cells(
@@ -823,7 +1103,7 @@ impl RPCClient {
         let mut collected_fullfilled_sudt = HashSet::new();
         let mut cursor = None;
 
-        while collected.capacity < withdrawals_amount.capacity
+        while collected.value.capacity < withdrawals_amount.capacity
             || collected_fullfilled_sudt.len() < withdrawals_amount.sudt.len()
         {
             let cells: Pagination<Cell> = to_result(
@@ -846,6 +1126,7 @@ impl RPCClient {
             cursor = Some(cells.last_cursor);
 
             for cell in cells.objects.into_iter() {
+                let mut target_sudt = None;
                 let args = cell.output.lock.args.clone().into_bytes();
                 let custodian_lock_args = match CustodianLockArgsReader::verify(&args[32..], false)
                 {
@@ -887,28 +1168,15 @@ impl RPCClient {
                             None => continue,
                         };
 
-                        let sudt_amount = match parse_sudt_amount(&cell) {
-                            Ok(amount) => amount,
-                            Err(_) => {
-                                log::error!("invalid sudt amount, out_point: {:?}", cell.out_point);
-                                continue;
-                            }
-                        };
-
-                        let (collected_amount, type_script) = {
-                            let sudt = collected.sudt.entry(sudt_type_hash);
-                            sudt.or_insert((0, Script::default()))
-                        };
-                        *collected_amount = collected_amount.saturating_add(sudt_amount);
-                        *type_script = sudt_type_script;
-
-                        if *collected_amount >= *withdrawal_amount {
-                            collected_fullfilled_sudt.insert(sudt_type_hash);
+                        if parse_sudt_amount(&cell).is_err() {
+                            log::error!("invalid sudt amount, out_point: {:?}", cell.out_point);
+                            continue;
                         }
+                        target_sudt = Some((sudt_type_hash, *withdrawal_amount));
                     }
                 }
 
-                // Collect capacity
+                // Collect capacity and sudt
                 let out_point = {
                     let out_point: ckb_types::packed::OutPoint = cell.out_point.into();
                     OutPoint::new_unchecked(out_point.as_bytes())
@@ -919,17 +1187,19 @@ impl RPCClient {
                     CellOutput::new_unchecked(output.as_bytes())
                 };
 
-                collected.capacity = collected
-                    .capacity
-                    .saturating_add(output.capacity().unpack() as u128);
-
                 let info = CellInfo {
                     out_point,
                     output,
                     data: cell.output_data.into_bytes(),
                 };
+                collected.push(info);
 
-                collected.cells_info.push(info);
+                if let Some((sudt_type_hash, withdrawal_amount)) = target_sudt {
+                    let collected_amount = collected.value.sudt.get(&sudt_type_hash);
+                    if collected_amount >= Some(&withdrawal_amount) {
+                        collected_fullfilled_sudt.insert(sudt_type_hash);
+                    }
+                }
             }
         }
 
@@ -1010,9 +1280,12 @@ impl RPCClient {
         }
     }
 
+    /// Query withdrawal cells of `block_hashes`, only cells owned by
+    /// `owner_lock_hash` if it's set
     pub async fn query_withdrawal_cells_by_block_hashes(
         &self,
         block_hashes: &HashSet<[u8; 32]>,
+        owner_lock_hash: Option<[u8; 32]>,
     ) -> Result<Vec<CellInfo>> {
         let rollup_context = &self.rollup_context;
 
@@ -1053,38 +1326,8 @@ impl RPCClient {
             }
             cursor = Some(cells.last_cursor);
 
-            for cell in cells.objects.into_iter() {
-                let args = cell.output.lock.args.clone().into_bytes();
-                let withdrawal_lock_args =
-                    match WithdrawalLockArgsReader::verify(&args[32..], false) {
-                        Ok(()) => WithdrawalLockArgs::new_unchecked(args.slice(32..)),
-                        Err(_) => continue,
-                    };
-
-                let withdrawal_block_hash: [u8; 32] =
-                    withdrawal_lock_args.withdrawal_block_hash().unpack();
-                if !block_hashes.contains(&withdrawal_block_hash) {
-                    continue;
-                }
-
-                let out_point = {
-                    let out_point: ckb_types::packed::OutPoint = cell.out_point.into();
-                    OutPoint::new_unchecked(out_point.as_bytes())
-                };
-
-                let output = {
-                    let output: ckb_types::packed::CellOutput = cell.output.into();
-                    CellOutput::new_unchecked(output.as_bytes())
-                };
-
-                let info = CellInfo {
-                    out_point,
-                    output,
-                    data: cell.output_data.into_bytes(),
-                };
-
-                collected.push(info);
-            }
+            let to_cell = |cell| to_withdrawal_cell(cell, block_hashes, owner_lock_hash.as_ref());
+            collected.extend(cells.objects.into_iter().filter_map(to_cell));
         }
 
         Ok(collected)
@@ -1257,6 +1500,18 @@ impl RPCClient {
     }
 
     pub async fn send_transaction(&self, tx: Transaction) -> Result<H256> {
+        if NodeMode::ReadOnly == self.node_mode {
+            return Err(anyhow!(
+                "refuse to send transaction {} in readonly node mode",
+                hex::encode(tx.hash())
+            ));
+        }
+        let consumed: HashSet<OutPoint> = tx
+            .raw()
+            .inputs()
+            .into_iter()
+            .map(|input| input.previous_output())
+            .collect();
         let tx: ckb_jsonrpc_types::Transaction = {
             let tx = ckb_types::packed::Transaction::new_unchecked(tx.as_bytes());
             tx.into()
@@ -1269,6 +1524,7 @@ impl RPCClient {
                 )
                 .await?,
         )?;
+        self.payment_cells_cache.lock().invalidate(&consumed);
         Ok(to_h256(tx_hash))
     }
 
@@ -1294,3 +1550,453 @@ impl RPCClient {
         Ok(dry_run_result.cycles.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custodian_cell(block_hash: [u8; 32], capacity: u64, sudt: Option<u128>) -> CellInfo {
+        let lock_args = {
+            let args = CustodianLockArgs::new_builder()
+                .deposit_block_hash(block_hash.pack())
+                .build();
+            let mut lock_args = vec![0u8; 32];
+            lock_args.extend_from_slice(args.as_slice());
+            Bytes::from(lock_args)
+        };
+        let type_ = sudt.map(|_| Script::new_builder().args(vec![1u8; 32].pack()).build());
+        let data = sudt.map(|amount| amount.pack().as_bytes());
+        CellInfo {
+            out_point: OutPoint::default(),
+            output: CellOutput::new_builder()
+                .capacity(capacity.pack())
+                .lock(Script::new_builder().args(lock_args.pack()).build())
+                .type_(type_.pack())
+                .build(),
+            data: data.unwrap_or_default(),
+        }
+    }
+
+    fn payment_cell(capacity: u64) -> CellInfo {
+        CellInfo {
+            out_point: OutPoint::default(),
+            output: CellOutput::new_builder().capacity(capacity.pack()).build(),
+            data: Bytes::new(),
+        }
+    }
+
+    fn to_indexer_cell(cell: &CellInfo) -> Cell {
+        Cell {
+            output: ckb_types::packed::CellOutput::new_unchecked(cell.output.as_bytes()).into(),
+            output_data: JsonBytes::from_bytes(cell.data.clone()),
+            out_point: ckb_types::packed::OutPoint::new_unchecked(cell.out_point.as_bytes()).into(),
+            block_number: 0u64.into(),
+            tx_index: 0u32.into(),
+        }
+    }
+
+    // Serve cells page by page and count `get_cells` queries, the cursor of
+    // a page is the index of the next page
+    struct MockIndexer {
+        pages: Vec<Vec<CellInfo>>,
+        queries: Mutex<usize>,
+    }
+
+    impl MockIndexer {
+        fn new(pages: Vec<Vec<CellInfo>>) -> Self {
+            MockIndexer {
+                pages,
+                queries: Mutex::new(0),
+            }
+        }
+
+        fn queries(&self) -> usize {
+            *self.queries.lock()
+        }
+    }
+
+    #[async_trait]
+    impl IndexerCellsQuery for MockIndexer {
+        async fn get_cells(
+            &self,
+            _search_key: &SearchKey,
+            _order: &Order,
+            _limit: Uint32,
+            cursor: Option<JsonBytes>,
+        ) -> Result<Pagination<Cell>> {
+            *self.queries.lock() += 1;
+
+            let page = cursor.map_or(0, |cursor| cursor.as_bytes()[0] as usize);
+            match self.pages.get(page) {
+                Some(cells) => Ok(Pagination {
+                    objects: cells.iter().map(to_indexer_cell).collect(),
+                    last_cursor: JsonBytes::from_vec(vec![page as u8 + 1]),
+                }),
+                None => Ok(Pagination {
+                    objects: vec![],
+                    last_cursor: JsonBytes::default(),
+                }),
+            }
+        }
+    }
+
+    #[test]
+    fn test_readonly_node_never_sends_transaction() {
+        let rpc_client = RPCClient {
+            indexer_client: HttpClient::new("http://127.0.0.1:8116".to_string()).unwrap(),
+            ckb_client: HttpClient::new("http://127.0.0.1:8114".to_string()).unwrap(),
+            rollup_type_script: Default::default(),
+            rollup_context: RollupContext {
+                rollup_script_hash: H256::zero(),
+                rollup_config: Default::default(),
+            },
+            payment_cells_config: Default::default(),
+            payment_cells_cache: Arc::new(Mutex::new(PaymentCellsCache::new(Duration::from_secs(
+                0,
+            )))),
+            reserved_payment_cells: Default::default(),
+            node_mode: NodeMode::ReadOnly,
+        };
+
+        let err = smol::block_on(rpc_client.send_transaction(Transaction::default())).unwrap_err();
+        assert!(err.to_string().contains("readonly"));
+    }
+
+    #[test]
+    fn test_query_payment_cells_cache_ttl() {
+        let config = PaymentCellsConfig {
+            min_capacity: 0,
+            max_cells: None,
+            cache_ttl_ms: 1000,
+            maturity_blocks: 0,
+        };
+        let ttl = Duration::from_millis(config.cache_ttl_ms);
+        let cache = Mutex::new(PaymentCellsCache::new(ttl));
+        let indexer = MockIndexer::new(vec![vec![payment_cell(100)], vec![payment_cell(200)]]);
+
+        let query_payment_cells = |now: Instant| {
+            let query = PaymentCellsQuery {
+                lock: Script::default(),
+                required_capacity: 300,
+                tip_number: smol::future::ready(Ok(0)),
+                is_excluded: |_: &OutPoint| false,
+            };
+            smol::block_on(query_payment_cells_with_cache(
+                &indexer, &cache, &config, query, now,
+            ))
+            .unwrap()
+        };
+
+        let now = Instant::now();
+        let cells = query_payment_cells(now);
+        assert_eq!(cells.len(), 2);
+        assert_eq!(indexer.queries(), 2);
+
+        // Fee of the next tx within ttl is filled by cached cells
+        let cells = query_payment_cells(now + ttl / 2);
+        assert_eq!(cells.len(), 2);
+        assert_eq!(indexer.queries(), 2);
+
+        // Query cells again once ttl expires
+        let cells = query_payment_cells(now + ttl);
+        assert_eq!(cells.len(), 2);
+        assert_eq!(indexer.queries(), 4);
+    }
+
+    #[test]
+    fn test_select_payment_cells_skip_dust() {
+        let config = PaymentCellsConfig {
+            min_capacity: 100,
+            max_cells: None,
+            cache_ttl_ms: 0,
+            maturity_blocks: 0,
+        };
+        let cells = vec![payment_cell(1), payment_cell(200), payment_cell(99)];
+
+        let mut collected_cells = Vec::new();
+        let mut collected_capacity = 0;
+        select_payment_cells(
+            cells.into_iter(),
+            &config,
+            300,
+            &mut collected_cells,
+            &mut collected_capacity,
+        );
+
+        assert_eq!(collected_cells.len(), 1);
+        assert_eq!(collected_capacity, 200);
+    }
+
+    #[test]
+    fn test_select_payment_cells_max_cells() {
+        let config = PaymentCellsConfig {
+            min_capacity: 0,
+            max_cells: Some(2),
+            cache_ttl_ms: 0,
+            maturity_blocks: 0,
+        };
+        let cells = vec![payment_cell(100), payment_cell(100), payment_cell(100)];
+
+        let mut collected_cells = Vec::new();
+        let mut collected_capacity = 0;
+        select_payment_cells(
+            cells.into_iter(),
+            &config,
+            300,
+            &mut collected_cells,
+            &mut collected_capacity,
+        );
+
+        // Capped cells are kept, callers query more cells for the rest
+        assert_eq!(collected_cells.len(), 2);
+        assert_eq!(collected_capacity, 200);
+    }
+
+    #[test]
+    fn test_to_payment_cell() {
+        let indexer_cell = |index: u32, block_number: u64| {
+            let out_point = OutPoint::new_builder().index(index.pack()).build();
+            let output = payment_cell(100).output;
+            Cell {
+                output: ckb_types::packed::CellOutput::new_unchecked(output.as_bytes()).into(),
+                output_data: Default::default(),
+                out_point: ckb_types::packed::OutPoint::new_unchecked(out_point.as_bytes()).into(),
+                block_number: block_number.into(),
+                tx_index: 0u32.into(),
+            }
+        };
+        let reserved: HashSet<OutPoint> = vec![OutPoint::new_builder().index(1u32.pack()).build()]
+            .into_iter()
+            .collect();
+        let is_excluded = |out_point: &OutPoint| reserved.contains(out_point);
+
+        // mature
+        let cell = to_payment_cell(indexer_cell(0, 90), 100, 10, &is_excluded).unwrap();
+        assert_eq!(cell.output.as_slice(), payment_cell(100).output.as_slice());
+        // reserved
+        assert!(to_payment_cell(indexer_cell(1, 90), 100, 10, &is_excluded).is_none());
+        // immature
+        assert!(to_payment_cell(indexer_cell(2, 91), 100, 10, &is_excluded).is_none());
+        // maturity disabled
+        assert!(to_payment_cell(indexer_cell(2, 100), 100, 0, &is_excluded).is_some());
+    }
+
+    #[test]
+    fn test_to_withdrawal_cell() {
+        let indexer_cell = |block_hash: [u8; 32], owner_lock_hash: [u8; 32]| {
+            let lock_args = {
+                let args = WithdrawalLockArgs::new_builder()
+                    .withdrawal_block_hash(block_hash.pack())
+                    .owner_lock_hash(owner_lock_hash.pack())
+                    .build();
+                let mut lock_args = vec![0u8; 32];
+                lock_args.extend_from_slice(args.as_slice());
+                Bytes::from(lock_args)
+            };
+            let output = CellOutput::new_builder()
+                .capacity(1000u64.pack())
+                .lock(Script::new_builder().args(lock_args.pack()).build())
+                .build();
+            Cell {
+                output: ckb_types::packed::CellOutput::new_unchecked(output.as_bytes()).into(),
+                output_data: Default::default(),
+                out_point: ckb_types::packed::OutPoint::default().into(),
+                block_number: 0u64.into(),
+                tx_index: 0u32.into(),
+            }
+        };
+        let (block_a, block_b) = ([1u8; 32], [2u8; 32]);
+        let (alice, bob) = ([3u8; 32], [4u8; 32]);
+        let block_hashes: HashSet<[u8; 32]> = vec![block_a].into_iter().collect();
+        let cells = || {
+            vec![
+                indexer_cell(block_a, alice),
+                indexer_cell(block_a, bob),
+                indexer_cell(block_a, alice),
+                indexer_cell(block_b, alice),
+            ]
+        };
+        let collect = |owner_lock_hash: Option<&[u8; 32]>| -> Vec<CellInfo> {
+            cells()
+                .into_iter()
+                .filter_map(|cell| to_withdrawal_cell(cell, &block_hashes, owner_lock_hash))
+                .collect()
+        };
+        let owner_of = |cell: &CellInfo| -> [u8; 32] {
+            let args: Bytes = cell.output.lock().args().unpack();
+            let lock_args = WithdrawalLockArgs::new_unchecked(args.slice(32..));
+            lock_args.owner_lock_hash().unpack()
+        };
+
+        // all owners
+        assert_eq!(collect(None).len(), 3);
+
+        let alice_cells = collect(Some(&alice));
+        assert_eq!(alice_cells.len(), 2);
+        assert!(alice_cells.iter().all(|cell| owner_of(cell) == alice));
+
+        let bob_cells = collect(Some(&bob));
+        assert_eq!(bob_cells.len(), 1);
+        assert_eq!(owner_of(&bob_cells[0]), bob);
+
+        assert!(collect(Some(&[5u8; 32])).is_empty());
+    }
+
+    #[test]
+    fn test_payment_cells_cache() {
+        let lock = Script::new_builder().args(vec![1u8; 20].pack()).build();
+        let cells: Vec<CellInfo> = (0..2u32)
+            .map(|index| CellInfo {
+                out_point: OutPoint::new_builder().index(index.pack()).build(),
+                ..payment_cell(100)
+            })
+            .collect();
+        let now = Instant::now();
+        let ttl = Duration::from_secs(3);
+
+        // Reused within ttl
+        let mut cache = PaymentCellsCache::new(ttl);
+        cache.insert(&lock, cells.clone(), now);
+        let cached = cache.get(&lock, now + Duration::from_secs(1)).unwrap();
+        assert_eq!(cached.len(), 2);
+        let other_lock = Script::new_builder().args(vec![2u8; 20].pack()).build();
+        assert!(cache.get(&other_lock, now).is_none());
+
+        // Expired
+        assert!(cache.get(&lock, now + ttl).is_none());
+
+        // Unrelated cells consumed
+        let unrelated = vec![OutPoint::new_builder().index(2u32.pack()).build()];
+        cache.invalidate(&unrelated.into_iter().collect());
+        assert!(cache.get(&lock, now).is_some());
+
+        // One of cached cells consumed
+        let consumed = vec![cells[1].out_point.clone()];
+        cache.invalidate(&consumed.into_iter().collect());
+        assert!(cache.get(&lock, now).is_none());
+
+        // Disabled
+        let mut cache = PaymentCellsCache::new(Duration::from_secs(0));
+        cache.insert(&lock, cells, now);
+        assert!(cache.get(&lock, now).is_none());
+    }
+
+    #[test]
+    fn test_collect_custodian_cells_by_block_hashes() {
+        let reverted_block_hash = [1u8; 32];
+        let block_hashes: HashSet<[u8; 32]> = vec![reverted_block_hash].into_iter().collect();
+        let pages = vec![
+            vec![
+                custodian_cell(reverted_block_hash, 500, None),
+                custodian_cell([2u8; 32], 1000, None),
+            ],
+            vec![
+                custodian_cell(reverted_block_hash, 300, Some(20)),
+                custodian_cell(reverted_block_hash, 200, Some(30)),
+            ],
+        ];
+
+        let mut collected = CollectedCustodianCells::default();
+        for page in pages {
+            collect_custodian_cells_by_block_hashes(
+                &mut collected,
+                page.into_iter(),
+                &block_hashes,
+            );
+        }
+
+        assert_eq!(collected.cells_info.len(), 3);
+        assert_eq!(collected.value.capacity, 1000);
+        assert_eq!(collected.value.sudt.len(), 1);
+        assert_eq!(collected.sudt_scripts.len(), 1);
+        let sudt_amount = collected.value.sudt.values().next().unwrap();
+        assert_eq!(*sudt_amount, 50);
+    }
+
+    #[test]
+    fn test_query_custodian_cells_by_block_hashes_all_pages() {
+        let reverted_block_hash = [1u8; 32];
+        let block_hashes: HashSet<[u8; 32]> = vec![reverted_block_hash].into_iter().collect();
+        let indexer = MockIndexer::new(vec![
+            vec![
+                custodian_cell(reverted_block_hash, 500, None),
+                custodian_cell([2u8; 32], 1000, None),
+            ],
+            vec![custodian_cell(reverted_block_hash, 300, Some(20))],
+            vec![
+                custodian_cell([2u8; 32], 700, Some(40)),
+                custodian_cell(reverted_block_hash, 200, Some(30)),
+            ],
+        ]);
+
+        let collected = smol::block_on(query_custodian_cells_by_block_hashes(
+            &indexer,
+            Script::default(),
+            &block_hashes,
+        ))
+        .unwrap();
+
+        // Three pages and the last empty one
+        assert_eq!(indexer.queries(), 4);
+        assert_eq!(collected.cells_info.len(), 3);
+        assert_eq!(collected.value.capacity, 1000);
+        assert_eq!(collected.value.sudt.len(), 1);
+        let sudt_amount = collected.value.sudt.values().next().unwrap();
+        assert_eq!(*sudt_amount, 50);
+    }
+
+    #[test]
+    fn test_parse_deposit_info_cancel_timeout() {
+        let layer2_lock = Script::new_builder().args(vec![3u8; 20].pack()).build();
+        let deposit_lock_args = DepositLockArgs::new_builder()
+            .layer2_lock(layer2_lock.clone())
+            .cancel_timeout(42u64.pack())
+            .build();
+        let lock_args = {
+            let mut args = vec![0u8; 32];
+            args.extend_from_slice(deposit_lock_args.as_slice());
+            Bytes::from(args)
+        };
+        let cell = CellInfo {
+            out_point: OutPoint::default(),
+            output: CellOutput::new_builder()
+                .capacity(1000u64.pack())
+                .lock(Script::new_builder().args(lock_args.pack()).build())
+                .build(),
+            data: Bytes::new(),
+        };
+
+        let info = parse_deposit_info(cell).unwrap();
+        assert_eq!(info.cancel_timeout, 42);
+        assert_eq!(info.request.script(), layer2_lock);
+
+        let invalid_cell = CellInfo {
+            out_point: OutPoint::default(),
+            output: CellOutput::default(),
+            data: Bytes::new(),
+        };
+        assert!(parse_deposit_info(invalid_cell).is_none());
+    }
+
+    #[test]
+    fn test_sort_deposits() {
+        let deposit = |capacity: u64| DepositInfo {
+            request: DepositRequest::new_builder()
+                .capacity(capacity.pack())
+                .build(),
+            cell: payment_cell(capacity),
+            cancel_timeout: 0,
+        };
+        let capacities = |deposits: &[DepositInfo]| -> Vec<u64> {
+            let to_capacity = |d: &DepositInfo| -> u64 { d.cell.output.capacity().unpack() };
+            deposits.iter().map(to_capacity).collect()
+        };
+        let mut deposits = vec![deposit(100), deposit(300), deposit(200), deposit(300)];
+
+        sort_deposits(&mut deposits, DepositOrder::Index);
+        assert_eq!(capacities(&deposits), vec![100, 300, 200, 300]);
+
+        sort_deposits(&mut deposits, DepositOrder::Capacity);
+        assert_eq!(capacities(&deposits), vec![300, 300, 200, 100]);
+    }
+}
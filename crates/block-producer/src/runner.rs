@@ -1,28 +1,41 @@
 use crate::{
-    block_producer::BlockProducer, challenger::Challenger, poa::PoA, poller::ChainUpdater,
-    rpc_client::RPCClient, test_mode_control::TestModeControl, types::ChainEvent,
-    utils::CKBGenesisInfo, wallet::Wallet,
+    block_producer::BlockProducer,
+    challenger::Challenger,
+    poa::PoA,
+    poller::{poll_layer1_events, ChainUpdater},
+    rpc_client::{PaymentCellsCache, RPCClient},
+    test_mode_control::TestModeControl,
+    types::ChainEvent,
+    utils::CKBGenesisInfo,
+    wallet::Wallet,
 };
 use anyhow::{anyhow, Context, Result};
+use async_channel::Receiver;
 use async_jsonrpc_client::HttpClient;
 use futures::{select, FutureExt};
 use gw_chain::chain::Chain;
-use gw_common::H256;
+use gw_common::{state::State, H256};
 use gw_config::{BlockProducerConfig, Config, NodeMode};
-use gw_db::{config::Config as DBConfig, schema::COLUMNS, RocksDB};
+use gw_db::{config::Config as DBConfig, schema::COLUMNS};
 use gw_generator::{
     account_lock_manage::{secp256k1::Secp256k1Eth, AccountLockManage},
     backend_manage::BackendManage,
-    genesis::init_genesis,
+    error::GenesisError,
+    genesis::{init_genesis, validate_rollup_config},
     Generator, RollupContext,
 };
+use gw_jsonrpc_types::h256::to_h256;
 use gw_mem_pool::pool::MemPool;
 use gw_rpc_server::{registry::Registry, server::start_jsonrpc_server};
-use gw_store::Store;
+use gw_store::{
+    state_db::{CheckPoint, StateDBMode, StateDBTransaction, SubState},
+    Store,
+};
+use gw_traits::CodeStore;
 use gw_types::prelude::{Pack, Unpack};
 use gw_types::{
     bytes::Bytes,
-    packed::{NumberHash, RollupConfig, Script},
+    packed::{RollupConfig, Script},
     prelude::*,
 };
 use gw_web3_indexer::Web3Indexer;
@@ -33,6 +46,7 @@ use sqlx::{
     ConnectOptions,
 };
 use std::{
+    cmp::max,
     net::{SocketAddr, ToSocketAddrs},
     process::exit,
     sync::Arc,
@@ -47,103 +61,54 @@ async fn poll_loop(
     block_producer: Option<BlockProducer>,
     challenger: Option<Challenger>,
     poll_interval: Duration,
+    event_channel_capacity: usize,
 ) -> Result<()> {
-    struct Inner {
-        chain_updater: ChainUpdater,
-        block_producer: Option<BlockProducer>,
-        challenger: Option<Challenger>,
-    }
+    // polling backpressures once block application falls behind
+    let (event_sender, event_receiver) = async_channel::bounded(max(event_channel_capacity, 1));
+    let poller = poll_layer1_events(&rpc_client, poll_interval, event_sender);
+    let applier = apply_loop(event_receiver, chain_updater, block_producer, challenger);
+    futures::try_join!(poller, applier)?;
+    Ok(())
+}
 
-    let inner = Arc::new(smol::lock::Mutex::new(Inner {
-        chain_updater,
-        challenger,
-        block_producer,
-    }));
-    // get tip
-    let (mut tip_number, mut tip_hash) = {
-        let tip = rpc_client.get_tip().await?;
-        let tip_number: u64 = tip.number().unpack();
-        let tip_hash: H256 = tip.block_hash().unpack();
-        (tip_number, tip_hash)
-    };
-    loop {
-        if let Some(block) = rpc_client.get_block_by_number(tip_number + 1).await? {
-            let raw_header = block.header().raw();
-            let event = if raw_header.parent_hash().as_slice() == tip_hash.as_slice() {
-                // received new layer1 block
-                log::info!(
-                    "received new layer1 block {}, {}",
-                    tip_number,
-                    hex::encode(tip_hash.as_slice())
-                );
-                ChainEvent::NewBlock {
-                    block: block.clone(),
-                }
-            } else {
-                // layer1 reverted
-                log::info!(
-                    "layer1 reverted {}, {:?}",
-                    tip_number,
-                    hex::encode(tip_hash.as_slice())
-                );
-                ChainEvent::Reverted {
-                    old_tip: NumberHash::new_builder()
-                        .number(tip_number.pack())
-                        .block_hash(tip_hash.pack())
-                        .build(),
-                    new_block: block.clone(),
-                }
-            };
-            // must execute chain update before block producer, otherwise we may run into an invalid chain state
-            // smol::spawn({
-            let event = event.clone();
-            let inner = inner.clone();
-            // async move {
-            let mut inner = inner.lock().await;
-            if let Err(err) = inner.chain_updater.handle_event(event.clone()).await {
+async fn apply_loop(
+    event_receiver: Receiver<ChainEvent>,
+    mut chain_updater: ChainUpdater,
+    mut block_producer: Option<BlockProducer>,
+    mut challenger: Option<Challenger>,
+) -> Result<()> {
+    while let Ok(event) = event_receiver.recv().await {
+        // must execute chain update before block producer, otherwise we may run into an invalid chain state
+        if let Err(err) = chain_updater.handle_event(event.clone()).await {
+            log::error!(
+                "Error occured when polling chain_updater, event: {:?}, error: {}",
+                event,
+                err
+            );
+        }
+
+        if let Some(ref mut challenger) = challenger {
+            if let Err(err) = challenger.handle_event(event.clone()).await {
                 log::error!(
-                    "Error occured when polling chain_updater, event: {:?}, error: {}",
+                    "Error occured when polling challenger, event: {:?}, error: {}",
                     event,
                     err
                 );
             }
+        }
 
-            if let Some(ref mut challenger) = inner.challenger {
-                if let Err(err) = challenger.handle_event(event.clone()).await {
-                    log::error!(
-                        "Error occured when polling challenger, event: {:?}, error: {}",
-                        event,
-                        err
-                    );
-                }
-            }
-
-            // TODO: implement test mode challenge control
-            if let Some(ref mut block_producer) = inner.block_producer {
-                if let Err(err) = block_producer.handle_event(event.clone()).await {
-                    log::error!(
-                        "Error occured when polling block_producer, event: {:?}, error: {}",
-                        event,
-                        err
-                    );
-                }
+        // TODO: implement test mode challenge control
+        if let Some(ref mut block_producer) = block_producer {
+            if let Err(err) = block_producer.handle_event(event.clone()).await {
+                log::error!(
+                    "Error occured when polling block_producer, event: {:?}, error: {}",
+                    event,
+                    err
+                );
             }
-
-            // }
-            // })
-            // .detach();
-            // update tip
-            tip_number = raw_header.number().unpack();
-            tip_hash = block.header().hash().into();
-        } else {
-            log::debug!(
-                "Not found layer1 block #{} sleep {}s then retry",
-                tip_number + 1,
-                poll_interval.as_secs()
-            );
-            async_std::task::sleep(poll_interval).await;
         }
     }
+    Ok(())
 }
 
 pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
@@ -161,15 +126,27 @@ pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
         let ckb_client = HttpClient::new(config.rpc_client.ckb_url)?;
         let rollup_type_script =
             ckb_types::packed::Script::new_unchecked(rollup_type_script.as_bytes());
+        let payment_cells_config = config
+            .block_producer
+            .as_ref()
+            .map(|config| config.payment_cells_config.clone())
+            .unwrap_or_default();
+        let payment_cells_cache =
+            PaymentCellsCache::new(Duration::from_millis(payment_cells_config.cache_ttl_ms));
         RPCClient {
             indexer_client,
             ckb_client,
             rollup_context: rollup_context.clone(),
             rollup_type_script,
+            payment_cells_config,
+            payment_cells_cache: Arc::new(Mutex::new(payment_cells_cache)),
+            reserved_payment_cells: Default::default(),
+            node_mode: config.node_mode,
         }
     };
 
     if !skip_config_check {
+        validate_rollup_config(&rollup_config)?;
         check_ckb_version(&rpc_client)?;
         // TODO: check ckb indexer version
         if NodeMode::ReadOnly != config.node_mode {
@@ -192,11 +169,11 @@ pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
             options: Default::default(),
             options_file: Default::default(),
         };
-        Store::new(RocksDB::open(&db_config, COLUMNS))
+        Store::open(&db_config, COLUMNS).with_context(|| "open store")?
     };
     let secp_data: Bytes = {
         let out_point = config.genesis.secp_data_dep.out_point.clone();
-        smol::block_on(rpc_client.get_transaction(out_point.tx_hash.0.into()))?
+        smol::block_on(rpc_client.get_transaction(to_h256(out_point.tx_hash.clone())))?
             .ok_or_else(|| anyhow!("can not found transaction: {:?}", out_point.tx_hash))?
             .raw()
             .outputs_data()
@@ -204,13 +181,30 @@ pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
             .expect("get secp output data")
             .raw_data()
     };
-    init_genesis(
+    let init_result = init_genesis(
         &store,
         &config.genesis,
         config.chain.genesis_committed_info.clone().into(),
         secp_data,
-    )
-    .with_context(|| "init genesis")?;
+    );
+    if let Err(err) = init_result {
+        match err.downcast_ref::<GenesisError>() {
+            // Node restarts on its own store
+            Some(GenesisError::AlreadyInitialized { genesis_hash }) => {
+                log::info!(
+                    "Genesis {} is already initialized",
+                    hex::encode(genesis_hash.as_slice())
+                );
+            }
+            _ => return Err(err.context("init genesis")),
+        }
+    }
+
+    if !skip_config_check && NodeMode::ReadOnly != config.node_mode {
+        if let Some(block_producer_config) = config.block_producer.as_ref() {
+            check_allowed_deps(&store, block_producer_config)?;
+        }
+    }
 
     let rollup_config_hash: H256 = rollup_config.hash().into();
     let generator = {
@@ -225,25 +219,40 @@ pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
             eth_lock_script_type_hash.unpack(),
             Box::new(Secp256k1Eth::default()),
         );
-        Arc::new(Generator::new(
-            backend_manage,
-            account_lock_manage,
-            rollup_context.clone(),
-        ))
+        Arc::new(
+            Generator::new(backend_manage, account_lock_manage, rollup_context.clone())
+                .with_batch_tx_execution(config.chain.batch_tx_execution)
+                .with_double_tx_execution(config.chain.double_tx_execution)
+                .with_state_journal_dir(config.chain.state_journal_dir.clone())
+                .with_cycles_per_gas(config.chain.cycles_per_gas)
+                .with_context(|| "config cycles_per_gas")?,
+        )
     };
     let mem_pool = Arc::new(Mutex::new(
         MemPool::create(store.clone(), generator.clone()).with_context(|| "create mem-pool")?,
     ));
-    let chain = Arc::new(Mutex::new(
-        Chain::create(
+    let chain = {
+        let skip_trusted_checkpoint_checks = config.block_producer.as_ref().map_or(false, |c| {
+            c.challenger_config.skip_trusted_checkpoint_checks
+        });
+        let mut chain = Chain::create(
             &rollup_config,
             &config.chain.rollup_type_script.clone().into(),
             store.clone(),
             generator.clone(),
             mem_pool.clone(),
         )
-        .with_context(|| "create chain")?,
-    ));
+        .with_context(|| "create chain")?
+        .with_max_safe_reorg_depth(config.chain.max_safe_reorg_depth)
+        .with_skip_trusted_checkpoint_checks(skip_trusted_checkpoint_checks)
+        .with_trust_mode(config.chain.trust_mode);
+        if config.chain.clear_unsafe_reorg {
+            chain
+                .clear_unsafe_reorg()
+                .with_context(|| "clear unsafe reorg")?;
+        }
+        Arc::new(Mutex::new(chain))
+    };
 
     // create web3 indexer
     let web3_indexer = match config.web3_indexer {
@@ -268,6 +277,8 @@ pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
                 polyjuce_type_script_hash,
                 config.genesis.rollup_type_hash,
                 eth_account_lock_hash,
+                web3_indexer_config.store_raw_input,
+                web3_indexer_config.lookup_concurrency,
             );
             Some(web3_indexer)
         }
@@ -351,7 +362,9 @@ pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
     };
 
     // RPC registry
-    let rpc_registry = Registry::new(store, mem_pool, generator, test_mode_control.map(Box::new));
+    let rpc_registry = Registry::new(store, mem_pool, generator, test_mode_control.map(Box::new))
+        .with_max_return_data_size(config.rpc_server.max_return_data_size)
+        .with_min_gas_price(config.fee.min_gas_price);
 
     let (s, ctrl_c) = async_channel::bounded(100);
     let handle = move || {
@@ -359,6 +372,9 @@ pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
     };
     ctrlc::set_handler(handle).unwrap();
 
+    let poll_interval = Duration::from_millis(config.rpc_client.poll_interval_ms);
+    let event_channel_capacity = config.rpc_client.event_channel_capacity;
+
     let rpc_address: SocketAddr = {
         let mut addrs: Vec<_> = config.rpc_server.listen.to_socket_addrs()?.collect();
         if addrs.len() != 1 {
@@ -386,7 +402,7 @@ pub fn run(config: Config, skip_config_check: bool) -> Result<()> {
     smol::block_on(async {
         select! {
             _ = ctrl_c.recv().fuse() => log::info!("Exiting..."),
-            e = poll_loop(rpc_client, chain_updater, block_producer, challenger, Duration::from_secs(3)).fuse() => {
+            e = poll_loop(rpc_client, chain_updater, block_producer, challenger, poll_interval, event_channel_capacity).fuse() => {
                 log::error!("Error in main poll loop: {:?}", e);
             }
             e = start_jsonrpc_server(rpc_address, rpc_registry).fuse() => {
@@ -457,6 +473,56 @@ fn check_rollup_config_cell(
     Ok(())
 }
 
+/// Code hashes of account scripts in `state` which have no dep in
+/// `allowed_eoa_deps` nor `allowed_contract_deps`
+pub fn audit_allowed_deps<S: State + CodeStore>(
+    state: &S,
+    block_producer_config: &BlockProducerConfig,
+) -> Result<Vec<H256>> {
+    let has_dep = |code_hash: &H256| {
+        let code_hash = ckb_fixed_hash::H256((*code_hash).into());
+        block_producer_config
+            .allowed_eoa_deps
+            .contains_key(&code_hash)
+            || block_producer_config
+                .allowed_contract_deps
+                .contains_key(&code_hash)
+    };
+
+    let mut missing: Vec<H256> = Vec::new();
+    for account_id in 0..state.get_account_count()? {
+        let script_hash = state.get_script_hash(account_id)?;
+        let script = state
+            .get_script(&script_hash)
+            .ok_or_else(|| anyhow!("account {} script not found", account_id))?;
+        let code_hash: H256 = script.code_hash().unpack();
+        if !has_dep(&code_hash) && !missing.contains(&code_hash) {
+            missing.push(code_hash);
+        }
+    }
+    missing.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+
+    Ok(missing)
+}
+
+fn check_allowed_deps(store: &Store, block_producer_config: &BlockProducerConfig) -> Result<()> {
+    let db = store.begin_transaction();
+    let tip_block_hash = db.get_tip_block_hash()?;
+    let state_db = StateDBTransaction::from_checkpoint(
+        &db,
+        CheckPoint::from_block_hash(&db, tip_block_hash, SubState::Block)?,
+        StateDBMode::ReadOnly,
+    )?;
+    let state = state_db.account_state_tree()?;
+    for code_hash in audit_allowed_deps(&state, block_producer_config)? {
+        log::warn!(
+            "account script code hash {} isn't in [block_producer.allowed_eoa_deps] or [block_producer.allowed_contract_deps]",
+            hex::encode(code_hash.as_slice())
+        );
+    }
+    Ok(())
+}
+
 fn check_locks(
     block_producer_config: &BlockProducerConfig,
     rollup_config: &RollupConfig,
@@ -511,3 +577,55 @@ fn check_locks(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gw_generator::{dummy_state::DummyState, traits::StateExt};
+    use gw_jsonrpc_types::blockchain::CellDep;
+
+    fn script(code_hash: [u8; 32]) -> Script {
+        Script::new_builder().code_hash(code_hash.pack()).build()
+    }
+
+    #[test]
+    fn test_audit_allowed_deps() {
+        let mut state = DummyState::default();
+        state.create_account_from_script(script([1u8; 32])).unwrap();
+        state.create_account_from_script(script([2u8; 32])).unwrap();
+        state
+            .create_account_from_script(
+                script([3u8; 32])
+                    .as_builder()
+                    .args(vec![1u8].pack())
+                    .build(),
+            )
+            .unwrap();
+        state
+            .create_account_from_script(
+                script([3u8; 32])
+                    .as_builder()
+                    .args(vec![2u8].pack())
+                    .build(),
+            )
+            .unwrap();
+
+        let mut block_producer_config = BlockProducerConfig::default();
+        block_producer_config
+            .allowed_eoa_deps
+            .insert(ckb_fixed_hash::H256([1u8; 32]), CellDep::default());
+        block_producer_config
+            .allowed_contract_deps
+            .insert(ckb_fixed_hash::H256([2u8; 32]), CellDep::default());
+
+        let missing = audit_allowed_deps(&state, &block_producer_config).unwrap();
+        assert_eq!(missing, vec![H256::from([3u8; 32])]);
+
+        block_producer_config
+            .allowed_contract_deps
+            .insert(ckb_fixed_hash::H256([3u8; 32]), CellDep::default());
+        let missing = audit_allowed_deps(&state, &block_producer_config).unwrap();
+        assert!(missing.is_empty());
+    }
+}
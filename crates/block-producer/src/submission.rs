@@ -0,0 +1,403 @@
+use crate::rpc_client::RPCClient;
+use crate::types::TxStatus;
+use crate::utils::{CKBErrorKind, JsonRpcError};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use gw_common::H256;
+use gw_types::{packed::Transaction, prelude::*};
+
+#[async_trait]
+pub trait SubmissionRPC {
+    async fn get_tip_number(&self) -> Result<u64>;
+    async fn get_transaction_block_number(&self, tx_hash: H256) -> Result<Option<u64>>;
+    async fn get_transaction_status(&self, tx_hash: H256) -> Result<Option<TxStatus>>;
+    async fn send_transaction(&self, tx: Transaction) -> Result<H256>;
+    async fn dry_run_transaction(&self, tx: Transaction) -> Result<u64>;
+}
+
+#[async_trait]
+impl SubmissionRPC for RPCClient {
+    async fn get_tip_number(&self) -> Result<u64> {
+        Ok(self.get_tip().await?.number().unpack())
+    }
+
+    async fn get_transaction_block_number(&self, tx_hash: H256) -> Result<Option<u64>> {
+        RPCClient::get_transaction_block_number(self, tx_hash).await
+    }
+
+    async fn get_transaction_status(&self, tx_hash: H256) -> Result<Option<TxStatus>> {
+        RPCClient::get_transaction_status(self, tx_hash).await
+    }
+
+    async fn send_transaction(&self, tx: Transaction) -> Result<H256> {
+        RPCClient::send_transaction(self, tx).await
+    }
+
+    async fn dry_run_transaction(&self, tx: Transaction) -> Result<u64> {
+        RPCClient::dry_run_transaction(self, tx).await
+    }
+}
+
+/// Dry run transaction, return its cycles or the rejection error
+pub async fn dry_run_and_check(
+    rpc: &(impl SubmissionRPC + Sync),
+    tx: Transaction,
+    action: &str,
+) -> Result<u64> {
+    let tx_hash = tx.hash();
+    let cycles = rpc.dry_run_transaction(tx).await.map_err(|err| {
+        log::error!(
+            "Fail to dry run {} transaction {}, error: {}",
+            action,
+            hex::encode(tx_hash),
+            err
+        );
+        err
+    })?;
+    log::info!(
+        "Tx({}) {} execution cycles: {}",
+        action,
+        hex::encode(tx_hash),
+        cycles
+    );
+    Ok(cycles)
+}
+
+/// Send transaction, dry run it first if `dry_run` is set so that invalid
+/// transactions are never broadcast
+pub async fn submit_transaction(
+    rpc: &(impl SubmissionRPC + Sync),
+    tx: Transaction,
+    dry_run: bool,
+    action: &str,
+) -> Result<H256> {
+    if dry_run {
+        dry_run_and_check(rpc, tx.clone(), action).await?;
+    }
+    rpc.send_transaction(tx).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmissionStatus {
+    /// No submission is waiting for confirmation
+    Confirmed,
+    /// Submission is not deep enough on layer1
+    Pending,
+    /// Submission was orphaned by a layer1 reorg and has been sent again
+    Resubmitted,
+    /// Submission was orphaned and can't be sent again, e.g. its inputs are
+    /// spent by another transaction
+    Dropped,
+}
+
+struct PendingSubmission {
+    block_number: u64,
+    tx: Transaction,
+    tx_hash: H256,
+    committed_number: Option<u64>,
+}
+
+/// Track the latest block submission until it is buried under
+/// `confirmation_depth` layer1 blocks
+pub struct SubmissionTracker {
+    confirmation_depth: u64,
+    pending: Option<PendingSubmission>,
+}
+
+impl SubmissionTracker {
+    pub fn new(confirmation_depth: u64) -> Self {
+        SubmissionTracker {
+            confirmation_depth,
+            pending: None,
+        }
+    }
+
+    pub fn track(&mut self, block_number: u64, tx: Transaction, tx_hash: H256) {
+        if 0 == self.confirmation_depth {
+            return;
+        }
+        self.pending = Some(PendingSubmission {
+            block_number,
+            tx,
+            tx_hash,
+            committed_number: None,
+        });
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub async fn poll(&mut self, rpc: &(impl SubmissionRPC + Sync)) -> Result<SubmissionStatus> {
+        let pending = match self.pending.as_mut() {
+            Some(pending) => pending,
+            None => return Ok(SubmissionStatus::Confirmed),
+        };
+
+        match rpc.get_transaction_status(pending.tx_hash).await? {
+            Some(TxStatus::Committed) => {
+                let committed_number = rpc
+                    .get_transaction_block_number(pending.tx_hash)
+                    .await?
+                    .ok_or_else(|| anyhow!("committed submission without block"))?;
+                let tip_number = rpc.get_tip_number().await?;
+                if tip_number.saturating_sub(committed_number) >= self.confirmation_depth {
+                    log::info!(
+                        "l2 block {} submission confirmed at l1 block {}",
+                        pending.block_number,
+                        committed_number
+                    );
+                    self.pending = None;
+                    return Ok(SubmissionStatus::Confirmed);
+                }
+                pending.committed_number = Some(committed_number);
+                Ok(SubmissionStatus::Pending)
+            }
+            // Still in tx pool, or put back into it by a layer1 reorg
+            Some(TxStatus::Pending) | Some(TxStatus::Proposed) => {
+                pending.committed_number = None;
+                Ok(SubmissionStatus::Pending)
+            }
+            None => {
+                match pending.committed_number {
+                    Some(_) => log::warn!(
+                        "l2 block {} submission {} orphaned, resubmit",
+                        pending.block_number,
+                        hex::encode(pending.tx_hash.as_slice())
+                    ),
+                    // Evicted or rejected by tx pool before it was ever committed
+                    None => log::warn!(
+                        "l2 block {} submission {} unknown to layer1, resubmit",
+                        pending.block_number,
+                        hex::encode(pending.tx_hash.as_slice())
+                    ),
+                }
+                match rpc.send_transaction(pending.tx.clone()).await {
+                    Ok(tx_hash) => {
+                        pending.tx_hash = tx_hash;
+                        pending.committed_number = None;
+                        Ok(SubmissionStatus::Resubmitted)
+                    }
+                    Err(err)
+                        if JsonRpcError::from_anyhow(&err).map(JsonRpcError::kind)
+                            == Some(CKBErrorKind::PoolRejectedDuplicatedTransaction) =>
+                    {
+                        // Already back in tx pool, keep waiting for it
+                        pending.committed_number = None;
+                        Ok(SubmissionStatus::Resubmitted)
+                    }
+                    Err(err) => {
+                        log::error!("resubmit l2 block {} error: {}", pending.block_number, err);
+                        self.pending = None;
+                        Ok(SubmissionStatus::Dropped)
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct MockRPC {
+        tip_number: Mutex<u64>,
+        committed: Mutex<Option<u64>>,
+        in_pool: Mutex<bool>,
+        sent: Mutex<Vec<Transaction>>,
+        reject_send: bool,
+        duplicated_send: bool,
+        reject_dry_run: bool,
+    }
+
+    #[async_trait]
+    impl SubmissionRPC for MockRPC {
+        async fn get_tip_number(&self) -> Result<u64> {
+            Ok(*self.tip_number.lock())
+        }
+
+        async fn get_transaction_block_number(&self, _tx_hash: H256) -> Result<Option<u64>> {
+            Ok(*self.committed.lock())
+        }
+
+        async fn get_transaction_status(&self, _tx_hash: H256) -> Result<Option<TxStatus>> {
+            if self.committed.lock().is_some() {
+                Ok(Some(TxStatus::Committed))
+            } else if *self.in_pool.lock() {
+                Ok(Some(TxStatus::Pending))
+            } else {
+                Ok(None)
+            }
+        }
+
+        async fn send_transaction(&self, tx: Transaction) -> Result<H256> {
+            if self.reject_send {
+                return Err(anyhow!("TransactionFailedToResolve"));
+            }
+            if self.duplicated_send {
+                return Err(JsonRpcError {
+                    code: -1107,
+                    message: "PoolRejectedDuplicatedTransaction".to_string(),
+                }
+                .into());
+            }
+            self.sent.lock().push(tx.clone());
+            Ok(tx.hash().into())
+        }
+
+        async fn dry_run_transaction(&self, _tx: Transaction) -> Result<u64> {
+            if self.reject_dry_run {
+                return Err(anyhow!("TransactionFailedToVerify"));
+            }
+            Ok(1000)
+        }
+    }
+
+    #[test]
+    fn test_resubmit_orphaned_submission() {
+        let rpc = MockRPC::default();
+        let tx = Transaction::default();
+        let mut tracker = SubmissionTracker::new(3);
+        tracker.track(1, tx.clone(), tx.hash().into());
+
+        // in tx pool
+        *rpc.tip_number.lock() = 100;
+        *rpc.in_pool.lock() = true;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Pending);
+
+        // committed but not deep enough
+        *rpc.committed.lock() = Some(101);
+        *rpc.tip_number.lock() = 102;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Pending);
+
+        // orphaned by l1 reorg within the window
+        *rpc.committed.lock() = None;
+        *rpc.in_pool.lock() = false;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Resubmitted);
+        assert_eq!(rpc.sent.lock().as_slice(), &[tx]);
+        assert!(tracker.is_pending());
+
+        // committed again and confirmed
+        *rpc.committed.lock() = Some(103);
+        *rpc.tip_number.lock() = 106;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Confirmed);
+        assert!(!tracker.is_pending());
+    }
+
+    #[test]
+    fn test_resubmit_evicted_submission() {
+        let rpc = MockRPC::default();
+        let tx = Transaction::default();
+        let mut tracker = SubmissionTracker::new(3);
+        tracker.track(1, tx.clone(), tx.hash().into());
+
+        *rpc.tip_number.lock() = 100;
+        *rpc.in_pool.lock() = true;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Pending);
+
+        // evicted from tx pool before it is ever committed
+        *rpc.in_pool.lock() = false;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Resubmitted);
+        assert_eq!(rpc.sent.lock().as_slice(), &[tx]);
+        assert!(tracker.is_pending());
+
+        *rpc.committed.lock() = Some(101);
+        *rpc.tip_number.lock() = 104;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Confirmed);
+    }
+
+    #[test]
+    fn test_drop_rejected_submission() {
+        let rpc = MockRPC {
+            reject_send: true,
+            ..Default::default()
+        };
+        let tx = Transaction::default();
+        let mut tracker = SubmissionTracker::new(3);
+        tracker.track(1, tx.clone(), tx.hash().into());
+
+        // rejected by tx pool, and can't be sent again
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Dropped);
+        assert!(!tracker.is_pending());
+    }
+
+    #[test]
+    fn test_drop_unsendable_orphaned_submission() {
+        let rpc = MockRPC {
+            reject_send: true,
+            ..Default::default()
+        };
+        let tx = Transaction::default();
+        let mut tracker = SubmissionTracker::new(3);
+        tracker.track(1, tx.clone(), tx.hash().into());
+
+        *rpc.committed.lock() = Some(101);
+        *rpc.tip_number.lock() = 101;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Pending);
+
+        *rpc.committed.lock() = None;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Dropped);
+        assert!(!tracker.is_pending());
+    }
+
+    #[test]
+    fn test_duplicated_orphaned_submission() {
+        let rpc = MockRPC {
+            duplicated_send: true,
+            ..Default::default()
+        };
+        let tx = Transaction::default();
+        let mut tracker = SubmissionTracker::new(3);
+        tracker.track(1, tx.clone(), tx.hash().into());
+
+        *rpc.committed.lock() = Some(101);
+        *rpc.tip_number.lock() = 101;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Pending);
+
+        *rpc.committed.lock() = None;
+        let status = smol::block_on(tracker.poll(&rpc)).unwrap();
+        assert_eq!(status, SubmissionStatus::Resubmitted);
+        assert!(tracker.is_pending());
+    }
+
+    #[test]
+    fn test_dry_run_before_submit() {
+        let rpc = MockRPC {
+            reject_dry_run: true,
+            ..Default::default()
+        };
+        let tx = Transaction::default();
+
+        let result = smol::block_on(submit_transaction(&rpc, tx.clone(), true, "test"));
+        assert!(result.is_err());
+        assert!(rpc.sent.lock().is_empty());
+
+        let result = smol::block_on(submit_transaction(&rpc, tx.clone(), false, "test"));
+        assert!(result.is_ok());
+        assert_eq!(rpc.sent.lock().as_slice(), &[tx]);
+    }
+
+    #[test]
+    fn test_zero_confirmation_depth() {
+        let tx = Transaction::default();
+        let mut tracker = SubmissionTracker::new(0);
+        tracker.track(1, tx.clone(), tx.hash().into());
+        assert!(!tracker.is_pending());
+    }
+}
@@ -48,6 +48,10 @@ impl TransactionSkeleton {
         &mut self.inputs
     }
 
+    pub fn cell_deps(&self) -> &Vec<CellDep> {
+        &self.cell_deps
+    }
+
     pub fn cell_deps_mut(&mut self) -> &mut Vec<CellDep> {
         &mut self.cell_deps
     }
@@ -1,8 +1,11 @@
+use gw_common::CKB_SUDT_SCRIPT_ARGS;
 use gw_types::{
     bytes::Bytes,
     packed::{Block, CellInput, CellOutput, NumberHash, OutPoint},
 };
 
+use std::collections::HashMap;
+
 #[derive(Debug, Clone)]
 pub struct CellInfo {
     pub out_point: OutPoint,
@@ -10,6 +13,50 @@ pub struct CellInfo {
     pub data: Bytes,
 }
 
+/// Capacity and sudt amount held by a cell, `sudt_script_hash` is
+/// `CKB_SUDT_SCRIPT_ARGS` for cells without sudt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellValue {
+    pub capacity: u64,
+    pub sudt_script_hash: [u8; 32],
+    pub amount: u128,
+}
+
+/// Sum capacity and sudt amounts (by sudt script hash) of cells
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CellValueAggregator {
+    pub capacity: u128,
+    pub sudt: HashMap<[u8; 32], u128>,
+}
+
+impl CellValueAggregator {
+    pub fn add(&mut self, value: &CellValue) {
+        self.capacity = self.capacity.saturating_add(value.capacity as u128);
+        if CKB_SUDT_SCRIPT_ARGS != value.sudt_script_hash {
+            let amount = self.sudt.entry(value.sudt_script_hash).or_insert(0);
+            *amount = amount.saturating_add(value.amount);
+        }
+    }
+
+    pub fn merge(&mut self, other: CellValueAggregator) {
+        self.capacity = self.capacity.saturating_add(other.capacity);
+        for (sudt_script_hash, other_amount) in other.sudt {
+            let amount = self.sudt.entry(sudt_script_hash).or_insert(0);
+            *amount = amount.saturating_add(other_amount);
+        }
+    }
+}
+
+impl<'a> std::iter::FromIterator<&'a CellValue> for CellValueAggregator {
+    fn from_iter<I: IntoIterator<Item = &'a CellValue>>(iter: I) -> Self {
+        let mut aggregator = CellValueAggregator::default();
+        for value in iter {
+            aggregator.add(value);
+        }
+        aggregator
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct InputCellInfo {
     pub input: CellInput,
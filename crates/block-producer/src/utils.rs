@@ -1,11 +1,13 @@
 #![allow(clippy::clippy::mutable_key_type)]
 
 use crate::debugger;
-use crate::types::InputCellInfo;
+use crate::types::{CellInfo, CellValue, InputCellInfo};
 use crate::{rpc_client::RPCClient, transaction_skeleton::TransactionSkeleton};
 use anyhow::{anyhow, Result};
 use async_jsonrpc_client::Output;
-use gw_common::{blake2b::new_blake2b, H256};
+use async_trait::async_trait;
+use gw_common::{blake2b::new_blake2b, CKB_SUDT_SCRIPT_ARGS, H256};
+use gw_config::DumpFormat;
 use gw_types::{
     core::DepType,
     packed::{Block, CellDep, CellInput, CellOutput, Header, OutPoint, Script, Transaction},
@@ -13,16 +15,77 @@ use gw_types::{
 };
 use serde::de::DeserializeOwned;
 use serde_json::from_value;
-use std::path::Path;
+use std::{collections::HashSet, path::Path};
+
+/// Known CKB rpc error codes, see ckb `rpc/src/error.rs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CKBErrorKind {
+    TransactionFailedToResolve,
+    TransactionFailedToVerify,
+    PoolRejectedTransactionByMinFeeRate,
+    PoolIsFull,
+    PoolRejectedDuplicatedTransaction,
+    PoolRejectedMalformedTransaction,
+    Other,
+}
+
+impl From<i64> for CKBErrorKind {
+    fn from(code: i64) -> Self {
+        match code {
+            -301 => CKBErrorKind::TransactionFailedToResolve,
+            -302 => CKBErrorKind::TransactionFailedToVerify,
+            -1104 => CKBErrorKind::PoolRejectedTransactionByMinFeeRate,
+            -1106 => CKBErrorKind::PoolIsFull,
+            -1107 => CKBErrorKind::PoolRejectedDuplicatedTransaction,
+            -1108 => CKBErrorKind::PoolRejectedMalformedTransaction,
+            _ => CKBErrorKind::Other,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("JSONRPC error: code {code}, {message}")]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl JsonRpcError {
+    pub fn kind(&self) -> CKBErrorKind {
+        self.code.into()
+    }
+
+    /// Downcast rpc error from `to_result`
+    pub fn from_anyhow(err: &anyhow::Error) -> Option<&JsonRpcError> {
+        err.downcast_ref::<JsonRpcError>()
+    }
+}
 
-// convert json output to result
+// convert json output to result, failure is returned as `JsonRpcError`
 pub fn to_result<T: DeserializeOwned>(output: Output) -> Result<T> {
     match output {
         Output::Success(success) => Ok(from_value(success.result)?),
-        Output::Failure(failure) => Err(anyhow!("JSONRPC error: {}", failure.error)),
+        Output::Failure(failure) => Err(JsonRpcError {
+            code: failure.error.code.code(),
+            message: failure.error.message,
+        }
+        .into()),
     }
 }
 
+/// Payment cells returned by an indexer don't sum to the requested capacity,
+/// both are totals over all queries of a tx
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("payment cells under collected: requested {requested}, collected {collected}")]
+pub struct UnderCollected {
+    pub requested: u64,
+    pub collected: u64,
+}
+
+// Max payment cells queries in `fill_tx_fee`, bounds retries against an
+// indexer that keeps returning too few cells
+const MAX_PAYMENT_CELLS_QUERIES: usize = 3;
+
 /// Calculate tx fee
 /// TODO accept fee rate args
 fn calculate_required_tx_fee(tx_size: usize) -> u64 {
@@ -30,10 +93,32 @@ fn calculate_required_tx_fee(tx_size: usize) -> u64 {
     tx_size as u64
 }
 
+#[async_trait]
+pub trait PaymentCellSource {
+    async fn query_payment_cells(
+        &self,
+        lock: Script,
+        required_capacity: u64,
+        taken_outpoints: &HashSet<OutPoint>,
+    ) -> Result<Vec<CellInfo>>;
+}
+
+#[async_trait]
+impl PaymentCellSource for RPCClient {
+    async fn query_payment_cells(
+        &self,
+        lock: Script,
+        required_capacity: u64,
+        taken_outpoints: &HashSet<OutPoint>,
+    ) -> Result<Vec<CellInfo>> {
+        RPCClient::query_payment_cells(self, lock, required_capacity, taken_outpoints).await
+    }
+}
+
 /// Add fee cell to tx skeleton
 pub async fn fill_tx_fee(
     tx_skeleton: &mut TransactionSkeleton,
-    rpc_client: &RPCClient,
+    rpc_client: &(impl PaymentCellSource + Sync),
     lock_script: Script,
 ) -> Result<()> {
     const CHANGE_CELL_CAPACITY: u64 = 61_00000000;
@@ -82,6 +167,8 @@ pub async fn fill_tx_fee(
     required_fee += CHANGE_CELL_CAPACITY;
 
     let mut change_capacity = 0;
+    let mut queries = 0;
+    let mut total_collected = 0u64;
     while required_fee > 0 {
         // to filter used input cells
         let taken_outpoints = tx_skeleton.taken_outpoints()?;
@@ -89,7 +176,20 @@ pub async fn fill_tx_fee(
         let cells = rpc_client
             .query_payment_cells(lock_script.clone(), required_fee, &taken_outpoints)
             .await?;
-        assert!(!cells.is_empty(), "need cells to pay fee");
+        queries += 1;
+
+        let collected = cells.iter().fold(0u64, |sum, cell| {
+            sum.saturating_add(cell.output.capacity().unpack())
+        });
+        let requested = total_collected.saturating_add(required_fee);
+        total_collected = total_collected.saturating_add(collected);
+        if cells.is_empty() || (collected < required_fee && queries >= MAX_PAYMENT_CELLS_QUERIES) {
+            return Err(UnderCollected {
+                requested,
+                collected: total_collected,
+            }
+            .into());
+        }
 
         // put cells in tx skeleton
         tx_skeleton
@@ -290,6 +390,32 @@ impl CKBGenesisInfo {
     }
 }
 
+/// Capacity and sudt value of a cell, sudt amount is 0 if data is too
+/// short to hold one
+pub fn fetch_capacity_and_sudt_value(cell: &CellInfo) -> CellValue {
+    let capacity: u64 = cell.output.capacity().unpack();
+    let (sudt_script_hash, amount) = match cell.output.type_().to_opt() {
+        Some(type_script) => {
+            let amount = match cell.data.get(..16) {
+                Some(data) => {
+                    let mut buf = [0u8; 16];
+                    buf.copy_from_slice(data);
+                    u128::from_le_bytes(buf)
+                }
+                None => 0,
+            };
+            (type_script.hash(), amount)
+        }
+        None => (CKB_SUDT_SCRIPT_ARGS, 0),
+    };
+
+    CellValue {
+        capacity,
+        sudt_script_hash,
+        amount,
+    }
+}
+
 pub fn is_debug_env_var_set() -> bool {
     match std::env::var("GODWOKEN_DEBUG") {
         Ok(s) => s.to_lowercase().trim() == "true",
@@ -316,8 +442,16 @@ pub async fn dry_run_transaction(rpc_client: &RPCClient, tx: Transaction, action
     }
 }
 
-pub async fn dump_transaction<P: AsRef<Path>>(dir: P, rpc_client: &RPCClient, tx: Transaction) {
-    if let Err(err) = debugger::dump_transaction(dir, rpc_client, tx.clone()).await {
+pub async fn dump_transaction<P: AsRef<Path>>(
+    dir: P,
+    max_files: usize,
+    formats: &[DumpFormat],
+    rpc_client: &RPCClient,
+    tx: Transaction,
+) {
+    if let Err(err) =
+        debugger::dump_transaction(dir, max_files, formats, rpc_client, tx.clone()).await
+    {
         log::error!(
             "Faild to dump transaction {} error: {}",
             hex::encode(&tx.hash()),
@@ -325,3 +459,235 @@ pub async fn dump_transaction<P: AsRef<Path>>(dir: P, rpc_client: &RPCClient, tx
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        fetch_capacity_and_sudt_value, fill_tx_fee, CKBErrorKind, CellInfo, InputCellInfo,
+        JsonRpcError, PaymentCellSource, TransactionSkeleton, UnderCollected,
+        MAX_PAYMENT_CELLS_QUERIES,
+    };
+    use crate::types::{CellValue, CellValueAggregator};
+
+    use anyhow::Result;
+    use async_trait::async_trait;
+    use gw_types::{
+        packed::{CellInput, CellOutput, OutPoint, Script},
+        prelude::*,
+    };
+    use parking_lot::Mutex;
+    use std::collections::HashSet;
+
+    #[derive(Default)]
+    struct MockPaymentCells {
+        cells: Vec<CellInfo>,
+        queries: Mutex<usize>,
+    }
+
+    #[async_trait]
+    impl PaymentCellSource for MockPaymentCells {
+        async fn query_payment_cells(
+            &self,
+            _lock: Script,
+            required_capacity: u64,
+            taken_outpoints: &HashSet<OutPoint>,
+        ) -> Result<Vec<CellInfo>> {
+            *self.queries.lock() += 1;
+
+            let mut collected = Vec::new();
+            let mut collected_capacity = 0u64;
+            let cells = self
+                .cells
+                .iter()
+                .filter(|cell| !taken_outpoints.contains(&cell.out_point));
+            for cell in cells {
+                if collected_capacity >= required_capacity {
+                    break;
+                }
+                collected_capacity += cell.output.capacity().unpack();
+                collected.push(cell.clone());
+            }
+            Ok(collected)
+        }
+    }
+
+    // Always returns a single new dust cell
+    #[derive(Default)]
+    struct UnderCollectingPaymentCells {
+        queries: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl PaymentCellSource for UnderCollectingPaymentCells {
+        async fn query_payment_cells(
+            &self,
+            _lock: Script,
+            _required_capacity: u64,
+            _taken_outpoints: &HashSet<OutPoint>,
+        ) -> Result<Vec<CellInfo>> {
+            let mut queries = self.queries.lock();
+            *queries += 1;
+            Ok(vec![cell(*queries, 1000)])
+        }
+    }
+
+    fn lock_script() -> Script {
+        Script::new_builder().args(vec![1u8; 20].pack()).build()
+    }
+
+    fn cell(index: u32, capacity: u64) -> CellInfo {
+        CellInfo {
+            out_point: OutPoint::new_builder().index(index.pack()).build(),
+            output: CellOutput::new_builder()
+                .lock(lock_script())
+                .capacity(capacity.pack())
+                .build(),
+            data: Default::default(),
+        }
+    }
+
+    fn tx_skeleton_with_input(capacity: u64) -> TransactionSkeleton {
+        let mut tx_skeleton = TransactionSkeleton::default();
+        let cell = cell(0, capacity);
+        tx_skeleton.inputs_mut().push(InputCellInfo {
+            input: CellInput::new_builder()
+                .previous_output(cell.out_point.clone())
+                .build(),
+            cell,
+        });
+        tx_skeleton
+    }
+
+    // Fee paid equals the required fee of the filled tx
+    fn assert_exact_fee(tx_skeleton: &TransactionSkeleton) {
+        let tx_size = tx_skeleton.tx_in_block_size().unwrap();
+        assert_eq!(tx_skeleton.calculate_fee().unwrap(), tx_size as u64);
+    }
+
+    #[test]
+    fn test_fill_tx_fee_with_change_only() {
+        let rpc = MockPaymentCells::default();
+        let mut tx_skeleton = tx_skeleton_with_input(1000_00000000);
+
+        smol::block_on(fill_tx_fee(&mut tx_skeleton, &rpc, lock_script())).unwrap();
+        assert_eq!(*rpc.queries.lock(), 0);
+        assert_eq!(tx_skeleton.inputs().len(), 1);
+        assert_eq!(tx_skeleton.outputs().len(), 1);
+        assert_exact_fee(&tx_skeleton);
+    }
+
+    #[test]
+    fn test_fill_tx_fee_with_extra_cell() {
+        let rpc = MockPaymentCells {
+            cells: vec![cell(1, 100_00000000), cell(2, 100_00000000)],
+            ..Default::default()
+        };
+        let mut tx_skeleton = tx_skeleton_with_input(10_00000000);
+
+        smol::block_on(fill_tx_fee(&mut tx_skeleton, &rpc, lock_script())).unwrap();
+        assert_eq!(*rpc.queries.lock(), 1);
+        assert_eq!(tx_skeleton.inputs().len(), 2);
+        assert_eq!(tx_skeleton.outputs().len(), 1);
+        assert_exact_fee(&tx_skeleton);
+    }
+
+    #[test]
+    fn test_fill_tx_fee_without_cells() {
+        let rpc = MockPaymentCells::default();
+        let mut tx_skeleton = tx_skeleton_with_input(10_00000000);
+
+        let result = smol::block_on(fill_tx_fee(&mut tx_skeleton, &rpc, lock_script()));
+        assert!(result.is_err());
+        assert!(tx_skeleton.outputs().is_empty());
+    }
+
+    #[test]
+    fn test_fill_tx_fee_under_collected() {
+        let rpc = UnderCollectingPaymentCells::default();
+        let mut tx_skeleton = tx_skeleton_with_input(10_00000000);
+
+        let err = smol::block_on(fill_tx_fee(&mut tx_skeleton, &rpc, lock_script())).unwrap_err();
+        let err = err
+            .downcast_ref::<UnderCollected>()
+            .expect("under collected");
+        // A 1000 shannons cell per query
+        assert_eq!(err.collected, 3000);
+        assert!(err.requested > err.collected);
+        assert_eq!(*rpc.queries.lock() as usize, MAX_PAYMENT_CELLS_QUERIES);
+        assert!(tx_skeleton.outputs().is_empty());
+    }
+
+    #[test]
+    fn test_json_rpc_error_kind() {
+        let kind = |code: i64| {
+            let err: anyhow::Error = JsonRpcError {
+                code,
+                message: "rejected".to_string(),
+            }
+            .into();
+            JsonRpcError::from_anyhow(&err).map(JsonRpcError::kind)
+        };
+
+        assert_eq!(kind(-301), Some(CKBErrorKind::TransactionFailedToResolve));
+        assert_eq!(kind(-302), Some(CKBErrorKind::TransactionFailedToVerify));
+        assert_eq!(
+            kind(-1104),
+            Some(CKBErrorKind::PoolRejectedTransactionByMinFeeRate)
+        );
+        assert_eq!(
+            kind(-1107),
+            Some(CKBErrorKind::PoolRejectedDuplicatedTransaction)
+        );
+        assert_eq!(kind(-1), Some(CKBErrorKind::Other));
+
+        let err = anyhow::anyhow!("not a rpc error");
+        assert!(JsonRpcError::from_anyhow(&err).is_none());
+    }
+
+    fn sudt_cell(capacity: u64, sudt_args: u8, amount: u128) -> CellInfo {
+        let type_script = Script::new_builder()
+            .args(vec![sudt_args; 32].pack())
+            .build();
+        CellInfo {
+            out_point: OutPoint::default(),
+            output: CellOutput::new_builder()
+                .capacity(capacity.pack())
+                .type_(Some(type_script).pack())
+                .build(),
+            data: amount.to_le_bytes().to_vec().into(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_cell_values() {
+        let sudt_a = sudt_cell(200, 1, 10);
+        let sudt_a_hash = sudt_a.output.type_().to_opt().unwrap().hash();
+        let sudt_b = sudt_cell(300, 2, 20);
+        let sudt_b_hash = sudt_b.output.type_().to_opt().unwrap().hash();
+
+        let ckb_value = fetch_capacity_and_sudt_value(&cell(0, 100));
+        assert_eq!(
+            ckb_value,
+            CellValue {
+                capacity: 100,
+                sudt_script_hash: [0u8; 32],
+                amount: 0,
+            }
+        );
+        let sudt_a_value = fetch_capacity_and_sudt_value(&sudt_a);
+        assert_eq!(sudt_a_value.sudt_script_hash, sudt_a_hash);
+        assert_eq!(sudt_a_value.amount, 10);
+
+        let values: Vec<CellValue> = vec![cell(0, 100), sudt_a.clone(), sudt_b, sudt_a]
+            .iter()
+            .map(fetch_capacity_and_sudt_value)
+            .collect();
+        let mut aggregator: CellValueAggregator = values[..2].iter().collect();
+        aggregator.merge(values[2..].iter().collect());
+
+        assert_eq!(aggregator.capacity, 800);
+        assert_eq!(aggregator.sudt.len(), 2);
+        assert_eq!(aggregator.sudt.get(&sudt_a_hash), Some(&20));
+        assert_eq!(aggregator.sudt.get(&sudt_b_hash), Some(&20));
+    }
+}
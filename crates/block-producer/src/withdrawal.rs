@@ -1,27 +1,32 @@
+use crate::transaction_skeleton::TransactionSkeleton;
 use crate::types::InputCellInfo;
+use crate::utils::{fill_tx_fee, PaymentCellSource};
 use crate::{
     rpc_client::{CollectedCustodianCells, RPCClient, WithdrawalsAmount},
     types::CellInfo,
 };
 
 use anyhow::{anyhow, Result};
-use gw_common::CKB_SUDT_SCRIPT_ARGS;
+use async_trait::async_trait;
+use gw_common::{CKB_SUDT_SCRIPT_ARGS, H256};
 use gw_config::BlockProducerConfig;
 use gw_generator::RollupContext;
+use gw_store::Store;
 use gw_types::{
     bytes::Bytes,
-    core::ScriptHashType,
+    core::{DepType, ScriptHashType},
     packed::{
         CellDep, CellInput, CellOutput, CustodianLockArgs, DepositLockArgs, GlobalState, L2Block,
-        RollupAction, RollupActionUnion, Script, UnlockWithdrawalViaRevert,
-        UnlockWithdrawalWitness, UnlockWithdrawalWitnessUnion, WithdrawalLockArgs,
-        WithdrawalRequest, WitnessArgs,
+        RollupAction, RollupActionUnion, Script, Transaction, UnlockWithdrawalViaFinalize,
+        UnlockWithdrawalViaRevert, UnlockWithdrawalWitness, UnlockWithdrawalWitnessUnion,
+        WithdrawalLockArgs, WithdrawalLockArgsReader, WithdrawalRequest, WitnessArgs,
     },
     prelude::*,
 };
 
 use std::{
     collections::{HashMap, HashSet},
+    ops::Range,
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -43,8 +48,16 @@ impl Default for AvailableCustodians {
 impl<'a> From<&'a CollectedCustodianCells> for AvailableCustodians {
     fn from(collected: &'a CollectedCustodianCells) -> Self {
         AvailableCustodians {
-            capacity: collected.capacity,
-            sudt: collected.sudt.clone(),
+            capacity: collected.value.capacity,
+            sudt: collected
+                .value
+                .sudt
+                .iter()
+                .map(|(sudt_script_hash, amount)| {
+                    let script = collected.sudt_scripts.get(sudt_script_hash).cloned();
+                    (*sudt_script_hash, (*amount, script.unwrap_or_default()))
+                })
+                .collect(),
         }
     }
 }
@@ -376,7 +389,7 @@ pub async fn revert(
         .collect();
 
     let reverted_withdrawal_cells = rpc_client
-        .query_withdrawal_cells_by_block_hashes(&reverted_block_hashes)
+        .query_withdrawal_cells_by_block_hashes(&reverted_block_hashes, None)
         .await?;
     if reverted_withdrawal_cells.is_empty() {
         return Ok(None);
@@ -475,6 +488,263 @@ pub async fn revert(
     }))
 }
 
+/// Build transaction paying finalized withdrawal cells out to their owner,
+/// tx fee is paid by owner cells
+pub async fn build_withdrawal_payout(
+    rollup_context: &RollupContext,
+    block_producer_config: &BlockProducerConfig,
+    rpc_client: &(impl PaymentCellSource + Sync),
+    rollup_cell: &CellInfo,
+    withdrawal_cells: Vec<CellInfo>,
+    owner_lock: Script,
+    owner_lock_dep: CellDep,
+) -> Result<TransactionSkeleton> {
+    let mut tx_skeleton = build_payout_skeleton(
+        rollup_context,
+        block_producer_config,
+        rollup_cell,
+        withdrawal_cells,
+        &owner_lock,
+    )?;
+
+    // Withdrawal lock requires owner cell in inputs, which is added by fee payment
+    tx_skeleton.cell_deps_mut().push(owner_lock_dep);
+    fill_tx_fee(&mut tx_skeleton, rpc_client, owner_lock).await?;
+
+    Ok(tx_skeleton)
+}
+
+fn build_payout_skeleton(
+    rollup_context: &RollupContext,
+    block_producer_config: &BlockProducerConfig,
+    rollup_cell: &CellInfo,
+    withdrawal_cells: Vec<CellInfo>,
+    owner_lock: &Script,
+) -> Result<TransactionSkeleton> {
+    if withdrawal_cells.is_empty() {
+        return Err(anyhow!("no withdrawal cell to pay out"));
+    }
+
+    let global_state = GlobalState::from_slice(&rollup_cell.data)?;
+    let last_finalized_block_number: u64 = global_state.last_finalized_block_number().unpack();
+    let owner_lock_hash = owner_lock.hash();
+
+    let mut tx_skeleton = TransactionSkeleton::default();
+    for withdrawal in withdrawal_cells {
+        let lock_args = parse_withdrawal_lock_args(rollup_context, &withdrawal.output.lock())?;
+        let withdrawal_block_number: u64 = lock_args.withdrawal_block_number().unpack();
+        if withdrawal_block_number > last_finalized_block_number {
+            return Err(anyhow!(
+                "withdrawal cell from block {} isn't finalized",
+                withdrawal_block_number
+            ));
+        }
+        if lock_args.owner_lock_hash().as_slice() != &owner_lock_hash[..] {
+            return Err(anyhow!("withdrawal cell owner lock hash mismatch"));
+        }
+
+        let payout_output = {
+            let output_builder = withdrawal.output.clone().as_builder();
+            output_builder.lock(owner_lock.to_owned()).build()
+        };
+
+        let withdrawal_input = {
+            let input = CellInput::new_builder()
+                .previous_output(withdrawal.out_point.clone())
+                .build();
+
+            InputCellInfo {
+                input,
+                cell: withdrawal.clone(),
+            }
+        };
+
+        let unlock_withdrawal_witness = UnlockWithdrawalWitness::new_builder()
+            .set(UnlockWithdrawalWitnessUnion::UnlockWithdrawalViaFinalize(
+                UnlockWithdrawalViaFinalize::default(),
+            ))
+            .build();
+        let withdrawal_witness_args = WitnessArgs::new_builder()
+            .lock(Some(unlock_withdrawal_witness.as_bytes()).pack())
+            .build();
+
+        tx_skeleton.inputs_mut().push(withdrawal_input);
+        tx_skeleton.witnesses_mut().push(withdrawal_witness_args);
+        tx_skeleton
+            .outputs_mut()
+            .push((payout_output, withdrawal.data));
+    }
+
+    // Withdrawal lock reads finalized block number from rollup cell
+    let rollup_cell_dep = CellDep::new_builder()
+        .out_point(rollup_cell.out_point.clone())
+        .dep_type(DepType::Code.into())
+        .build();
+    let withdrawal_lock_dep = block_producer_config.withdrawal_cell_lock_dep.clone();
+    let rollup_config_dep = block_producer_config.rollup_config_cell_dep.clone();
+    tx_skeleton.cell_deps_mut().extend(vec![
+        rollup_cell_dep,
+        rollup_config_dep.into(),
+        withdrawal_lock_dep.into(),
+    ]);
+    if tx_skeleton
+        .inputs()
+        .iter()
+        .any(|info| info.cell.output.type_().to_opt().is_some())
+    {
+        let sudt_type_dep = block_producer_config.l1_sudt_type_dep.clone();
+        tx_skeleton.cell_deps_mut().push(sudt_type_dep.into());
+    }
+
+    Ok(tx_skeleton)
+}
+
+fn parse_withdrawal_lock_args(
+    rollup_context: &RollupContext,
+    lock: &Script,
+) -> Result<WithdrawalLockArgs> {
+    let args: Bytes = lock.args().unpack();
+    let is_withdrawal_lock = lock.code_hash()
+        == rollup_context.rollup_config.withdrawal_script_type_hash()
+        && lock.hash_type() == ScriptHashType::Type.into()
+        && args.len() > 32
+        && &args[..32] == rollup_context.rollup_script_hash.as_slice();
+    if !is_withdrawal_lock {
+        return Err(anyhow!("not a withdrawal cell of this rollup"));
+    }
+
+    match WithdrawalLockArgsReader::verify(&args[32..], false) {
+        Ok(()) => Ok(WithdrawalLockArgs::new_unchecked(args.slice(32..))),
+        Err(_) => Err(anyhow!("invalid withdrawal lock args")),
+    }
+}
+
+#[async_trait]
+pub trait CommittedTransactionQuery {
+    async fn get_transaction(&self, tx_hash: H256) -> Result<Option<Transaction>>;
+}
+
+#[async_trait]
+impl CommittedTransactionQuery for RPCClient {
+    async fn get_transaction(&self, tx_hash: H256) -> Result<Option<Transaction>> {
+        RPCClient::get_transaction(self, tx_hash).await
+    }
+}
+
+/// Withdrawal request which doesn't have a withdrawal cell created by its
+/// block submission transaction
+#[derive(Debug)]
+pub struct UnmatchedWithdrawal {
+    pub block_number: u64,
+    pub block_hash: [u8; 32],
+    pub withdrawal_index: usize,
+    pub withdrawal: WithdrawalRequest,
+}
+
+/// Collect local blocks in range along with their layer1 submission tx hash
+pub fn committed_blocks(store: &Store, range: Range<u64>) -> Result<Vec<(L2Block, H256)>> {
+    let db = store.begin_transaction();
+    let mut blocks = Vec::new();
+    for number in range {
+        let block_hash = db
+            .get_block_hash_by_number(number)?
+            .ok_or_else(|| anyhow!("block {} not found", number))?;
+        let block = db
+            .get_block(&block_hash)?
+            .ok_or_else(|| anyhow!("block {} not found", number))?;
+        let committed_info = store
+            .get_l2block_committed_info(&block_hash)?
+            .ok_or_else(|| anyhow!("block {} committed info not found", number))?;
+        let tx_hash: [u8; 32] = committed_info.transaction_hash().unpack();
+        blocks.push((block, tx_hash.into()));
+    }
+    Ok(blocks)
+}
+
+/// Custodian cells are consumed into withdrawal cells by the block submission
+/// transaction, report withdrawals whose withdrawal cell is missing there.
+/// A withdrawal cell must match lock, capacity and sudt amount of request,
+/// and covers one withdrawal only.
+pub async fn reconcile(
+    rollup_context: &RollupContext,
+    query: &(impl CommittedTransactionQuery + Sync),
+    committed_blocks: Vec<(L2Block, H256)>,
+) -> Result<Vec<UnmatchedWithdrawal>> {
+    let mut unmatched = Vec::new();
+    for (block, tx_hash) in committed_blocks {
+        if block.withdrawals().is_empty() {
+            continue;
+        }
+
+        let submit_tx = query.get_transaction(tx_hash).await?;
+        let mut outputs: Vec<(CellOutput, Bytes, bool)> = match submit_tx {
+            Some(tx) => {
+                let outputs = tx.raw().outputs().into_iter();
+                let outputs_data = tx.raw().outputs_data().into_iter();
+                let to_unused =
+                    |(output, data): (_, gw_types::packed::Bytes)| (output, data.unpack(), false);
+                outputs.zip(outputs_data).map(to_unused).collect()
+            }
+            None => Vec::new(),
+        };
+
+        for (withdrawal_index, withdrawal) in block.withdrawals().into_iter().enumerate() {
+            let lock_hash = build_withdrawal_lock(&withdrawal, rollup_context, &block).hash();
+            let matched = outputs.iter_mut().find(|(output, data, used)| {
+                !*used && is_withdrawal_cell_of(&withdrawal, lock_hash, output, data)
+            });
+            match matched {
+                Some((_, _, used)) => *used = true,
+                None => {
+                    let block_number = block.raw().number().unpack();
+                    log::warn!(
+                        "block {} withdrawal {} has no withdrawal cell",
+                        block_number,
+                        withdrawal_index
+                    );
+                    unmatched.push(UnmatchedWithdrawal {
+                        block_number,
+                        block_hash: block.hash(),
+                        withdrawal_index,
+                        withdrawal,
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(unmatched)
+}
+
+fn is_withdrawal_cell_of(
+    req: &WithdrawalRequest,
+    lock_hash: [u8; 32],
+    output: &CellOutput,
+    data: &Bytes,
+) -> bool {
+    let capacity: u64 = output.capacity().unpack();
+    if output.lock().hash() != lock_hash || capacity != req.raw().capacity().unpack() {
+        return false;
+    }
+
+    let req_sudt: u128 = req.raw().amount().unpack();
+    if 0 == req_sudt {
+        return true;
+    }
+
+    let sudt_type_hash: [u8; 32] = req.raw().sudt_script_hash().unpack();
+    let type_hash = output.type_().to_opt().map(|type_| type_.hash());
+    let amount = match data.get(..16) {
+        Some(amount) => {
+            let mut buf = [0u8; 16];
+            buf.copy_from_slice(amount);
+            u128::from_le_bytes(buf)
+        }
+        None => return false,
+    };
+    type_hash == Some(sudt_type_hash) && amount == req_sudt
+}
+
 fn sum<Iter: Iterator<Item = WithdrawalRequest>>(reqs: Iter) -> WithdrawalsAmount {
     reqs.fold(
         WithdrawalsAmount::default(),
@@ -601,3 +871,326 @@ fn generate_finalized_custodian(
 
     (output, data.as_bytes())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_types::packed::{
+        CellOutputVec, OutPoint, RawL2Block, RawTransaction, RawWithdrawalRequest, RollupConfig,
+        WithdrawalRequestVec,
+    };
+
+    struct MockRPC {
+        tx_hash: H256,
+        tx: Transaction,
+    }
+
+    #[async_trait]
+    impl CommittedTransactionQuery for MockRPC {
+        async fn get_transaction(&self, tx_hash: H256) -> Result<Option<Transaction>> {
+            if tx_hash == self.tx_hash {
+                Ok(Some(self.tx.clone()))
+            } else {
+                Ok(None)
+            }
+        }
+    }
+
+    fn submit_tx(outputs: Vec<(CellOutput, Bytes)>) -> Transaction {
+        let (outputs, outputs_data): (Vec<_>, Vec<_>) = outputs.into_iter().unzip();
+        let outputs_data: Vec<gw_types::packed::Bytes> =
+            outputs_data.into_iter().map(|data| data.pack()).collect();
+        let raw = RawTransaction::new_builder()
+            .outputs(CellOutputVec::new_builder().set(outputs).build())
+            .outputs_data(outputs_data.pack())
+            .build();
+        Transaction::new_builder().raw(raw).build()
+    }
+
+    fn withdrawal(capacity: u64) -> WithdrawalRequest {
+        let raw = RawWithdrawalRequest::new_builder()
+            .capacity(capacity.pack())
+            .account_script_hash([1u8; 32].pack())
+            .owner_lock_hash([2u8; 32].pack())
+            .build();
+        WithdrawalRequest::new_builder().raw(raw).build()
+    }
+
+    #[test]
+    fn test_reconcile_reports_unmatched_withdrawal() {
+        let rollup_context = RollupContext {
+            rollup_script_hash: [3u8; 32].into(),
+            rollup_config: RollupConfig::default(),
+        };
+
+        let matched = withdrawal(500_00000000);
+        let missing = withdrawal(600_00000000);
+        let block = L2Block::new_builder()
+            .raw(RawL2Block::new_builder().number(10u64.pack()).build())
+            .withdrawals(
+                WithdrawalRequestVec::new_builder()
+                    .push(matched.clone())
+                    .push(missing.clone())
+                    .build(),
+            )
+            .build();
+
+        // Submission tx only creates withdrawal cell for the first request
+        let matched_output = CellOutput::new_builder()
+            .capacity(matched.raw().capacity())
+            .lock(build_withdrawal_lock(&matched, &rollup_context, &block))
+            .build();
+        let tx = submit_tx(vec![(matched_output, Bytes::new())]);
+        let tx_hash: H256 = [4u8; 32].into();
+        let rpc = MockRPC { tx_hash, tx };
+
+        let unmatched = smol::block_on(reconcile(&rollup_context, &rpc, vec![(block, tx_hash)]))
+            .expect("reconcile");
+        assert_eq!(unmatched.len(), 1);
+        assert_eq!(unmatched[0].block_number, 10);
+        assert_eq!(unmatched[0].withdrawal_index, 1);
+        assert_eq!(unmatched[0].withdrawal.as_slice(), missing.as_slice());
+    }
+
+    #[test]
+    fn test_reconcile_matches_withdrawal_cell_once() {
+        let rollup_context = RollupContext {
+            rollup_script_hash: [3u8; 32].into(),
+            rollup_config: RollupConfig::default(),
+        };
+
+        let sudt_type = Script::new_builder().args(vec![6u8; 32].pack()).build();
+        let sudt_withdrawal = {
+            let raw = RawWithdrawalRequest::new_builder()
+                .capacity(300_00000000u64.pack())
+                .amount(100u128.pack())
+                .sudt_script_hash(sudt_type.hash().pack())
+                .account_script_hash([1u8; 32].pack())
+                .owner_lock_hash([2u8; 32].pack())
+                .build();
+            WithdrawalRequest::new_builder().raw(raw).build()
+        };
+        // Same withdrawal twice, their withdrawal cells share the same lock
+        let ckb_withdrawal = withdrawal(500_00000000);
+        let block = L2Block::new_builder()
+            .raw(RawL2Block::new_builder().number(10u64.pack()).build())
+            .withdrawals(
+                WithdrawalRequestVec::new_builder()
+                    .push(ckb_withdrawal.clone())
+                    .push(ckb_withdrawal.clone())
+                    .push(sudt_withdrawal.clone())
+                    .build(),
+            )
+            .build();
+
+        let ckb_lock = build_withdrawal_lock(&ckb_withdrawal, &rollup_context, &block);
+        let sudt_lock = build_withdrawal_lock(&sudt_withdrawal, &rollup_context, &block);
+        let outputs = vec![
+            (
+                CellOutput::new_builder()
+                    .capacity(500_00000000u64.pack())
+                    .lock(ckb_lock.clone())
+                    .build(),
+                Bytes::new(),
+            ),
+            // Capacity less than requested
+            (
+                CellOutput::new_builder()
+                    .capacity(400_00000000u64.pack())
+                    .lock(ckb_lock)
+                    .build(),
+                Bytes::new(),
+            ),
+            // Sudt amount less than requested
+            (
+                CellOutput::new_builder()
+                    .capacity(300_00000000u64.pack())
+                    .lock(sudt_lock)
+                    .type_(Some(sudt_type).pack())
+                    .build(),
+                99u128.pack().as_bytes(),
+            ),
+        ];
+        let tx_hash: H256 = [4u8; 32].into();
+        let rpc = MockRPC {
+            tx_hash,
+            tx: submit_tx(outputs),
+        };
+
+        let unmatched = smol::block_on(reconcile(&rollup_context, &rpc, vec![(block, tx_hash)]))
+            .expect("reconcile");
+        let unmatched_indexes: Vec<usize> = unmatched.iter().map(|w| w.withdrawal_index).collect();
+        assert_eq!(unmatched_indexes, vec![1, 2]);
+    }
+
+    // Rollup finalized up to block 10, and owner lock of withdrawals
+    fn payout_fixture() -> (RollupContext, Script, CellInfo) {
+        let rollup_context = RollupContext {
+            rollup_script_hash: [3u8; 32].into(),
+            rollup_config: RollupConfig::new_builder()
+                .withdrawal_script_type_hash([5u8; 32].pack())
+                .build(),
+        };
+        let owner_lock = Script::new_builder().args(vec![2u8; 20].pack()).build();
+        let rollup_cell = CellInfo {
+            out_point: OutPoint::new_builder().index(0u32.pack()).build(),
+            output: CellOutput::default(),
+            data: GlobalState::new_builder()
+                .last_finalized_block_number(10u64.pack())
+                .build()
+                .as_bytes(),
+        };
+        (rollup_context, owner_lock, rollup_cell)
+    }
+
+    fn payout_withdrawal_cell(
+        rollup_context: &RollupContext,
+        owner_lock: &Script,
+        index: u32,
+        capacity: u64,
+        block_number: u64,
+    ) -> CellInfo {
+        let req = {
+            let raw = RawWithdrawalRequest::new_builder()
+                .capacity(capacity.pack())
+                .account_script_hash([1u8; 32].pack())
+                .owner_lock_hash(owner_lock.hash().pack())
+                .build();
+            WithdrawalRequest::new_builder().raw(raw).build()
+        };
+        let block = L2Block::new_builder()
+            .raw(
+                RawL2Block::new_builder()
+                    .number(block_number.pack())
+                    .build(),
+            )
+            .build();
+        CellInfo {
+            out_point: OutPoint::new_builder().index((index + 1).pack()).build(),
+            output: CellOutput::new_builder()
+                .capacity(capacity.pack())
+                .lock(build_withdrawal_lock(&req, rollup_context, &block))
+                .build(),
+            data: Bytes::default(),
+        }
+    }
+
+    // Owner cells not taken yet, all at once
+    struct OwnerCells(Vec<CellInfo>);
+
+    #[async_trait]
+    impl PaymentCellSource for OwnerCells {
+        async fn query_payment_cells(
+            &self,
+            _lock: Script,
+            _required_capacity: u64,
+            taken_outpoints: &HashSet<OutPoint>,
+        ) -> Result<Vec<CellInfo>> {
+            let cells = self.0.iter();
+            let not_taken = cells.filter(|cell| !taken_outpoints.contains(&cell.out_point));
+            Ok(not_taken.cloned().collect())
+        }
+    }
+
+    #[test]
+    fn test_build_withdrawal_payout() {
+        let (rollup_context, owner_lock, rollup_cell) = payout_fixture();
+        let cells = vec![
+            payout_withdrawal_cell(&rollup_context, &owner_lock, 0, 500_00000000, 9),
+            payout_withdrawal_cell(&rollup_context, &owner_lock, 1, 600_00000000, 10),
+        ];
+        let owner_cell = CellInfo {
+            out_point: OutPoint::new_builder().tx_hash([6u8; 32].pack()).build(),
+            output: CellOutput::new_builder()
+                .capacity(1000_00000000u64.pack())
+                .lock(owner_lock.clone())
+                .build(),
+            data: Bytes::default(),
+        };
+        let owner_lock_dep = CellDep::new_builder()
+            .out_point(OutPoint::new_builder().tx_hash([7u8; 32].pack()).build())
+            .build();
+
+        let tx_skeleton = smol::block_on(build_withdrawal_payout(
+            &rollup_context,
+            &BlockProducerConfig::default(),
+            &OwnerCells(vec![owner_cell.clone()]),
+            &rollup_cell,
+            cells,
+            owner_lock.clone(),
+            owner_lock_dep.clone(),
+        ))
+        .expect("payout");
+
+        // Owner cell is added to pay the fee, and unlocks the withdrawals
+        let inputs = tx_skeleton.inputs();
+        assert_eq!(inputs.len(), 3);
+        assert_eq!(inputs[2].cell.out_point, owner_cell.out_point);
+        assert!(tx_skeleton.cell_deps().contains(&owner_lock_dep));
+
+        // Two payouts and the change back to owner
+        let outputs = tx_skeleton.outputs();
+        assert_eq!(outputs.len(), 3);
+        assert!(outputs
+            .iter()
+            .all(|(output, _)| output.lock() == owner_lock));
+
+        let tx_size = tx_skeleton.tx_in_block_size().unwrap();
+        assert_eq!(tx_skeleton.calculate_fee().unwrap(), tx_size as u64);
+    }
+
+    #[test]
+    fn test_build_payout_skeleton() {
+        let (rollup_context, owner_lock, rollup_cell) = payout_fixture();
+        let config = BlockProducerConfig::default();
+        let withdrawal_cell = |index: u32, capacity: u64, block_number: u64| {
+            payout_withdrawal_cell(&rollup_context, &owner_lock, index, capacity, block_number)
+        };
+
+        let cells = vec![
+            withdrawal_cell(0, 500_00000000, 9),
+            withdrawal_cell(1, 600_00000000, 10),
+        ];
+        let tx_skeleton = build_payout_skeleton(
+            &rollup_context,
+            &config,
+            &rollup_cell,
+            cells.clone(),
+            &owner_lock,
+        )
+        .expect("payout");
+
+        assert_eq!(tx_skeleton.inputs().len(), 2);
+        assert_eq!(tx_skeleton.witnesses().len(), 2);
+        let outputs = tx_skeleton.outputs();
+        assert_eq!(outputs.len(), 2);
+        for ((output, _data), cell) in outputs.iter().zip(cells.iter()) {
+            assert_eq!(output.lock(), owner_lock);
+            assert_eq!(
+                output.capacity().as_slice(),
+                cell.output.capacity().as_slice()
+            );
+        }
+        // All capacity goes to owner, fee must be covered by owner cells
+        assert_eq!(tx_skeleton.calculate_fee().unwrap(), 0);
+
+        // Not finalized
+        let unfinalized = vec![withdrawal_cell(2, 500_00000000, 11)];
+        let err = build_payout_skeleton(
+            &rollup_context,
+            &config,
+            &rollup_cell,
+            unfinalized,
+            &owner_lock,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("isn't finalized"));
+
+        // Other owner
+        let other_owner = Script::new_builder().args(vec![4u8; 20].pack()).build();
+        let err =
+            build_payout_skeleton(&rollup_context, &config, &rollup_cell, cells, &other_owner)
+                .unwrap_err();
+        assert!(err.to_string().contains("owner lock hash mismatch"));
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Result};
 use gw_common::{h256_ext::H256Ext, sparse_merkle_tree, state::State, H256};
+use gw_db::schema::{COLUMN_META, META_UNSAFE_REORG_KEY};
 use gw_generator::{
     generator::{StateTransitionArgs, StateTransitionResult},
     ChallengeContext, Generator,
@@ -8,6 +9,7 @@ use gw_mem_pool::pool::MemPool;
 use gw_store::{
     chain_view::ChainView,
     state_db::{CheckPoint, StateDBMode, StateDBTransaction, SubState, WriteContext},
+    traits::KVStore,
     transaction::StoreTransaction,
     Store,
 };
@@ -97,6 +99,10 @@ pub enum SyncEvent {
         cell: ChallengeCell,
         context: crate::challenge::RevertContext,
     },
+    // syncing is halted by an unsafe reorg
+    Halted {
+        reorg: UnsafeReorg,
+    },
 }
 
 impl SyncEvent {
@@ -105,9 +111,69 @@ impl SyncEvent {
     }
 }
 
+/// local tip switched to another fork by a sync
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReorgDetected {
+    /// last block shared by both forks
+    pub common_ancestor: H256,
+    pub old_tip: H256,
+    pub new_tip: H256,
+}
+
+/// reorg deeper than `max_safe_reorg_depth`, chain stops syncing until an
+/// operator intervenes. The halt is persisted in the store, so it survives
+/// restarts until cleared by `Chain::clear_unsafe_reorg`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsafeReorg {
+    /// number of local blocks the reorg would revert
+    pub depth: u64,
+    pub max_safe_depth: u64,
+    pub tip: H256,
+}
+
+impl UnsafeReorg {
+    // depth, max safe depth and tip
+    const SERIALIZED_SIZE: usize = 8 + 8 + 32;
+
+    fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(Self::SERIALIZED_SIZE);
+        buf.extend_from_slice(&self.depth.to_le_bytes());
+        buf.extend_from_slice(&self.max_safe_depth.to_le_bytes());
+        buf.extend_from_slice(self.tip.as_slice());
+        buf
+    }
+
+    fn from_slice(slice: &[u8]) -> Result<Self> {
+        if slice.len() != Self::SERIALIZED_SIZE {
+            return Err(anyhow!("invalid unsafe reorg length {}", slice.len()));
+        }
+        let mut depth = [0u8; 8];
+        depth.copy_from_slice(&slice[..8]);
+        let mut max_safe_depth = [0u8; 8];
+        max_safe_depth.copy_from_slice(&slice[8..16]);
+        let mut tip = [0u8; 32];
+        tip.copy_from_slice(&slice[16..]);
+        Ok(UnsafeReorg {
+            depth: u64::from_le_bytes(depth),
+            max_safe_depth: u64::from_le_bytes(max_safe_depth),
+            tip: tip.into(),
+        })
+    }
+}
+
+/// Chain state matching committed db, restored if sync fails
+struct CommittedState {
+    local_state: LocalState,
+    bad_block_context: Option<ChallengeContext>,
+    bad_blocks: Vec<L2Block>,
+    pending_revert_blocks: Vec<L2Block>,
+    last_sync_event: SyncEvent,
+}
+
 /// concrete type aliases
 pub type StateStore = sparse_merkle_tree::default_store::DefaultStore<sparse_merkle_tree::H256>;
 
+#[derive(Clone)]
 pub struct LocalState {
     tip: L2Block,
     last_synced: L2BlockCommittedInfo,
@@ -141,6 +207,14 @@ pub struct Chain {
     bad_blocks: Vec<L2Block>,
     pending_revert_blocks: Vec<L2Block>,
     last_sync_event: SyncEvent,
+    last_reorg: Option<ReorgDetected>,
+    max_safe_reorg_depth: Option<u64>,
+    unsafe_reorg: Option<UnsafeReorg>,
+    skip_trusted_checkpoint_checks: bool,
+    trust_mode: bool,
+    // Blocks up to this number are finalized by the layer1 global state of
+    // current sync
+    trusted_finalized_number: Option<u64>,
     local_state: LocalState,
     generator: Arc<Generator>,
     mem_pool: Arc<Mutex<MemPool>>,
@@ -178,13 +252,38 @@ impl Chain {
             last_synced,
             last_global_state,
         };
+        let unsafe_reorg = match store
+            .begin_transaction()
+            .get(COLUMN_META, META_UNSAFE_REORG_KEY)
+        {
+            Some(slice) => Some(UnsafeReorg::from_slice(&slice)?),
+            None => None,
+        };
+        let last_sync_event = match unsafe_reorg {
+            Some(ref reorg) => {
+                log::error!(
+                    "chain halted by unsafe reorg {:?}, needs operator intervention",
+                    reorg
+                );
+                SyncEvent::Halted {
+                    reorg: reorg.clone(),
+                }
+            }
+            None => SyncEvent::Success,
+        };
         let rollup_config_hash = rollup_config.hash();
         Ok(Chain {
             store,
             bad_block_context: None,
             bad_blocks: Vec::new(),
             pending_revert_blocks: Vec::new(),
-            last_sync_event: SyncEvent::Success,
+            last_sync_event,
+            last_reorg: None,
+            max_safe_reorg_depth: None,
+            unsafe_reorg,
+            skip_trusted_checkpoint_checks: false,
+            trust_mode: false,
+            trusted_finalized_number: None,
             local_state,
             generator,
             mem_pool,
@@ -193,6 +292,26 @@ impl Chain {
         })
     }
 
+    /// Refuse to revert more than `depth` local blocks in a sync
+    pub fn with_max_safe_reorg_depth(mut self, depth: Option<u64>) -> Self {
+        self.max_safe_reorg_depth = depth;
+        self
+    }
+
+    /// Skip checkpoint checks when building verify context to cancel a
+    /// challenge. The challenged block was verified when it was applied.
+    pub fn with_skip_trusted_checkpoint_checks(mut self, skip: bool) -> Self {
+        self.skip_trusted_checkpoint_checks = skip;
+        self
+    }
+
+    /// Skip signature checks of synced blocks already finalized on layer1,
+    /// e.g. for fast catch-up from a trusted source
+    pub fn with_trust_mode(mut self, trust_mode: bool) -> Self {
+        self.trust_mode = trust_mode;
+        self
+    }
+
     /// return local state
     pub fn local_state(&self) -> &LocalState {
         &self.local_state
@@ -226,6 +345,28 @@ impl Chain {
         &self.last_sync_event
     }
 
+    /// reorg happened in the last sync, if any
+    pub fn last_reorg(&self) -> Option<&ReorgDetected> {
+        self.last_reorg.as_ref()
+    }
+
+    /// reorg halted the chain, if any
+    pub fn unsafe_reorg(&self) -> Option<&UnsafeReorg> {
+        self.unsafe_reorg.as_ref()
+    }
+
+    /// Resume syncing after an operator has checked the unsafe reorg
+    pub fn clear_unsafe_reorg(&mut self) -> Result<()> {
+        let db = self.store.begin_transaction();
+        db.delete(COLUMN_META, META_UNSAFE_REORG_KEY)?;
+        db.commit()?;
+        if let Some(reorg) = self.unsafe_reorg.take() {
+            log::warn!("clear unsafe reorg halt {:?}", reorg);
+            self.last_sync_event = SyncEvent::Success;
+        }
+        Ok(())
+    }
+
     /// update a layer1 action
     fn update_l1action(&mut self, db: &StoreTransaction, action: L1Action) -> Result<()> {
         let L1Action {
@@ -362,8 +503,13 @@ impl Chain {
                         log::info!("challenge cancelable, build verify context");
 
                         let generator = Arc::clone(&self.generator);
-                        let context =
-                            crate::challenge::build_verify_context(generator, db, &target)?;
+                        let verify_checkpoints = !self.skip_trusted_checkpoint_checks;
+                        let context = crate::challenge::build_verify_context_with_checks(
+                            generator,
+                            db,
+                            &target,
+                            verify_checkpoints,
+                        )?;
 
                         return Ok(SyncEvent::BadChallenge { cell, context });
                     }
@@ -652,30 +798,44 @@ impl Chain {
 
     /// Sync chain from layer1
     pub fn sync(&mut self, param: SyncParam) -> Result<()> {
+        self.check_reorg_depth(&param)?;
+
         let db = self.store.begin_transaction();
-        // revert layer1 actions
-        if !param.reverts.is_empty() {
-            // revert
-            for reverted_action in param.reverts {
-                self.revert_l1action(&db, reverted_action)?;
-            }
-        }
+        let old_tip: H256 = self.local_state.tip.hash().into();
+        self.last_reorg = None;
+        self.trusted_finalized_number = if self.trust_mode {
+            finalized_block_number(&param.updates, &self.rollup_type_script_hash)?
+        } else {
+            None
+        };
 
-        // update layer1 actions
-        for action in param.updates {
-            self.update_l1action(&db, action)?;
-            match self.last_sync_event() {
-                SyncEvent::Success => (),
-                _ => db.commit()?,
-            }
-        }
+        let mut committed = self.committed_state();
+        let common_ancestor =
+            match self.apply_l1actions(&db, param.reverts, param.updates, &mut committed) {
+                Ok(common_ancestor) => common_ancestor,
+                Err(err) => {
+                    // Uncommitted db changes are dropped along with db transaction
+                    self.restore_committed_state(committed);
+                    return Err(err);
+                }
+            };
 
         db.commit()?;
         log::debug!("commit db after sync");
 
         let tip_block_hash: H256 = self.local_state.tip.hash().into();
+        if common_ancestor != old_tip {
+            let reorg = ReorgDetected {
+                common_ancestor,
+                old_tip,
+                new_tip: tip_block_hash,
+            };
+            log::info!("reorg detected {:?}", reorg);
+            self.last_reorg = Some(reorg);
+        }
+
         if let SyncEvent::Success = self.last_sync_event {
-            // update mem pool state
+            // update mem pool state, txs of reverted blocks are reinjected
             self.mem_pool.lock().notify_new_tip(tip_block_hash)?;
         }
 
@@ -708,6 +868,132 @@ impl Chain {
         Ok(())
     }
 
+    /// Revert and update layer1 actions, returns common ancestor of reverted
+    /// and updated blocks. `committed` tracks chain state of committed db.
+    fn apply_l1actions(
+        &mut self,
+        db: &StoreTransaction,
+        reverts: Vec<RevertedL1Action>,
+        updates: Vec<L1Action>,
+        committed: &mut CommittedState,
+    ) -> Result<H256> {
+        // revert layer1 actions
+        for reverted_action in reverts {
+            self.revert_l1action(db, reverted_action)?;
+        }
+        let common_ancestor: H256 = self.local_state.tip.hash().into();
+
+        // update layer1 actions
+        for action in updates {
+            self.check_fork(&action)?;
+            self.update_l1action(db, action)?;
+            match self.last_sync_event() {
+                SyncEvent::Success => (),
+                _ => {
+                    db.commit()?;
+                    *committed = self.committed_state();
+                }
+            }
+        }
+
+        Ok(common_ancestor)
+    }
+
+    fn committed_state(&self) -> CommittedState {
+        CommittedState {
+            local_state: self.local_state.clone(),
+            bad_block_context: self.bad_block_context.clone(),
+            bad_blocks: self.bad_blocks.clone(),
+            pending_revert_blocks: self.pending_revert_blocks.clone(),
+            last_sync_event: self.last_sync_event.clone(),
+        }
+    }
+
+    fn restore_committed_state(&mut self, committed: CommittedState) {
+        self.local_state = committed.local_state;
+        self.bad_block_context = committed.bad_block_context;
+        self.bad_blocks = committed.bad_blocks;
+        self.pending_revert_blocks = committed.pending_revert_blocks;
+        self.last_sync_event = committed.last_sync_event;
+    }
+
+    /// Halt syncing instead of reverting blocks beyond the finality
+    /// assumption
+    fn check_reorg_depth(&mut self, param: &SyncParam) -> Result<()> {
+        if let Some(ref unsafe_reorg) = self.unsafe_reorg {
+            return Err(anyhow!(
+                "chain halted by unsafe reorg {:?}, needs operator intervention",
+                unsafe_reorg
+            ));
+        }
+        let max_safe_depth = match self.max_safe_reorg_depth {
+            Some(depth) => depth,
+            None => return Ok(()),
+        };
+
+        let reverted_blocks = param
+            .reverts
+            .iter()
+            .filter(|action| matches!(action.context, L1ActionContext::SubmitBlock { .. }));
+        let depth = reverted_blocks.count() as u64;
+        if depth <= max_safe_depth {
+            return Ok(());
+        }
+
+        let unsafe_reorg = UnsafeReorg {
+            depth,
+            max_safe_depth,
+            tip: self.local_state.tip.hash().into(),
+        };
+        log::error!(
+            "reorg of {} blocks exceeds max safe depth {}, stop syncing at tip 0x{}, needs operator intervention",
+            depth,
+            max_safe_depth,
+            hex::encode(unsafe_reorg.tip.as_slice())
+        );
+        let db = self.store.begin_transaction();
+        db.insert_raw(COLUMN_META, META_UNSAFE_REORG_KEY, &unsafe_reorg.to_vec())?;
+        db.commit()?;
+        self.unsafe_reorg = Some(unsafe_reorg.clone());
+        // Stop block producing and challenging on the stale tip
+        self.last_sync_event = SyncEvent::Halted {
+            reorg: unsafe_reorg.clone(),
+        };
+        Err(anyhow!(
+            "chain halted by unsafe reorg {:?}, needs operator intervention",
+            unsafe_reorg
+        ))
+    }
+
+    /// Reject submitted block which doesn't follow local tip, the layer1
+    /// source must revert the local fork first
+    fn check_fork(&self, action: &L1Action) -> Result<()> {
+        let l2block = match action.context {
+            L1ActionContext::SubmitBlock { ref l2block, .. } => l2block,
+            _ => return Ok(()),
+        };
+        // Blocks after a bad block don't extend local tip
+        if self.local_state.status() != Status::Running || self.bad_block_context.is_some() {
+            return Ok(());
+        }
+
+        let parent_block_hash: [u8; 32] = l2block.raw().parent_block_hash().unpack();
+        let tip_block_hash = self.local_state.tip.hash();
+        if parent_block_hash != tip_block_hash {
+            return Err(anyhow!(
+                "fork detected, block #{} parent 0x{} isn't local tip 0x{}",
+                {
+                    let number: u64 = l2block.raw().number().unpack();
+                    number
+                },
+                hex::encode(parent_block_hash),
+                hex::encode(tip_block_hash)
+            ));
+        }
+
+        Ok(())
+    }
+
     fn process_block(
         &mut self,
         db: &StoreTransaction,
@@ -719,6 +1005,12 @@ impl Chain {
         let tip_number: u64 = self.local_state.tip.raw().number().unpack();
         let tip_block_hash = self.local_state.tip.raw().hash();
         let block_number: u64 = l2block.raw().number().unpack();
+        let _span = tracing::debug_span!(
+            "sync_block",
+            number = block_number,
+            hash = %hex::encode(l2block.hash())
+        )
+        .entered();
         assert_eq!(
             {
                 let parent_block_hash: [u8; 32] = l2block.raw().parent_block_hash().unpack();
@@ -729,9 +1021,17 @@ impl Chain {
         );
 
         // process l2block
+        let skip_signature_check = self
+            .trusted_finalized_number
+            .map(|finalized_number| block_number <= finalized_number)
+            .unwrap_or(false);
+        if skip_signature_check {
+            log::debug!("skip signature check of finalized block #{}", block_number);
+        }
         let args = StateTransitionArgs {
             l2block: l2block.clone(),
             deposit_requests: deposit_requests.clone(),
+            skip_signature_check,
         };
         let tip_block_hash = self.local_state.tip().hash().into();
         let chain_view = ChainView::new(db, tip_block_hash);
@@ -794,6 +1094,17 @@ impl Chain {
     }
 }
 
+/// Last finalized block number of the latest layer1 global state in
+/// `updates`, `None` if there is no update
+fn finalized_block_number(updates: &[L1Action], rollup_id: &[u8; 32]) -> Result<Option<u64>> {
+    let last_action = match updates.last() {
+        Some(action) => action,
+        None => return Ok(None),
+    };
+    let global_state = parse_global_state(&last_action.transaction, rollup_id)?;
+    Ok(Some(global_state.last_finalized_block_number().unpack()))
+}
+
 fn parse_global_state(tx: &Transaction, rollup_id: &[u8; 32]) -> Result<GlobalState> {
     // find rollup state cell from outputs
     let (i, _) = tx
@@ -870,3 +1181,61 @@ fn update_block_smt(db: &StoreTransaction, block: &L2Block) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_types::packed::RawTransaction;
+
+    // Submit block `number` along with a layer1 global state finalizing
+    // blocks up to `last_finalized_block_number`
+    fn submit_block(
+        rollup_type_script: &Script,
+        number: u64,
+        last_finalized_block_number: u64,
+    ) -> L1Action {
+        let global_state = GlobalState::new_builder()
+            .last_finalized_block_number(last_finalized_block_number.pack())
+            .build();
+        let rollup_cell = CellOutput::new_builder()
+            .type_(Some(rollup_type_script.clone()).pack())
+            .build();
+        let raw_tx = RawTransaction::new_builder()
+            .outputs(vec![rollup_cell].pack())
+            .outputs_data(vec![global_state.as_bytes()].pack())
+            .build();
+        let raw = RawL2Block::new_builder().number(number.pack()).build();
+        L1Action {
+            transaction: Transaction::new_builder().raw(raw_tx).build(),
+            l2block_committed_info: L2BlockCommittedInfo::default(),
+            context: L1ActionContext::SubmitBlock {
+                l2block: L2Block::new_builder().raw(raw).build(),
+                deposit_requests: vec![],
+                reverted_block_hashes: vec![],
+            },
+        }
+    }
+
+    #[test]
+    fn test_finalized_block_number() {
+        let rollup_type_script = Script::default();
+        let rollup_id = rollup_type_script.hash();
+
+        assert_eq!(finalized_block_number(&[], &rollup_id).unwrap(), None);
+
+        // Finality comes from the latest layer1 global state instead of the
+        // highest submitted block
+        let updates = vec![
+            submit_block(&rollup_type_script, 9, 2),
+            submit_block(&rollup_type_script, 10, 3),
+        ];
+        assert_eq!(
+            finalized_block_number(&updates, &rollup_id).unwrap(),
+            Some(3)
+        );
+
+        // No rollup cell in the layer1 transaction
+        let updates = vec![submit_block(&Script::default(), 10, 8)];
+        assert!(finalized_block_number(&updates, &[1u8; 32]).is_err());
+    }
+}
@@ -24,6 +24,21 @@ use gw_types::prelude::{Builder, Entity, Pack, Reader, Unpack};
 use std::convert::TryInto;
 use std::sync::Arc;
 
+/// Opcode pushing a leaf, the first instruction of every compiled proof
+const PROOF_LEAF_OPCODE: u8 = 0x4c;
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq, Clone)]
+pub enum ChallengeError {
+    #[error("target index {index} out of bounds, count {count}")]
+    TargetIndexOutOfBounds { index: u32, count: u32 },
+    #[error("{kind} checkpoint mismatch, local 0x{}, block 0x{}", hex::encode(.local), hex::encode(.block))]
+    CheckpointMismatch {
+        kind: &'static str,
+        local: [u8; 32],
+        block: [u8; 32],
+    },
+}
+
 #[derive(Debug, Clone)]
 pub enum VerifyWitness {
     TxExecution(VerifyTransactionWitness),
@@ -43,23 +58,71 @@ pub fn build_verify_context(
     db: &StoreTransaction,
     target: &ChallengeTarget,
 ) -> Result<VerifyContext> {
-    let challenge_type = target.target_type().try_into();
+    build_verify_context_with_checks(generator, db, target, true)
+}
+
+/// Checkpoint checks can be skipped only for blocks already trusted, e.g.
+/// local blocks challenged by others. Challenge context of bad blocks is
+/// always built with checks
+pub fn build_verify_context_with_checks(
+    generator: Arc<Generator>,
+    db: &StoreTransaction,
+    target: &ChallengeTarget,
+    verify_checkpoints: bool,
+) -> Result<VerifyContext> {
     let block_hash: [u8; 32] = target.block_hash().unpack();
     let target_index = target.target_index().unpack();
 
-    match challenge_type.map_err(|_| anyhow!("invalid challenge type"))? {
-        ChallengeTargetType::TxExecution => {
-            build_verify_transaction_witness(generator, db, block_hash.into(), target_index)
-        }
-        ChallengeTargetType::TxSignature => {
-            build_verify_transaction_signature_witness(db, block_hash.into(), target_index)
-        }
+    // Keep it exhaustive, new target types must be handled here
+    match parse_target_type(target)? {
+        ChallengeTargetType::TxExecution => build_verify_transaction_witness(
+            generator,
+            db,
+            block_hash.into(),
+            target_index,
+            verify_checkpoints,
+        ),
+        ChallengeTargetType::TxSignature => build_verify_transaction_signature_witness(
+            db,
+            block_hash.into(),
+            target_index,
+            verify_checkpoints,
+        ),
         ChallengeTargetType::Withdrawal => {
             build_verify_withdrawal_witness(db, block_hash.into(), target_index)
         }
     }
 }
 
+/// Reject target index before building any proof
+fn check_target_index(index: u32, count: usize) -> Result<(), ChallengeError> {
+    if index as usize >= count {
+        return Err(ChallengeError::TargetIndexOutOfBounds {
+            index,
+            count: count as u32,
+        });
+    }
+    Ok(())
+}
+
+fn check_checkpoint(
+    kind: &'static str,
+    local: [u8; 32],
+    block: [u8; 32],
+) -> Result<(), ChallengeError> {
+    if local != block {
+        return Err(ChallengeError::CheckpointMismatch { kind, local, block });
+    }
+    Ok(())
+}
+
+fn parse_target_type(target: &ChallengeTarget) -> Result<ChallengeTargetType> {
+    target
+        .target_type()
+        .try_into()
+        .map_err(|n| anyhow!("invalid challenge type {}", n))
+}
+
 #[derive(Debug, Clone)]
 pub struct RevertWitness {
     pub reverted_blocks: RawL2BlockVec, // sorted by block number
@@ -99,7 +162,7 @@ pub fn build_revert_context(
         let leaves = reverted_raw_blocks.iter().map(to_leave).collect();
         let proof = smt.merkle_proof(keys)?.compile(leaves)?;
 
-        (root, proof)
+        (root, check_proof_format(proof)?)
     };
     log::debug!("build reverted block proof");
 
@@ -127,6 +190,7 @@ fn build_verify_withdrawal_witness(
     let block = db
         .get_block(&block_hash)?
         .ok_or_else(|| anyhow!("block not found"))?;
+    check_target_index(withdrawal_index, block.withdrawals().len())?;
 
     // Build withdrawal proof
     let mut tree: SMT<DefaultStore<H256>> = Default::default();
@@ -150,19 +214,10 @@ fn build_verify_withdrawal_witness(
     let withdrawal_proof = tree
         .merkle_proof(vec![H256::from_u32(withdrawal_index)])?
         .compile(leaves)?;
+    let withdrawal_proof = check_proof_format(withdrawal_proof)?;
     log::debug!("build withdrawal proof");
 
-    // Get sender account script
-    let sender_script_hash: [u8; 32] = withdrawal.raw().account_script_hash().unpack();
-    let sender_script = {
-        let raw_block = block.raw();
-        let check_point = CheckPoint::new(raw_block.number().unpack() - 1, SubState::Block);
-        let state_db = StateDBTransaction::from_checkpoint(db, check_point, StateDBMode::ReadOnly)?;
-        let tree = state_db.account_state_tree()?;
-
-        tree.get_script(&sender_script_hash.into())
-            .ok_or_else(|| anyhow!("sender script not found"))?
-    };
+    let sender_script = get_withdrawal_sender_script(db, &block, withdrawal_index)?;
 
     let verify_witness = VerifyWithdrawalWitness::new_builder()
         .raw_l2block(block.raw())
@@ -177,19 +232,54 @@ fn build_verify_withdrawal_witness(
     })
 }
 
+/// Sender script of withdrawal at `withdrawal_index`, resolved from the state
+/// of the parent block since withdrawals are applied first in a block
+pub fn get_withdrawal_sender_script(
+    db: &StoreTransaction,
+    block: &L2Block,
+    withdrawal_index: u32,
+) -> Result<Script> {
+    let withdrawal = block
+        .withdrawals()
+        .get(withdrawal_index as usize)
+        .ok_or_else(|| anyhow!("withdrawal not found in block"))?;
+    let sender_script_hash: [u8; 32] = withdrawal.raw().account_script_hash().unpack();
+
+    let block_number: u64 = block.raw().number().unpack();
+    let parent_block_number = block_number
+        .checked_sub(1)
+        .ok_or_else(|| anyhow!("genesis block has no withdrawal"))?;
+    let check_point = CheckPoint::new(parent_block_number, SubState::Block);
+    let state_db = StateDBTransaction::from_checkpoint(db, check_point, StateDBMode::ReadOnly)?;
+    let tree = state_db.account_state_tree()?;
+
+    tree.get_script(&sender_script_hash.into())
+        .ok_or_else(|| anyhow!("sender script not found"))
+}
+
 fn build_verify_transaction_signature_witness(
     db: &StoreTransaction,
     block_hash: H256,
     tx_index: u32,
+    verify_checkpoints: bool,
 ) -> Result<VerifyContext> {
     let block = db
         .get_block(&block_hash)?
         .ok_or_else(|| anyhow!("block not found"))?;
+    check_target_index(tx_index, block.transactions().len())?;
 
     let (tx, tx_proof) = build_tx_proof(&block, tx_index)?;
     log::debug!("build tx proof");
 
-    let kv_witness = build_tx_kv_witness(db, &block, &tx.raw(), tx_index, TxKvState::Signature)?;
+    let tx_kv_state = TxKvState::Signature;
+    let kv_witness = build_tx_kv_witness(
+        db,
+        &block,
+        &tx.raw(),
+        tx_index,
+        tx_kv_state,
+        verify_checkpoints,
+    )?;
     log::debug!("build kv witness");
 
     let context = VerifyTransactionSignatureContext::new_builder()
@@ -218,17 +308,26 @@ fn build_verify_transaction_witness(
     db: &StoreTransaction,
     block_hash: H256,
     tx_index: u32,
+    verify_checkpoints: bool,
 ) -> Result<VerifyContext> {
     let block = db
         .get_block(&block_hash)?
         .ok_or_else(|| anyhow!("block not found"))?;
+    check_target_index(tx_index, block.transactions().len())?;
     let raw_block = block.raw();
 
     let (tx, tx_proof) = build_tx_proof(&block, tx_index)?;
     log::debug!("build tx proof");
 
     let tx_kv_state = TxKvState::Execution { generator };
-    let kv_witness = build_tx_kv_witness(db, &block, &tx.raw(), tx_index, tx_kv_state)?;
+    let kv_witness = build_tx_kv_witness(
+        db,
+        &block,
+        &tx.raw(),
+        tx_index,
+        tx_kv_state,
+        verify_checkpoints,
+    )?;
     log::debug!("build kv witness");
 
     let return_data_hash = kv_witness
@@ -275,7 +374,7 @@ fn build_tx_proof(block: &L2Block, tx_index: u32) -> Result<(L2Transaction, Comp
         .merkle_proof(vec![H256::from_u32(tx_index)])?
         .compile(leaves)?;
 
-    Ok((tx, proof))
+    Ok((tx, check_proof_format(proof)?))
 }
 
 enum TxKvState {
@@ -299,6 +398,7 @@ fn build_tx_kv_witness(
     raw_tx: &RawL2Transaction,
     tx_index: u32,
     tx_kv_state: TxKvState,
+    verify_checkpoints: bool,
 ) -> Result<TxKvWitness> {
     let raw_block = block.as_reader().raw();
     let withdrawal_len: u32 = {
@@ -330,15 +430,17 @@ fn build_tx_kv_witness(
         }
     };
 
+    // Execute on history state, changes are discarded by rollback below
     let state_db =
-        StateDBTransaction::from_checkpoint(db, local_prev_tx_checkpoint, StateDBMode::ReadOnly)?;
+        StateDBTransaction::from_checkpoint(db, local_prev_tx_checkpoint, StateDBMode::ReadOnly)?
+            .with_discardable_writes();
     let mut tree = state_db.account_state_tree()?;
     let prev_tx_account_count = tree.get_account_count()?;
 
     // Check prev tx account state
-    {
+    if verify_checkpoints {
         let local_checkpoint: [u8; 32] = tree.calculate_state_checkpoint()?.into();
-        assert_eq!(local_checkpoint, block_prev_tx_checkpoint);
+        check_checkpoint("prev tx", local_checkpoint, block_prev_tx_checkpoint)?;
     }
 
     tree.tracker_mut().enable();
@@ -393,10 +495,10 @@ fn build_tx_kv_witness(
         .ok_or_else(|| anyhow!("block tx checkpoint not found"))?
         .unpack();
 
-    if matches!(tx_kv_state, TxKvState::Execution { .. }) {
+    if verify_checkpoints && matches!(tx_kv_state, TxKvState::Execution { .. }) {
         // Check post tx account state
         let local_checkpoint: [u8; 32] = tree.calculate_state_checkpoint()?.into();
-        assert_eq!(local_checkpoint, block_post_tx_checkpoint);
+        check_checkpoint("post tx", local_checkpoint, block_post_tx_checkpoint)?;
     }
 
     let touched_keys: Vec<H256> = {
@@ -407,13 +509,10 @@ fn build_tx_kv_witness(
     };
     let post_tx_account_count = tree.get_account_count()?;
     let post_kv_state = {
-        let keys = touched_keys.iter();
-        let to_kv = keys.map(|k| {
-            let v = tree.get_raw(k)?;
-            Ok((*k, v))
-        });
-        to_kv.collect::<Result<Vec<(H256, H256)>>>()
-    }?;
+        let values = tree.get_raw_many(&touched_keys)?;
+        let keys = touched_keys.iter().cloned();
+        keys.zip(values).collect::<Vec<(H256, H256)>>()
+    };
 
     // Discard all changes
     drop(tree);
@@ -421,38 +520,41 @@ fn build_tx_kv_witness(
 
     tree = state_db.account_state_tree()?;
     let prev_kv_state = {
-        let keys = touched_keys.iter();
-        let to_kv = keys.map(|k| {
-            let v = tree.get_raw(k)?;
-            Ok((*k, v))
-        });
-        to_kv.collect::<Result<Vec<(H256, H256)>>>()
-    }?;
+        let values = tree.get_raw_many(&touched_keys)?;
+        let keys = touched_keys.iter().cloned();
+        keys.zip(values).collect::<Vec<(H256, H256)>>()
+    };
 
     let kv_state_proof = {
         let smt = state_db.account_smt()?;
         let prev_kv_state = prev_kv_state.clone();
-        smt.merkle_proof(touched_keys)?.compile(prev_kv_state)?
+        let proof = smt.merkle_proof(touched_keys)?.compile(prev_kv_state)?;
+        check_proof_format(proof)?
     };
     log::debug!("build kv state proof");
 
     // Check proof
-    {
+    if verify_checkpoints {
         let proof_root = kv_state_proof.compute_root::<Blake2bHasher>(prev_kv_state.clone())?;
         let proof_checkpoint = calculate_state_checkpoint(&proof_root, prev_tx_account_count);
-        assert_eq!(proof_checkpoint, block_prev_tx_checkpoint.into());
+        check_checkpoint(
+            "prev tx proof",
+            proof_checkpoint.into(),
+            block_prev_tx_checkpoint,
+        )?;
 
         if matches!(tx_kv_state, TxKvState::Execution { .. }) {
             let proof_root = kv_state_proof.compute_root::<Blake2bHasher>(post_kv_state)?;
             let proof_checkpoint = calculate_state_checkpoint(&proof_root, post_tx_account_count);
-            assert_eq!(proof_checkpoint, block_post_tx_checkpoint.into());
+            check_checkpoint(
+                "post tx proof",
+                proof_checkpoint.into(),
+                block_post_tx_checkpoint,
+            )?;
         }
     }
 
-    let scripts = ScriptVec::new_builder()
-        .push(sender_script.clone())
-        .push(receiver_script.clone())
-        .build();
+    let scripts = build_tx_scripts(&sender_script, &receiver_script);
 
     let witness = TxKvWitness {
         account_count: prev_tx_account_count.pack(),
@@ -467,6 +569,15 @@ fn build_tx_kv_witness(
     Ok(witness)
 }
 
+// Scripts are looked up by hash, so a self transaction only needs one copy
+fn build_tx_scripts(sender_script: &Script, receiver_script: &Script) -> ScriptVec {
+    let mut builder = ScriptVec::new_builder().push(sender_script.clone());
+    if receiver_script.as_slice() != sender_script.as_slice() {
+        builder = builder.push(receiver_script.clone());
+    }
+    builder.build()
+}
+
 fn build_block_proof(
     db: &StoreTransaction,
     raw_blocks: &[RawL2Block],
@@ -491,9 +602,137 @@ fn build_block_proof(
 
         let smt_keys = raw_blocks.iter().map(|rb| rb.smt_key().into());
         let leaves = raw_blocks.iter().map(to_leave);
-        smt.merkle_proof(smt_keys.collect())?
-            .compile(leaves.collect())?
+        let proof = smt
+            .merkle_proof(smt_keys.collect())?
+            .compile(leaves.collect())?;
+        check_proof_format(proof)?
     };
 
     Ok((block_hashes, block_proof))
 }
+
+/// On-chain verifier only understands proofs start with a leaf push opcode,
+/// reject proofs encoded by an incompatible sparse-merkle-tree version.
+fn check_proof_format(proof: CompiledMerkleProof) -> Result<CompiledMerkleProof> {
+    match proof.0.first() {
+        Some(&PROOF_LEAF_OPCODE) => Ok(proof),
+        Some(opcode) => Err(anyhow!("unsupported merkle proof opcode {:#x}", opcode)),
+        None => Err(anyhow!("empty merkle proof")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gw_db::schema::COLUMN_BLOCK;
+    use gw_store::traits::KVStore;
+    use gw_store::Store;
+
+    // Pinned proof of a single leaf tree, update it only if the on-chain
+    // verifier is upgraded with the sparse-merkle-tree dependency.
+    const SINGLE_LEAF_PROOF: [u8; 3] = [0x4c, 0x4f, 0x00];
+
+    #[test]
+    fn test_pinned_single_leaf_proof_bytes() {
+        let key = H256::from_u32(1);
+        let value = H256::one();
+
+        let mut tree: SMT<DefaultStore<H256>> = Default::default();
+        tree.update(key, value).unwrap();
+
+        let proof = tree
+            .merkle_proof(vec![key])
+            .unwrap()
+            .compile(vec![(key, value)])
+            .unwrap();
+        let proof = check_proof_format(proof).unwrap();
+        assert_eq!(proof.0, SINGLE_LEAF_PROOF.to_vec());
+
+        let root = proof
+            .compute_root::<Blake2bHasher>(vec![(key, value)])
+            .unwrap();
+        assert_eq!(&root, tree.root());
+    }
+
+    #[test]
+    fn test_parse_target_type() {
+        let target = ChallengeTarget::new_builder()
+            .target_type(ChallengeTargetType::Withdrawal.into())
+            .build();
+        assert_eq!(
+            parse_target_type(&target).unwrap(),
+            ChallengeTargetType::Withdrawal
+        );
+
+        let target = ChallengeTarget::new_builder()
+            .target_type(3u8.into())
+            .build();
+        let err = parse_target_type(&target).unwrap_err();
+        assert_eq!(err.to_string(), "invalid challenge type 3");
+    }
+
+    #[test]
+    fn test_target_index_out_of_bounds() {
+        let block = L2Block::new_builder()
+            .transactions(vec![L2Transaction::default()].pack())
+            .build();
+        let store = Store::open_tmp().unwrap();
+        let db = store.begin_transaction();
+        db.insert_raw(COLUMN_BLOCK, &block.hash(), block.as_slice())
+            .unwrap();
+
+        let err = build_verify_transaction_signature_witness(&db, block.hash().into(), 1, true)
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ChallengeError>(),
+            Some(&ChallengeError::TargetIndexOutOfBounds { index: 1, count: 1 })
+        );
+
+        let err = build_verify_withdrawal_witness(&db, block.hash().into(), 0).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ChallengeError>(),
+            Some(&ChallengeError::TargetIndexOutOfBounds { index: 0, count: 0 })
+        );
+
+        assert!(check_target_index(0, 1).is_ok());
+    }
+
+    #[test]
+    fn test_check_checkpoint() {
+        assert!(check_checkpoint("prev tx", [1u8; 32], [1u8; 32]).is_ok());
+        assert_eq!(
+            check_checkpoint("prev tx", [1u8; 32], [2u8; 32]),
+            Err(ChallengeError::CheckpointMismatch {
+                kind: "prev tx",
+                local: [1u8; 32],
+                block: [2u8; 32],
+            })
+        );
+    }
+
+    #[test]
+    fn test_build_tx_scripts() {
+        let sender_script = Script::new_builder().args(vec![1u8; 32].pack()).build();
+        let receiver_script = Script::new_builder().args(vec![2u8; 32].pack()).build();
+
+        let scripts = build_tx_scripts(&sender_script, &receiver_script);
+        assert_eq!(scripts.len(), 2);
+        assert_eq!(scripts.get(0).unwrap().as_slice(), sender_script.as_slice());
+        assert_eq!(
+            scripts.get(1).unwrap().as_slice(),
+            receiver_script.as_slice()
+        );
+
+        // Self transaction
+        let scripts = build_tx_scripts(&sender_script, &sender_script);
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts.get(0).unwrap().as_slice(), sender_script.as_slice());
+    }
+
+    #[test]
+    fn test_reject_unknown_proof_format() {
+        assert!(check_proof_format(CompiledMerkleProof(vec![])).is_err());
+        assert!(check_proof_format(CompiledMerkleProof(vec![0xff, 0x4c])).is_err());
+    }
+}
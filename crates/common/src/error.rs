@@ -17,6 +17,8 @@ cfg_if::cfg_if! {
             Store,
             #[error("Invalid short address error")]
             InvalidShortAddress,
+            #[error("Invalid account count update from {current} to {count}")]
+            InvalidAccountCount { current: u32, count: u32 },
         }
     } else {
         #[derive(Debug, Eq, PartialEq, Clone)]
@@ -27,6 +29,7 @@ cfg_if::cfg_if! {
             MissingKey,
             Store,
             InvalidShortAddress,
+            InvalidAccountCount { current: u32, count: u32 },
         }
     }
 }
@@ -113,6 +113,10 @@ pub struct PrepareWithdrawalRecord {
 pub trait State {
     // KV interface
     fn get_raw(&self, key: &H256) -> Result<H256, Error>;
+    /// Get values of keys in order, may be overridden for a faster batch read
+    fn get_raw_many(&self, keys: &[H256]) -> Result<Vec<H256>, Error> {
+        keys.iter().map(|key| self.get_raw(key)).collect()
+    }
     fn update_raw(&mut self, key: H256, value: H256) -> Result<(), Error>;
     fn get_account_count(&self) -> Result<u32, Error>;
     fn set_account_count(&mut self, count: u32) -> Result<(), Error>;
@@ -17,17 +17,64 @@ pub struct Config {
     pub rpc_server: RPCServerConfig,
     pub block_producer: Option<BlockProducerConfig>,
     pub web3_indexer: Option<Web3IndexerConfig>,
+    #[serde(default)]
+    pub fee: FeeConfig,
 }
 
 #[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
 pub struct RPCServerConfig {
     pub listen: String,
+    /// Truncate return data of executed transactions in responses,
+    /// unlimited if not set. The generator keeps full return data, which
+    /// its set return data syscall already caps at 24KB, so this only
+    /// bounds rpc responses
+    #[serde(default)]
+    pub max_return_data_size: Option<usize>,
 }
 
+/// Transaction fee parameters
 #[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FeeConfig {
+    /// Minimum polyjuice gas price, quoted to wallets by `eth_gasPrice`
+    pub min_gas_price: u64,
+}
+
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 3000;
+
+fn default_poll_interval_ms() -> u64 {
+    DEFAULT_POLL_INTERVAL_MS
+}
+
+pub const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 16;
+
+fn default_event_channel_capacity() -> usize {
+    DEFAULT_EVENT_CHANNEL_CAPACITY
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct RPCClientConfig {
     pub indexer_url: String,
     pub ckb_url: String,
+    /// Base interval to poll new layer1 blocks, it shrinks while syncing
+    /// blocks and backs off while staying at tip
+    #[serde(default = "default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Layer1 events polled ahead of block application, polling waits
+    /// while this many events are not applied yet
+    #[serde(default = "default_event_channel_capacity")]
+    pub event_channel_capacity: usize,
+}
+
+// Keep defaults in line with the serde ones
+impl Default for RPCClientConfig {
+    fn default() -> Self {
+        RPCClientConfig {
+            indexer_url: Default::default(),
+            ckb_url: Default::default(),
+            poll_interval_ms: default_poll_interval_ms(),
+            event_channel_capacity: default_event_channel_capacity(),
+        }
+    }
 }
 
 /// Onchain rollup cell config
@@ -35,6 +82,33 @@ pub struct RPCClientConfig {
 pub struct ChainConfig {
     pub genesis_committed_info: L2BlockCommittedInfo,
     pub rollup_type_script: Script,
+    /// Share loaded backends across transactions while applying a block
+    #[serde(default)]
+    pub batch_tx_execution: bool,
+    /// Execute each transaction twice while applying a block and fail on
+    /// differing results, for canary nodes
+    #[serde(default)]
+    pub double_tx_execution: bool,
+    /// Halt syncing instead of reverting more local blocks than this on a
+    /// layer1 reorg, unlimited if not set
+    #[serde(default)]
+    pub max_safe_reorg_depth: Option<u64>,
+    /// Resume syncing on start, after the operator has checked the unsafe
+    /// reorg which halted the chain
+    #[serde(default)]
+    pub clear_unsafe_reorg: bool,
+    /// Skip signature checks of synced blocks already finalized on layer1
+    #[serde(default)]
+    pub trust_mode: bool,
+    /// Dump state writes of transactions whose post checkpoint diverges from
+    /// the block into this directory, disabled if not set
+    #[serde(default)]
+    pub state_journal_dir: Option<PathBuf>,
+    /// Report execution cycles divided by this ratio, rounded up, as
+    /// `cycles_gas_used` of run results. Gas used and fee of receipts are
+    /// still reported by backends. Disabled if not set
+    #[serde(default)]
+    pub cycles_per_gas: Option<u64>,
 }
 
 /// Genesis config
@@ -62,13 +136,47 @@ pub struct WalletConfig {
 pub struct ChallengerConfig {
     pub rewards_receiver_lock: Script,
     pub burn_lock: Script,
+    /// Skip checkpoint checks when building verify context to cancel a
+    /// challenge of a local, already verified block. Bad blocks are always
+    /// checked before being challenged.
+    #[serde(default)]
+    pub skip_trusted_checkpoint_checks: bool,
 }
 
-#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub const DEFAULT_DEBUG_TX_DUMP_MAX_FILES: usize = 100;
+
+fn default_debug_tx_dump_max_files() -> usize {
+    DEFAULT_DEBUG_TX_DUMP_MAX_FILES
+}
+
+fn default_debug_tx_dump_formats() -> Vec<DumpFormat> {
+    vec![DumpFormat::Json]
+}
+
+/// File format of dumped transactions
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpFormat {
+    /// Molecule serialized transaction
+    Molecule,
+    /// Mock transaction for ckb-debugger, or the raw transaction if any cell
+    /// can't be resolved
+    Json,
+    /// Transaction file accepted by `ckb-cli tx`
+    CkbRpcTx,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BlockProducerConfig {
     pub account_id: u32,
     /// Directory to save debugging info of l1 transactions
     pub debug_tx_dump_path: PathBuf,
+    /// Max number of dumped transactions to keep, oldest are removed first,
+    /// 0 means unlimited
+    #[serde(default = "default_debug_tx_dump_max_files")]
+    pub debug_tx_dump_max_files: usize,
+    #[serde(default = "default_debug_tx_dump_formats")]
+    pub debug_tx_dump_formats: Vec<DumpFormat>,
     // cell deps
     pub rollup_cell_type_dep: CellDep,
     pub rollup_config_cell_dep: CellDep,
@@ -84,6 +192,102 @@ pub struct BlockProducerConfig {
     pub allowed_contract_deps: HashMap<H256, CellDep>,
     pub challenger_config: ChallengerConfig,
     pub wallet_config: WalletConfig,
+    #[serde(default)]
+    pub payment_cells_config: PaymentCellsConfig,
+    /// Number of layer1 blocks a block submission must be buried under
+    /// before producing the next block, 0 means don't wait
+    #[serde(default)]
+    pub submission_confirmation_depth: u64,
+    /// Dry run block submission and abort it on failure
+    #[serde(default)]
+    pub dry_run_before_submit: bool,
+    #[serde(default)]
+    pub deposit_order: DepositOrder,
+    #[serde(default)]
+    pub idle_block_mode: IdleBlockMode,
+}
+
+// Keep defaults in line with the serde ones
+impl Default for BlockProducerConfig {
+    fn default() -> Self {
+        BlockProducerConfig {
+            account_id: Default::default(),
+            debug_tx_dump_path: Default::default(),
+            debug_tx_dump_max_files: default_debug_tx_dump_max_files(),
+            debug_tx_dump_formats: default_debug_tx_dump_formats(),
+            rollup_cell_type_dep: Default::default(),
+            rollup_config_cell_dep: Default::default(),
+            deposit_cell_lock_dep: Default::default(),
+            stake_cell_lock_dep: Default::default(),
+            poa_lock_dep: Default::default(),
+            poa_state_dep: Default::default(),
+            custodian_cell_lock_dep: Default::default(),
+            withdrawal_cell_lock_dep: Default::default(),
+            challenge_cell_lock_dep: Default::default(),
+            l1_sudt_type_dep: Default::default(),
+            allowed_eoa_deps: Default::default(),
+            allowed_contract_deps: Default::default(),
+            challenger_config: Default::default(),
+            wallet_config: Default::default(),
+            payment_cells_config: Default::default(),
+            submission_confirmation_depth: Default::default(),
+            dry_run_before_submit: Default::default(),
+            deposit_order: Default::default(),
+            idle_block_mode: Default::default(),
+        }
+    }
+}
+
+/// Order of deposit cells to be packed into a block
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DepositOrder {
+    /// Layer1 cell index order
+    Index,
+    /// Largest capacity first
+    Capacity,
+}
+
+impl Default for DepositOrder {
+    fn default() -> Self {
+        DepositOrder::Index
+    }
+}
+
+/// Block production when there are no txs, withdrawals or deposits to pack
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleBlockMode {
+    /// Produce empty blocks in every round
+    Always,
+    /// Produce blocks only if there is something to pack
+    Pending,
+    /// Same as `Pending`, but produce an empty block if the tip is older
+    /// than `interval_secs`
+    KeepAlive { interval_secs: u64 },
+}
+
+impl Default for IdleBlockMode {
+    fn default() -> Self {
+        IdleBlockMode::Always
+    }
+}
+
+/// Payment cells selection for tx fee
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaymentCellsConfig {
+    /// Skip dust cells whose capacity is less than this value
+    pub min_capacity: u64,
+    /// Maximum number of payment cells collected by one query
+    pub max_cells: Option<usize>,
+    /// Reuse queried payment cells for this long unless one of them is
+    /// consumed, 0 disables the cache
+    #[serde(default)]
+    pub cache_ttl_ms: u64,
+    /// Skip cells committed within this many layer1 blocks of the indexer
+    /// tip, 0 means no maturity requirement
+    #[serde(default)]
+    pub maturity_blocks: u64,
 }
 
 #[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
@@ -103,6 +307,12 @@ pub struct Web3IndexerConfig {
     pub database_url: String,
     pub polyjuice_script_type_hash: H256,
     pub eth_account_lock_hash: H256,
+    /// Store raw l2 transaction args as input of non-polyjuice transactions
+    #[serde(default)]
+    pub store_raw_input: bool,
+    /// Max concurrent script and receipt lookups of a block, 0 means sequential
+    #[serde(default)]
+    pub lookup_concurrency: usize,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -0,0 +1,148 @@
+use crate::Config;
+
+use anyhow::{Context, Result};
+use std::{env, fs, path::Path};
+
+/// Prefix of environment variables overriding config fields, nested keys
+/// are separated by `__`, e.g. `GODWOKEN_STORE__PATH=./data`
+const ENV_PREFIX: &str = "GODWOKEN_";
+
+impl Config {
+    /// Load config from `base` file, fields set in `override_path` file
+    /// replace the base ones, then fields set by `GODWOKEN_*` environment
+    /// variables replace both. Tables are merged field by field, other
+    /// values (including arrays) are replaced as a whole.
+    pub fn load_layered<P: AsRef<Path>>(base: P, override_path: Option<P>) -> Result<Config> {
+        let read = |path: &Path| {
+            fs::read_to_string(path)
+                .with_context(|| format!("read config file from {}", path.to_string_lossy()))
+        };
+        let base_content = read(base.as_ref())?;
+        let override_content = match override_path {
+            Some(path) => Some(read(path.as_ref())?),
+            None => None,
+        };
+        Self::from_layered_str(&base_content, override_content.as_deref(), env::vars())
+    }
+
+    pub fn from_layered_str<I>(
+        base: &str,
+        override_content: Option<&str>,
+        env_vars: I,
+    ) -> Result<Config>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut value: toml::Value = toml::from_str(base).with_context(|| "parse config file")?;
+        if let Some(content) = override_content {
+            let override_value = toml::from_str(content).with_context(|| "parse override file")?;
+            merge(&mut value, override_value);
+        }
+        for (name, raw_value) in env_vars {
+            if let Some(env_value) = env_override(&name, &raw_value) {
+                merge(&mut value, env_value);
+            }
+        }
+        let config = value.try_into().with_context(|| "parse layered config")?;
+        Ok(config)
+    }
+}
+
+/// Nested table of the field set by an environment variable, keys are
+/// lowercased. Values are parsed as toml, e.g. `true` or `10`, or taken as
+/// strings if they aren't valid toml.
+fn env_override(name: &str, raw_value: &str) -> Option<toml::Value> {
+    let fields = name.strip_prefix(ENV_PREFIX)?;
+    if fields.is_empty() {
+        return None;
+    }
+    let parsed_value = toml::from_str::<toml::value::Table>(&format!("value = {}", raw_value))
+        .ok()
+        .and_then(|mut table| table.remove("value"));
+    let mut value = parsed_value.unwrap_or_else(|| toml::Value::String(raw_value.to_string()));
+    for key in fields.rsplit("__") {
+        let mut table = toml::value::Table::new();
+        table.insert(key.to_lowercase(), value);
+        value = toml::Value::Table(table);
+    }
+    Some(value)
+}
+
+fn merge(base: &mut toml::Value, override_value: toml::Value) {
+    match (base, override_value) {
+        (toml::Value::Table(base), toml::Value::Table(override_table)) => {
+            for (key, value) in override_table {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_layered_config() {
+        let mut base_config = Config::default();
+        base_config.rpc_server.listen = "127.0.0.1:8119".to_string();
+        base_config.store.path = "./base".into();
+        base_config.chain.max_safe_reorg_depth = Some(10);
+        base_config.backends.push(Default::default());
+        base_config.backends.push(Default::default());
+        let base = toml::to_string_pretty(&base_config).unwrap();
+
+        let override_content = r#"
+            backends = []
+
+            [store]
+            path = "./override"
+
+            [chain]
+            trust_mode = true
+        "#;
+        let config =
+            Config::from_layered_str(&base, Some(override_content), std::iter::empty()).unwrap();
+
+        let mut expected = base_config.clone();
+        expected.backends.clear();
+        expected.store.path = "./override".into();
+        expected.chain.trust_mode = true;
+        assert_eq!(config, expected);
+    }
+
+    #[test]
+    fn test_merge_env_overrides() {
+        let mut base_config = Config::default();
+        base_config.store.path = "./base".into();
+        base_config.chain.max_safe_reorg_depth = Some(10);
+        let base = toml::to_string_pretty(&base_config).unwrap();
+
+        let override_content = r#"
+            [store]
+            path = "./override"
+        "#;
+        let env_vars = vec![
+            ("GODWOKEN_STORE__PATH", "./env"),
+            ("GODWOKEN_CHAIN__TRUST_MODE", "true"),
+            ("GODWOKEN_CHAIN__MAX_SAFE_REORG_DEPTH", "20"),
+            ("OTHER_STORE__PATH", "./other"),
+            ("GODWOKEN_", "ignored"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let config = Config::from_layered_str(&base, Some(override_content), env_vars).unwrap();
+
+        let mut expected = base_config.clone();
+        expected.store.path = "./env".into();
+        expected.chain.trust_mode = true;
+        expected.chain.max_safe_reorg_depth = Some(20);
+        assert_eq!(config, expected);
+    }
+}
@@ -1,3 +1,4 @@
 mod config;
+mod layered;
 
 pub use config::*;
@@ -0,0 +1,71 @@
+//! ReadOnlyDB wrapper base on rocksdb read-only instance
+use crate::schema::Col;
+use crate::{internal_error, Result};
+use rocksdb::ops::{GetColumnFamilys, GetPinnedCF, OpenCF};
+use rocksdb::{DBPinnableSlice, Options, ReadOnlyDB as RawReadOnlyDB};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Read-only instance doesn't take the db lock, so it can be opened
+/// alongside a primary instance. Writes of the primary after open are
+/// invisible until reopen.
+#[derive(Clone)]
+pub struct ReadOnlyDB {
+    pub(crate) inner: Arc<RawReadOnlyDB>,
+}
+
+impl ReadOnlyDB {
+    pub fn open_cf<P: AsRef<Path>>(path: P, columns: u32) -> Result<Self> {
+        let opts = Options::default();
+        let cf_names: Vec<_> = (0..columns).map(|c| c.to_string()).collect();
+        let db = RawReadOnlyDB::open_cf(&opts, path, &cf_names).map_err(|err| {
+            internal_error(format!("failed to open the read-only database: {}", err))
+        })?;
+        Ok(ReadOnlyDB {
+            inner: Arc::new(db),
+        })
+    }
+
+    pub fn get_pinned(&self, col: Col, key: &[u8]) -> Result<Option<DBPinnableSlice>> {
+        let cf = self
+            .inner
+            .cf_handle(&col.to_string())
+            .ok_or_else(|| internal_error(format!("column {} not found", col)))?;
+        self.inner.get_pinned_cf(cf, &key).map_err(internal_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadOnlyDB;
+    use crate::{config::Config as DBConfig, RocksDB};
+
+    #[test]
+    fn test_read_only_db() {
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("test_read_only_db")
+            .tempdir()
+            .unwrap();
+        let config = DBConfig {
+            path: tmp_dir.as_ref().to_path_buf(),
+            ..Default::default()
+        };
+
+        // Missing db
+        assert!(ReadOnlyDB::open_cf(tmp_dir.path().join("missing"), 2).is_err());
+
+        let db = RocksDB::open(&config, 2);
+        db.put_default("k0", "v0").unwrap();
+        let txn = db.transaction();
+        txn.put(1, &[1], &[1]).unwrap();
+        txn.commit().unwrap();
+
+        let read_only_db = ReadOnlyDB::open_cf(&config.path, 2).unwrap();
+        assert_eq!(
+            read_only_db.get_pinned(1, &[1]).unwrap().as_deref(),
+            Some(&[1u8][..])
+        );
+        assert!(read_only_db.get_pinned(1, &[2]).unwrap().is_none());
+        assert!(read_only_db.get_pinned(2, &[1]).is_err());
+    }
+}
@@ -3,7 +3,7 @@
 /// Column families alias type
 pub type Col = u8;
 /// Total column number
-pub const COLUMNS: u32 = 23;
+pub const COLUMNS: u32 = 24;
 /// Column store meta data
 pub const COLUMN_META: Col = 0;
 /// Column store chain index
@@ -50,6 +50,8 @@ pub const COLUMN_CHECKPOINT: Col = 20;
 pub const COLUMN_REVERTED_BLOCK_SMT_BRANCH: Col = 21;
 /// Column reverted block SMT leaf
 pub const COLUMN_REVERTED_BLOCK_SMT_LEAF: Col = 22;
+/// Column withdrawals by owner lock hash
+pub const COLUMN_OWNER_WITHDRAWALS: Col = 23;
 
 /// chain id
 pub const META_CHAIN_ID_KEY: &[u8] = b"CHAIN_ID";
@@ -63,8 +65,10 @@ pub const META_REVERTED_BLOCK_SMT_ROOT_KEY: &[u8] = b"REVERTED_BLOCK_SMT_ROOT_KE
 pub const META_ACCOUNT_SMT_ROOT_KEY: &[u8] = b"ACCOUNT_SMT_ROOT_KEY";
 /// account SMT count
 pub const META_ACCOUNT_SMT_COUNT_KEY: &[u8] = b"ACCOUNT_SMT_COUNT_KEY";
+/// unsafe reorg which halted the chain, until an operator clears it
+pub const META_UNSAFE_REORG_KEY: &[u8] = b"UNSAFE_REORG_KEY";
 
 /// CHAIN_SPEC_HASH_KEY tracks the hash of chain spec which created current database
 pub const CHAIN_SPEC_HASH_KEY: &[u8] = b"chain-spec-hash";
-/// CHAIN_SPEC_HASH_KEY tracks the current database version.
+/// MIGRATION_VERSION_KEY tracks the current database version.
 pub const MIGRATION_VERSION_KEY: &[u8] = b"db-version";
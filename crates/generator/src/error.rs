@@ -17,6 +17,8 @@ pub enum Error {
     Deposit(DepositError),
     #[error("Withdrawal error {0}")]
     Withdrawal(WithdrawalError),
+    #[error("Non-deterministic execution of tx {tx_index}: {diff}")]
+    NonDeterministic { tx_index: u32, diff: String },
 }
 
 impl From<StateError> for Error {
@@ -67,6 +69,8 @@ pub enum WithdrawalError {
     WithdrawFakedCKB,
     #[error("Non positive sudt amount")]
     NonPositiveSUDTAmount,
+    #[error("Invalid owner lock hash")]
+    InvalidOwnerLock,
 }
 
 impl From<WithdrawalError> for Error {
@@ -91,6 +95,8 @@ pub enum AccountError {
     NonceOverflow,
     #[error("can't find script for account {account_id}")]
     ScriptNotFound { account_id: u32 },
+    #[error("unknown receiver {account_id}")]
+    UnknownReceiver { account_id: u32 },
 }
 
 impl From<AccountError> for Error {
@@ -99,6 +105,25 @@ impl From<AccountError> for Error {
     }
 }
 
+/// Invalid generator config
+#[derive(Error, Debug, PartialEq, Clone, Eq)]
+pub enum ConfigError {
+    #[error("cycles per gas must be positive")]
+    ZeroCyclesPerGas,
+}
+
+/// Error of initializing genesis on an already initialized store
+#[derive(Error, Debug, PartialEq, Clone, Eq)]
+pub enum GenesisError {
+    #[error("store is already initialized by rollup_type_hash {chain_id:?}")]
+    ChainIdMismatch { chain_id: H256 },
+    #[error("stored genesis {stored:?} differs from config genesis {expected:?}")]
+    GenesisMismatch { stored: H256, expected: H256 },
+    /// Stored genesis matches the config, it's not rewritten
+    #[error("store is already initialized with genesis {genesis_hash:?}")]
+    AlreadyInitialized { genesis_hash: H256 },
+}
+
 /// Transaction error
 #[derive(Error, Debug, PartialEq, Clone, Eq)]
 pub enum TransactionError {
@@ -185,3 +210,29 @@ impl From<TransactionValidateError> for Error {
         }
     }
 }
+
+/// Store failure which may succeed on retry, other state errors are logical
+/// and fail the same way again
+pub fn is_transient_state_error(err: &StateError) -> bool {
+    matches!(err, StateError::Store | StateError::SMT(SMTError::Store(_)))
+}
+
+impl TransactionError {
+    pub fn is_transient(&self) -> bool {
+        match self {
+            TransactionError::State(err) => is_transient_state_error(err),
+            TransactionError::SMT(SMTError::Store(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+impl TransactionValidateError {
+    pub fn is_transient(&self) -> bool {
+        match self {
+            TransactionValidateError::Transaction(err) => err.is_transient(),
+            TransactionValidateError::State(err) => is_transient_state_error(err),
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,9 @@
+//! Gas of polyjuice transactions derived from execution cycles
+
+use std::num::NonZeroU64;
+
+/// Gas charged for `cycles`, rounded up
+pub fn cycles_to_gas(cycles: u64, cycles_per_gas: NonZeroU64) -> u64 {
+    let cycles_per_gas = cycles_per_gas.get();
+    cycles / cycles_per_gas + (cycles % cycles_per_gas != 0) as u64
+}
@@ -9,9 +9,15 @@ use crate::{
     error::{Error, TransactionError},
     sudt::build_l2_sudt_script,
 };
-use crate::{error::AccountError, syscalls::L2Syscalls};
 use crate::{error::LockAlgorithmError, traits::StateExt};
+use crate::{
+    error::{is_transient_state_error, AccountError, ConfigError},
+    gas::cycles_to_gas,
+    journal::TxStateJournal,
+    syscalls::L2Syscalls,
+};
 use gw_common::{
+    blake2b::new_blake2b,
     builtins::CKB_SUDT_ACCOUNT_ID,
     error::Error as StateError,
     h256_ext::H256Ext,
@@ -23,15 +29,20 @@ use gw_types::{
     core::{ChallengeTargetType, ScriptHashType},
     offchain::RunResult,
     packed::{
-        AccountMerkleState, BlockInfo, ChallengeTarget, DepositRequest, L2Block, L2Transaction,
-        RawL2Block, RawL2Transaction, TxReceipt, WithdrawalReceipt, WithdrawalRequest,
+        AccountMerkleState, BlockInfo, ChallengeTarget, DepositRequest, Fee, L2Block,
+        L2Transaction, LogItem, RawL2Block, RawL2Transaction, RawWithdrawalRequest, TxReceipt,
+        WithdrawalReceipt, WithdrawalRequest,
     },
     prelude::*,
 };
 
 use ckb_vm::{
+    instructions::cost_model::instruction_cycles,
     machine::asm::{AsmCoreMachine, AsmMachine},
-    DefaultMachineBuilder,
+    DefaultMachineBuilder, SupportMachine,
+};
+use std::{
+    collections::HashMap, fmt::Display, num::NonZeroU64, path::PathBuf, thread, time::Duration,
 };
 
 // TODO ensure this value
@@ -40,10 +51,32 @@ const MIN_WITHDRAWAL_CAPACITY: u64 = 100_00000000;
 const MAX_WRITE_DATA_BYTES_LIMIT: usize = 25_000;
 // 2MB
 const MAX_READ_DATA_BYTES_LIMIT: usize = 1024 * 1024 * 2;
+// Retries of per transaction store reads on transient store errors
+const MAX_TRANSIENT_RETRIES: u32 = 3;
+const TRANSIENT_RETRY_INTERVAL: Duration = Duration::from_millis(10);
 
 pub struct StateTransitionArgs {
     pub l2block: L2Block,
     pub deposit_requests: Vec<DepositRequest>,
+    /// Skip withdrawal and transaction signature checks, only for blocks
+    /// already finalized on layer1
+    pub skip_signature_check: bool,
+}
+
+/// Fields of an unsigned withdrawal request
+#[derive(Debug, Clone, Default)]
+pub struct WithdrawalRequestParams {
+    pub account_script_hash: H256,
+    pub nonce: u32,
+    pub capacity: u64,
+    pub amount: u128,
+    pub sudt_script_hash: H256,
+    pub sell_amount: u128,
+    pub sell_capacity: u64,
+    pub owner_lock_hash: H256,
+    pub payment_lock_hash: H256,
+    pub fee_sudt_id: u32,
+    pub fee_amount: u128,
 }
 
 pub enum StateTransitionResult {
@@ -59,10 +92,21 @@ pub enum StateTransitionResult {
     Error(Error),
 }
 
+/// Backends loaded by transactions of a block. Scripts are keyed by their
+/// hash, so a cached backend stays valid across transactions.
+#[derive(Default)]
+struct BackendCache {
+    backends: HashMap<H256, Backend>,
+}
+
 pub struct Generator {
     backend_manage: BackendManage,
     account_lock_manage: AccountLockManage,
     rollup_context: RollupContext,
+    batch_tx_execution: bool,
+    double_tx_execution: bool,
+    state_journal_dir: Option<PathBuf>,
+    cycles_per_gas: Option<NonZeroU64>,
 }
 
 impl Generator {
@@ -75,9 +119,49 @@ impl Generator {
             backend_manage,
             account_lock_manage,
             rollup_context,
+            batch_tx_execution: false,
+            double_tx_execution: false,
+            state_journal_dir: None,
+            cycles_per_gas: None,
         }
     }
 
+    /// Keep backends loaded by earlier transactions of a block in
+    /// `verify_and_apply_state_transition` instead of loading them from state
+    /// for each transaction. Chain view and block info are shared already.
+    pub fn with_batch_tx_execution(mut self, batch_tx_execution: bool) -> Self {
+        self.batch_tx_execution = batch_tx_execution;
+        self
+    }
+
+    /// Execute each transaction a second time with freshly loaded backends in
+    /// `verify_and_apply_state_transition`, and fail the block if the two run
+    /// results differ. Both runs start from the same state, so identical run
+    /// results imply identical post states.
+    pub fn with_double_tx_execution(mut self, double_tx_execution: bool) -> Self {
+        self.double_tx_execution = double_tx_execution;
+        self
+    }
+
+    /// Check each post tx checkpoint in `verify_and_apply_state_transition`,
+    /// and dump the state writes of a transaction into `dir` if its checkpoint
+    /// diverges from the one in block. Only for diagnosis, the state
+    /// transition result is not affected.
+    pub fn with_state_journal_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.state_journal_dir = dir;
+        self
+    }
+
+    /// Meter execution cycles and report `ceil(cycles / cycles_per_gas)` as
+    /// `cycles_gas_used` of run results. Backends still charge fee by their
+    /// own gas, which stays the gas used of polyjuice system logs.
+    pub fn with_cycles_per_gas(mut self, cycles_per_gas: Option<u64>) -> Result<Self, ConfigError> {
+        self.cycles_per_gas = cycles_per_gas
+            .map(|n| NonZeroU64::new(n).ok_or(ConfigError::ZeroCyclesPerGas))
+            .transpose()?;
+        Ok(self)
+    }
+
     pub fn rollup_context(&self) -> &RollupContext {
         &self.rollup_context
     }
@@ -176,6 +260,39 @@ impl Generator {
         Ok(())
     }
 
+    /// Build a withdrawal request and verify it against current state, the
+    /// returned request is unsigned
+    pub fn build_withdrawal_request<S: State + CodeStore>(
+        &self,
+        state: &S,
+        params: WithdrawalRequestParams,
+    ) -> Result<WithdrawalRequest, Error> {
+        if params.owner_lock_hash.is_zero() {
+            return Err(WithdrawalError::InvalidOwnerLock.into());
+        }
+
+        let fee = Fee::new_builder()
+            .sudt_id(params.fee_sudt_id.pack())
+            .amount(params.fee_amount.pack())
+            .build();
+        let raw = RawWithdrawalRequest::new_builder()
+            .nonce(params.nonce.pack())
+            .capacity(params.capacity.pack())
+            .amount(params.amount.pack())
+            .sudt_script_hash(params.sudt_script_hash.pack())
+            .account_script_hash(params.account_script_hash.pack())
+            .sell_amount(params.sell_amount.pack())
+            .sell_capacity(params.sell_capacity.pack())
+            .owner_lock_hash(params.owner_lock_hash.pack())
+            .payment_lock_hash(params.payment_lock_hash.pack())
+            .fee(fee)
+            .build();
+        let withdrawal_request = WithdrawalRequest::new_builder().raw(raw).build();
+
+        self.verify_withdrawal_request(state, &withdrawal_request)?;
+        Ok(withdrawal_request)
+    }
+
     /// Check withdrawal request signature
     pub fn check_withdrawal_request_signature<S: State + CodeStore>(
         &self,
@@ -230,6 +347,24 @@ impl Generator {
             .into());
         }
 
+        self.check_transaction_receiver(state, tx)?;
+
+        Ok(())
+    }
+
+    /// Check transaction receiver is a registered account
+    pub fn check_transaction_receiver<S: State + CodeStore>(
+        &self,
+        state: &S,
+        tx: &L2Transaction,
+    ) -> Result<(), TransactionValidateError> {
+        let receiver_id: u32 = tx.raw().to_id().unpack();
+        if state.get_script_hash(receiver_id)?.is_zero() {
+            return Err(AccountError::UnknownReceiver {
+                account_id: receiver_id,
+            }
+            .into());
+        }
         Ok(())
     }
 
@@ -286,15 +421,26 @@ impl Generator {
     ) -> StateTransitionResult {
         let raw_block = args.l2block.raw();
         let block_info = get_block_info(&raw_block);
+        let _span = tracing::debug_span!(
+            "apply_state_transition",
+            block_number = Unpack::<u64>::unpack(&raw_block.number())
+        )
+        .entered();
 
         // apply withdrawal to state
         let withdrawal_requests: Vec<_> = args.l2block.withdrawals().into_iter().collect();
+        let withdrawals_len = withdrawal_requests.len();
         let block_hash = raw_block.hash();
         let block_producer_id: u32 = block_info.block_producer_id().unpack();
 
         let mut withdrawal_receipts = Vec::with_capacity(withdrawal_requests.len());
         for (wth_idx, request) in withdrawal_requests.into_iter().enumerate() {
-            if let Err(error) = self.check_withdrawal_request_signature(state, &request) {
+            let checked = if args.skip_signature_check {
+                Ok(())
+            } else {
+                self.check_withdrawal_request_signature(state, &request)
+            };
+            if let Err(error) = checked {
                 let target = build_challenge_target(
                     block_hash.into(),
                     ChallengeTargetType::Withdrawal,
@@ -331,9 +477,28 @@ impl Generator {
         };
 
         // handle transactions
+        let mut backend_cache = if self.batch_tx_execution {
+            Some(BackendCache::default())
+        } else {
+            None
+        };
         let mut tx_receipts = Vec::with_capacity(args.l2block.transactions().len());
         for (tx_index, tx) in args.l2block.transactions().into_iter().enumerate() {
-            if let Err(err) = self.check_transaction_signature(state, &tx) {
+            let _tx_span = tracing::trace_span!("execute_tx", tx_index).entered();
+            let checked = if args.skip_signature_check {
+                Ok(())
+            } else {
+                retry_transient(
+                    "check tx signature",
+                    || self.check_transaction_signature(state, &tx),
+                    TransactionValidateError::is_transient,
+                )
+            };
+            if let Err(err) = checked {
+                // local store failure, not a fault of the block
+                if err.is_transient() {
+                    return StateTransitionResult::Error(err.into());
+                }
                 let target = build_challenge_target(
                     block_hash.into(),
                     ChallengeTargetType::TxSignature,
@@ -348,7 +513,11 @@ impl Generator {
 
             // check nonce
             let raw_tx = tx.raw();
-            let expected_nonce = match state.get_nonce(raw_tx.from_id().unpack()) {
+            let expected_nonce = match retry_transient(
+                "get nonce",
+                || state.get_nonce(raw_tx.from_id().unpack()),
+                is_transient_state_error,
+            ) {
                 Err(err) => return StateTransitionResult::Error(Error::from(err)),
                 Ok(nonce) => nonce,
             };
@@ -369,8 +538,24 @@ impl Generator {
 
             // build call context
             // NOTICE users only allowed to send HandleMessage CallType txs
-            let run_result = match self.execute_transaction(chain, state, &block_info, &raw_tx) {
+            // execution only reads state, it is safe to run again
+            let run_result = match retry_transient(
+                "execute tx",
+                || {
+                    self.execute_transaction_with_cache(
+                        chain,
+                        state,
+                        &block_info,
+                        &raw_tx,
+                        backend_cache.as_mut(),
+                    )
+                },
+                TransactionError::is_transient,
+            ) {
                 Ok(run_result) => run_result,
+                Err(err) if err.is_transient() => {
+                    return StateTransitionResult::Error(Error::Transaction(err));
+                }
                 Err(err) => {
                     let target = build_challenge_target(
                         block_hash.into(),
@@ -385,6 +570,26 @@ impl Generator {
                 }
             };
 
+            if self.double_tx_execution {
+                let diff = match self.execute_transaction(chain, state, &block_info, &raw_tx) {
+                    Ok(second_run_result) => diff_run_results(&run_result, &second_run_result),
+                    Err(err) => Some(format!("second execution failed: {}", err)),
+                };
+                if let Some(diff) = diff {
+                    log::error!(
+                        "non-deterministic execution of block {} tx {} {}: {}",
+                        Unpack::<u64>::unpack(&raw_block.number()),
+                        tx_index,
+                        hex::encode(tx.hash()),
+                        diff
+                    );
+                    return StateTransitionResult::Error(Error::NonDeterministic {
+                        tx_index: tx_index as u32,
+                        diff,
+                    });
+                }
+            }
+
             let apply_result = || -> Result<(), Error> {
                 state.apply_run_result(&run_result)?;
 
@@ -397,6 +602,40 @@ impl Generator {
                         .build()
                 };
 
+                if let Some(ref dir) = self.state_journal_dir {
+                    let checkpoint_index = withdrawals_len + tx_index;
+                    let block_checkpoint: Option<H256> = raw_block
+                        .state_checkpoint_list()
+                        .get(checkpoint_index)
+                        .map(|checkpoint| checkpoint.unpack());
+                    let local_checkpoint = state.calculate_state_checkpoint()?;
+                    if let Some(block_checkpoint) = block_checkpoint {
+                        if local_checkpoint != block_checkpoint {
+                            let journal = TxStateJournal::new(
+                                raw_block.number().unpack(),
+                                tx_index as u32,
+                                local_checkpoint,
+                                block_checkpoint,
+                                &run_result,
+                            );
+                            match journal.dump(dir) {
+                                Ok(path) => log::error!(
+                                    "block {} tx {} checkpoint diverged, state journal: {:?}",
+                                    journal.block_number,
+                                    tx_index,
+                                    path
+                                ),
+                                Err(err) => log::error!(
+                                    "block {} tx {} checkpoint diverged, dump state journal error: {}",
+                                    journal.block_number,
+                                    tx_index,
+                                    err
+                                ),
+                            }
+                        }
+                    }
+                }
+
                 let tx_receipt = TxReceipt::new_builder()
                     .tx_witness_hash(tx.witness_hash().pack())
                     .post_state(post_state)
@@ -459,14 +698,25 @@ impl Generator {
         state: &S,
         block_info: &BlockInfo,
         raw_tx: &RawL2Transaction,
+    ) -> Result<RunResult, TransactionError> {
+        self.execute_transaction_with_cache(chain, state, block_info, raw_tx, None)
+    }
+
+    fn execute_transaction_with_cache<S: State + CodeStore, C: ChainStore>(
+        &self,
+        chain: &C,
+        state: &S,
+        block_info: &BlockInfo,
+        raw_tx: &RawL2Transaction,
+        backend_cache: Option<&mut BackendCache>,
     ) -> Result<RunResult, TransactionError> {
         let sender_id: u32 = raw_tx.from_id().unpack();
         let nonce_before_execution = state.get_nonce(sender_id)?;
 
         let mut run_result = RunResult::default();
-        {
+        let used_cycles = {
             let core_machine = Box::<AsmCoreMachine>::default();
-            let machine_builder =
+            let mut machine_builder =
                 DefaultMachineBuilder::new(core_machine).syscall(Box::new(L2Syscalls {
                     chain,
                     state,
@@ -477,18 +727,35 @@ impl Generator {
                     result: &mut run_result,
                     code_store: state,
                 }));
+            if self.cycles_per_gas.is_some() {
+                machine_builder =
+                    machine_builder.instruction_cycle_func(Box::new(instruction_cycles));
+            }
             let mut machine = AsmMachine::new(machine_builder.build(), None);
             let account_id = raw_tx.to_id().unpack();
             let script_hash = state.get_script_hash(account_id)?;
-            let backend = self
-                .load_backend(state, &script_hash)
-                .ok_or(TransactionError::BackendNotFound { script_hash })?;
+            let backend = match backend_cache {
+                Some(cache) => match cache.backends.get(&script_hash) {
+                    Some(backend) => Some(backend.clone()),
+                    None => self.load_backend(state, &script_hash).map(|backend| {
+                        cache.backends.insert(script_hash, backend.clone());
+                        backend
+                    }),
+                },
+                None => self.load_backend(state, &script_hash),
+            }
+            .ok_or(TransactionError::BackendNotFound { script_hash })?;
             machine.load_program(&backend.generator, &[])?;
             let code = machine.run()?;
             if code != 0 {
                 return Err(TransactionError::InvalidExitCode(code));
             }
-        }
+            machine.machine.cycles()
+        };
+        run_result.used_cycles = used_cycles;
+        run_result.cycles_gas_used = self
+            .cycles_per_gas
+            .map(|cycles_per_gas| cycles_to_gas(used_cycles, cycles_per_gas));
 
         // check nonce is increased by backends
         let nonce_after_execution = {
@@ -525,6 +792,66 @@ impl Generator {
     }
 }
 
+/// Describe the differences of two executions of the same transaction, `None`
+/// if they are identical
+pub(crate) fn diff_run_results(first: &RunResult, second: &RunResult) -> Option<String> {
+    let mut diffs = Vec::new();
+    if first.write_values != second.write_values {
+        let mut keys: Vec<&H256> = first
+            .write_values
+            .keys()
+            .chain(second.write_values.keys())
+            .collect();
+        keys.sort_unstable_by(|a, b| a.as_slice().cmp(b.as_slice()));
+        keys.dedup();
+        for key in keys {
+            let (a, b) = (first.write_values.get(key), second.write_values.get(key));
+            if a != b {
+                diffs.push(format!("write value {:?}: {:?} != {:?}", key, a, b));
+            }
+        }
+    }
+    if first.write_data != second.write_data {
+        diffs.push("write data".to_string());
+    }
+    if first.new_scripts != second.new_scripts {
+        diffs.push("new scripts".to_string());
+    }
+    if first.account_count != second.account_count {
+        diffs.push(format!(
+            "account count: {:?} != {:?}",
+            first.account_count, second.account_count
+        ));
+    }
+    if first.return_data != second.return_data {
+        diffs.push(format!(
+            "return data hash: {} != {}",
+            hex::encode(blake2b_hash(&first.return_data)),
+            hex::encode(blake2b_hash(&second.return_data))
+        ));
+    }
+    let to_slices = |logs: &[LogItem]| -> Vec<Vec<u8>> {
+        logs.iter().map(|log| log.as_slice().to_vec()).collect()
+    };
+    if to_slices(&first.logs) != to_slices(&second.logs) {
+        diffs.push("logs".to_string());
+    }
+
+    if diffs.is_empty() {
+        None
+    } else {
+        Some(diffs.join(", "))
+    }
+}
+
+fn blake2b_hash(data: &[u8]) -> [u8; 32] {
+    let mut hasher = new_blake2b();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
 fn get_block_info(l2block: &RawL2Block) -> BlockInfo {
     BlockInfo::new_builder()
         .block_producer_id(l2block.block_producer_id())
@@ -545,3 +872,30 @@ fn build_challenge_target(
         .target_type(target_type.into())
         .build()
 }
+
+/// Run `read` again on transient store errors, up to `MAX_TRANSIENT_RETRIES`
+/// times. Logical errors and the last transient error are returned as is.
+fn retry_transient<T, E, F, P>(name: &str, mut read: F, is_transient: P) -> Result<T, E>
+where
+    E: Display,
+    F: FnMut() -> Result<T, E>,
+    P: Fn(&E) -> bool,
+{
+    let mut retries = 0;
+    loop {
+        match read() {
+            Err(err) if is_transient(&err) && retries < MAX_TRANSIENT_RETRIES => {
+                retries += 1;
+                log::warn!(
+                    "transient store error on {}, retry {}/{}: {}",
+                    name,
+                    retries,
+                    MAX_TRANSIENT_RETRIES,
+                    err
+                );
+                thread::sleep(TRANSIENT_RETRY_INTERVAL * retries);
+            }
+            result => return result,
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use crate::{traits::StateExt, RollupContext};
-use anyhow::Result;
+use crate::{error::GenesisError, traits::StateExt, RollupContext};
+use anyhow::{anyhow, Result};
 use gw_common::{
     blake2b::new_blake2b,
     builtins::{CKB_SUDT_ACCOUNT_ID, RESERVED_ACCOUNT_ID},
@@ -19,11 +19,70 @@ use gw_types::{
     core::{ScriptHashType, Status},
     packed::{
         AccountMerkleState, BlockMerkleState, GlobalState, L2Block, L2BlockCommittedInfo,
-        RawL2Block, Script, SubmitTransactions,
+        RawL2Block, RollupConfig, Script, SubmitTransactions,
     },
     prelude::*,
 };
 
+/// Check rollup config parameters before a chain is built on it
+pub fn validate_rollup_config(config: &RollupConfig) -> Result<()> {
+    let type_hashes = [
+        (
+            "l1_sudt_script_type_hash",
+            config.l1_sudt_script_type_hash(),
+        ),
+        (
+            "custodian_script_type_hash",
+            config.custodian_script_type_hash(),
+        ),
+        (
+            "deposit_script_type_hash",
+            config.deposit_script_type_hash(),
+        ),
+        (
+            "withdrawal_script_type_hash",
+            config.withdrawal_script_type_hash(),
+        ),
+        (
+            "challenge_script_type_hash",
+            config.challenge_script_type_hash(),
+        ),
+        ("stake_script_type_hash", config.stake_script_type_hash()),
+        (
+            "l2_sudt_validator_script_type_hash",
+            config.l2_sudt_validator_script_type_hash(),
+        ),
+    ];
+    for (name, hash) in type_hashes.iter() {
+        let hash: [u8; 32] = hash.unpack();
+        if hash == [0u8; 32] {
+            return Err(anyhow!("rollup config {} is zero", name));
+        }
+    }
+
+    let finality_blocks: u64 = config.finality_blocks().unpack();
+    if 0 == finality_blocks {
+        return Err(anyhow!("rollup config finality_blocks is zero"));
+    }
+    let challenge_maturity_blocks: u64 = config.challenge_maturity_blocks().unpack();
+    if 0 == challenge_maturity_blocks || challenge_maturity_blocks >= finality_blocks {
+        return Err(anyhow!(
+            "rollup config challenge_maturity_blocks {} must be in range (0, finality_blocks {})",
+            challenge_maturity_blocks,
+            finality_blocks
+        ));
+    }
+    let reward_burn_rate: u8 = config.reward_burn_rate().into();
+    if reward_burn_rate > 100 {
+        return Err(anyhow!(
+            "rollup config reward_burn_rate {} is greater than 100",
+            reward_burn_rate
+        ));
+    }
+
+    Ok(())
+}
+
 /// Build genesis block
 pub fn build_genesis(config: &GenesisConfig, secp_data: Bytes) -> Result<GenesisWithGlobalState> {
     let store = Store::open_tmp()?;
@@ -187,14 +246,22 @@ pub fn init_genesis(
     };
     if store.has_genesis()? {
         let chain_id = store.get_chain_id()?;
-        if chain_id == rollup_script_hash {
-            return Ok(());
-        } else {
-            panic!(
-                "The store is already initialized by rollup_type_hash: 0x{}!",
-                hex::encode(chain_id.as_slice())
-            );
+        if chain_id != rollup_script_hash {
+            return Err(GenesisError::ChainIdMismatch { chain_id }.into());
+        }
+        // Don't rewrite genesis, but make sure it's built from the same config
+        let expected: H256 = build_genesis(config, secp_data)?.genesis.hash().into();
+        let stored = store
+            .begin_transaction()
+            .get_block_hash_by_number(0)?
+            .ok_or_else(|| anyhow!("stored genesis block hash isn't exist"))?;
+        if stored != expected {
+            return Err(GenesisError::GenesisMismatch { stored, expected }.into());
+        }
+        return Err(GenesisError::AlreadyInitialized {
+            genesis_hash: stored,
         }
+        .into());
     }
     let db = store.begin_transaction();
     db.setup_chain_id(rollup_script_hash)?;
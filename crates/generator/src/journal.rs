@@ -0,0 +1,95 @@
+//! Per transaction state write journal, dumped for diagnosis when the local
+//! post tx checkpoint diverges from the one committed in block
+
+use gw_common::H256;
+use gw_types::offchain::RunResult;
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateWrite {
+    pub key: H256,
+    pub old_value: H256,
+    pub new_value: H256,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxStateJournal {
+    pub block_number: u64,
+    pub tx_index: u32,
+    pub local_checkpoint: H256,
+    pub block_checkpoint: H256,
+    /// Sorted by key
+    pub writes: Vec<StateWrite>,
+}
+
+impl TxStateJournal {
+    pub fn new(
+        block_number: u64,
+        tx_index: u32,
+        local_checkpoint: H256,
+        block_checkpoint: H256,
+        run_result: &RunResult,
+    ) -> Self {
+        let mut writes: Vec<_> = run_result
+            .write_values
+            .iter()
+            .map(|(key, new_value)| StateWrite {
+                key: *key,
+                old_value: run_result
+                    .prev_values
+                    .get(key)
+                    .cloned()
+                    .unwrap_or_else(H256::zero),
+                new_value: *new_value,
+            })
+            .collect();
+        writes.sort_unstable_by(|a, b| a.key.as_slice().cmp(b.key.as_slice()));
+
+        TxStateJournal {
+            block_number,
+            tx_index,
+            local_checkpoint,
+            block_checkpoint,
+            writes,
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}-{}-state-journal.txt", self.block_number, self.tx_index)
+    }
+
+    /// Header lines followed by one `key old_value new_value` line per write,
+    /// all values in hex
+    pub fn encode(&self) -> String {
+        let mut buf = format!(
+            "block: {}\ntx_index: {}\nlocal_checkpoint: {}\nblock_checkpoint: {}\n",
+            self.block_number,
+            self.tx_index,
+            hex::encode(self.local_checkpoint.as_slice()),
+            hex::encode(self.block_checkpoint.as_slice()),
+        );
+        for write in self.writes.iter() {
+            buf.push_str(&format!(
+                "{} {} {}\n",
+                hex::encode(write.key.as_slice()),
+                hex::encode(write.old_value.as_slice()),
+                hex::encode(write.new_value.as_slice()),
+            ));
+        }
+        buf
+    }
+
+    /// Write journal into `dir`, return the file path
+    pub fn dump<P: AsRef<Path>>(&self, dir: P) -> io::Result<PathBuf> {
+        fs::create_dir_all(dir.as_ref())?;
+        let path = dir.as_ref().join(self.file_name());
+        let mut file = fs::File::create(&path)?;
+        file.write_all(self.encode().as_bytes())?;
+        file.sync_all()?;
+        Ok(path)
+    }
+}
@@ -5,8 +5,10 @@ pub mod account_lock_manage;
 pub mod backend_manage;
 pub mod dummy_state;
 pub mod error;
+pub mod gas;
 pub mod generator;
 pub mod genesis;
+pub mod journal;
 pub mod sudt;
 pub mod syscalls;
 pub mod traits;
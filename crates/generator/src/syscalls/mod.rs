@@ -140,7 +140,7 @@ impl<'a, S: State, C: ChainStore, Mac: SupportMachine> Syscalls<Mac> for L2Sysca
                 let key = load_data_h256(machine, key_addr)?;
                 let value_addr = machine.registers()[A1].to_u64();
                 let value = load_data_h256(machine, value_addr)?;
-                self.result.write_values.insert(key, value);
+                self.set_raw(key, value)?;
                 machine.set_register(A0, Mac::REG::from_u8(SUCCESS));
                 Ok(true)
             }
@@ -251,31 +251,31 @@ impl<'a, S: State, C: ChainStore, Mac: SupportMachine> Syscalls<Mac> for L2Sysca
 
                 // Same logic from State::create_account()
                 let id = self.get_account_count()?;
-                self.result.write_values.insert(
+                self.set_raw(
                     build_account_field_key(id, GW_ACCOUNT_NONCE_TYPE),
                     H256::zero(),
-                );
-                self.result.write_values.insert(
+                )?;
+                self.set_raw(
                     build_account_field_key(id, GW_ACCOUNT_SCRIPT_HASH_TYPE),
                     script_hash.into(),
-                );
+                )?;
                 // script hash to id
-                self.result.write_values.insert(
+                self.set_raw(
                     build_script_hash_to_account_id_key(&script_hash[..]),
                     H256::from_u32(id),
-                );
+                )?;
                 // short script hash to script_hash
-                self.result.write_values.insert(
+                self.set_raw(
                     build_short_script_hash_to_script_hash_key(
                         &script_hash[..DEFAULT_SHORT_SCRIPT_HASH_LEN],
                     ),
                     script_hash.into(),
-                );
+                )?;
                 // insert script
                 self.result
                     .new_scripts
                     .insert(script_hash.into(), script.as_slice().to_vec());
-                self.set_account_count(id + 1);
+                self.set_account_count(id + 1)?;
                 machine
                     .memory_mut()
                     .store32(&account_id_addr, &Mac::REG::from_u32(id))?;
@@ -328,7 +328,7 @@ impl<'a, S: State, C: ChainStore, Mac: SupportMachine> Syscalls<Mac> for L2Sysca
                 hasher.finalize(&mut data_hash);
                 // insert data hash into SMT
                 let data_hash_key = build_data_hash_key(&data_hash);
-                self.result.write_values.insert(data_hash_key, H256::one());
+                self.set_raw(data_hash_key, H256::one())?;
                 // write data
                 self.result
                     .write_data
@@ -495,6 +495,15 @@ impl<'a, S: State, C: ChainStore> L2Syscalls<'a, S, C> {
         };
         Ok(value)
     }
+    fn set_raw(&mut self, key: H256, value: H256) -> Result<(), VMError> {
+        // record value before execution, so the run result can be reverted
+        if !self.result.prev_values.contains_key(&key) {
+            let tree_value = self.state.get_raw(&key).map_err(|_| VMError::Unexpected)?;
+            self.result.prev_values.insert(key, tree_value);
+        }
+        self.result.write_values.insert(key, value);
+        Ok(())
+    }
     fn get_account_count(&self) -> Result<u32, VMError> {
         if let Some(id) = self.result.account_count {
             Ok(id)
@@ -505,8 +514,12 @@ impl<'a, S: State, C: ChainStore> L2Syscalls<'a, S, C> {
             })
         }
     }
-    fn set_account_count(&mut self, count: u32) {
+    fn set_account_count(&mut self, count: u32) -> Result<(), VMError> {
+        if self.result.prev_account_count.is_none() {
+            self.result.prev_account_count = Some(self.get_account_count()?);
+        }
         self.result.account_count = Some(count);
+        Ok(())
     }
     fn get_script(&self, script_hash: &H256) -> Option<Script> {
         self.result
@@ -0,0 +1,48 @@
+use crate::generator::diff_run_results;
+use gw_common::{h256_ext::H256Ext, H256};
+use gw_types::offchain::RunResult;
+
+fn build_run_result() -> RunResult {
+    let mut run_result = RunResult::default();
+    run_result
+        .write_values
+        .insert(H256::from_u32(1), H256::from_u32(100));
+    run_result
+        .write_values
+        .insert(H256::from_u32(2), H256::from_u32(200));
+    run_result.return_data = vec![42u8; 32];
+    run_result
+}
+
+#[test]
+fn test_deterministic_run_results() {
+    let run_result = build_run_result();
+    assert_eq!(diff_run_results(&run_result, &run_result.clone()), None);
+}
+
+#[test]
+fn test_non_deterministic_run_results() {
+    let run_result = build_run_result();
+
+    // backend writes a different value on the second run
+    let mut diverged = run_result.clone();
+    diverged
+        .write_values
+        .insert(H256::from_u32(2), H256::from_u32(201));
+    let diff = diff_run_results(&run_result, &diverged).expect("diff");
+    assert!(diff.contains("write value"), "{}", diff);
+    assert!(!diff.contains("return data"), "{}", diff);
+
+    // backend returns different data on the second run
+    let mut diverged = run_result.clone();
+    diverged.return_data = vec![43u8; 32];
+    let diff = diff_run_results(&run_result, &diverged).expect("diff");
+    assert!(diff.contains("return data hash"), "{}", diff);
+
+    // backend writes an extra key on the second run
+    let mut diverged = run_result.clone();
+    diverged
+        .write_values
+        .insert(H256::from_u32(3), H256::from_u32(300));
+    assert!(diff_run_results(&run_result, &diverged).is_some());
+}
@@ -0,0 +1,35 @@
+use crate::{
+    account_lock_manage::AccountLockManage, backend_manage::BackendManage, error::ConfigError,
+    gas::cycles_to_gas, Generator, RollupContext,
+};
+use std::num::NonZeroU64;
+
+#[test]
+fn test_cycles_to_gas() {
+    let ratio = |n| NonZeroU64::new(n).unwrap();
+    assert_eq!(cycles_to_gas(0, ratio(100)), 0);
+    assert_eq!(cycles_to_gas(100, ratio(100)), 1);
+    assert_eq!(cycles_to_gas(101, ratio(100)), 2);
+    assert_eq!(cycles_to_gas(1_000_001, ratio(1000)), 1001);
+    assert_eq!(cycles_to_gas(u64::MAX, ratio(1)), u64::MAX);
+}
+
+#[test]
+fn test_zero_cycles_per_gas() {
+    let generator = || {
+        Generator::new(
+            BackendManage::from_config(vec![]).unwrap(),
+            AccountLockManage::default(),
+            RollupContext {
+                rollup_script_hash: Default::default(),
+                rollup_config: Default::default(),
+            },
+        )
+    };
+    assert_eq!(
+        generator().with_cycles_per_gas(Some(0)).err(),
+        Some(ConfigError::ZeroCyclesPerGas)
+    );
+    assert!(generator().with_cycles_per_gas(Some(1000)).is_ok());
+    assert!(generator().with_cycles_per_gas(None).is_ok());
+}
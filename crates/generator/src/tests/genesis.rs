@@ -1,4 +1,7 @@
-use crate::genesis::{build_genesis, init_genesis};
+use crate::{
+    error::GenesisError,
+    genesis::{build_genesis, init_genesis, validate_rollup_config},
+};
 use gw_common::{sparse_merkle_tree::H256, state::State};
 use gw_config::GenesisConfig;
 use gw_store::{
@@ -65,3 +68,97 @@ fn test_init_genesis() {
     let code_hash: [u8; 32] = script.code_hash().unpack();
     assert_eq!(code_hash, meta_contract_code_hash);
 }
+
+#[test]
+fn test_validate_rollup_config() {
+    let config = RollupConfig::new_builder()
+        .l1_sudt_script_type_hash([1u8; 32].pack())
+        .custodian_script_type_hash([2u8; 32].pack())
+        .deposit_script_type_hash([3u8; 32].pack())
+        .withdrawal_script_type_hash([4u8; 32].pack())
+        .challenge_script_type_hash([5u8; 32].pack())
+        .stake_script_type_hash([6u8; 32].pack())
+        .l2_sudt_validator_script_type_hash([7u8; 32].pack())
+        .challenge_maturity_blocks(10u64.pack())
+        .finality_blocks(100u64.pack())
+        .reward_burn_rate(50u8.into())
+        .build();
+    validate_rollup_config(&config).unwrap();
+
+    let zero_type_hash = config
+        .clone()
+        .as_builder()
+        .stake_script_type_hash([0u8; 32].pack())
+        .build();
+    assert!(validate_rollup_config(&zero_type_hash).is_err());
+
+    let zero_finality = config
+        .clone()
+        .as_builder()
+        .finality_blocks(0u64.pack())
+        .build();
+    assert!(validate_rollup_config(&zero_finality).is_err());
+
+    let late_challenge_maturity = config
+        .clone()
+        .as_builder()
+        .challenge_maturity_blocks(100u64.pack())
+        .build();
+    assert!(validate_rollup_config(&late_challenge_maturity).is_err());
+
+    let invalid_burn_rate = config.as_builder().reward_burn_rate(101u8.into()).build();
+    assert!(validate_rollup_config(&invalid_burn_rate).is_err());
+
+    assert!(validate_rollup_config(&RollupConfig::default()).is_err());
+}
+
+#[test]
+fn test_init_genesis_twice() {
+    let config = GenesisConfig {
+        timestamp: 42,
+        meta_contract_validator_type_hash: [1u8; 32].into(),
+        rollup_config: RollupConfig::default().into(),
+        rollup_type_hash: [42u8; 32].into(),
+        secp_data_dep: Default::default(),
+    };
+    let store: Store = Store::open_tmp().unwrap();
+    let init = |config: &GenesisConfig| {
+        let committed_info = L2BlockCommittedInfo::default();
+        init_genesis(&store, config, committed_info, Bytes::default())
+    };
+    init(&config).unwrap();
+    let genesis_hash = store.get_tip_block_hash().unwrap();
+
+    // re-init with the same config
+    let err = init(&config).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<GenesisError>(),
+        Some(&GenesisError::AlreadyInitialized { genesis_hash })
+    );
+    assert_eq!(store.get_tip_block_hash().unwrap(), genesis_hash);
+
+    // re-init with different genesis
+    let mismatched_genesis = GenesisConfig {
+        timestamp: 43,
+        ..config.clone()
+    };
+    let err = init(&mismatched_genesis).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<GenesisError>(),
+        Some(GenesisError::GenesisMismatch { stored, .. }) if *stored == genesis_hash
+    ));
+
+    // re-init with different rollup
+    let mismatched_chain = GenesisConfig {
+        rollup_type_hash: [43u8; 32].into(),
+        ..config
+    };
+    let err = init(&mismatched_chain).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<GenesisError>(),
+        Some(&GenesisError::ChainIdMismatch {
+            chain_id: [42u8; 32].into()
+        })
+    );
+    assert_eq!(store.get_tip_block_hash().unwrap(), genesis_hash);
+}
@@ -0,0 +1,67 @@
+use crate::journal::{StateWrite, TxStateJournal};
+use gw_common::{h256_ext::H256Ext, H256};
+use gw_types::offchain::RunResult;
+
+#[test]
+fn test_tx_state_journal() {
+    let mut run_result = RunResult::default();
+    // overwrite an existing key
+    run_result
+        .write_values
+        .insert(H256::from_u32(2), H256::from_u32(200));
+    run_result
+        .prev_values
+        .insert(H256::from_u32(2), H256::from_u32(100));
+    // new key
+    run_result
+        .write_values
+        .insert(H256::from_u32(1), H256::from_u32(10));
+
+    let journal = TxStateJournal::new(5, 1, H256::from_u32(7), H256::from_u32(8), &run_result);
+    let expected_writes = vec![
+        StateWrite {
+            key: H256::from_u32(1),
+            old_value: H256::zero(),
+            new_value: H256::from_u32(10),
+        },
+        StateWrite {
+            key: H256::from_u32(2),
+            old_value: H256::from_u32(100),
+            new_value: H256::from_u32(200),
+        },
+    ];
+    assert_eq!(journal.writes, expected_writes);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = journal.dump(dir.path()).unwrap();
+    assert_eq!(path, dir.path().join("5-1-state-journal.txt"));
+
+    let content = std::fs::read_to_string(path).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(lines[0], "block: 5");
+    assert_eq!(lines[1], "tx_index: 1");
+    assert_eq!(
+        lines[2],
+        format!(
+            "local_checkpoint: {}",
+            hex::encode(H256::from_u32(7).as_slice())
+        )
+    );
+    assert_eq!(
+        lines[3],
+        format!(
+            "block_checkpoint: {}",
+            hex::encode(H256::from_u32(8).as_slice())
+        )
+    );
+    assert_eq!(
+        lines[5],
+        format!(
+            "{} {} {}",
+            hex::encode(H256::from_u32(2).as_slice()),
+            hex::encode(H256::from_u32(100).as_slice()),
+            hex::encode(H256::from_u32(200).as_slice()),
+        )
+    );
+    assert_eq!(lines.len(), 6);
+}
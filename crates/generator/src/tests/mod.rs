@@ -1 +1,6 @@
+mod determinism;
+mod gas;
 mod genesis;
+mod journal;
+mod state_ext;
+mod withdrawal;
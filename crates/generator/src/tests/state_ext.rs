@@ -0,0 +1,121 @@
+use crate::{dummy_state::DummyState, traits::StateExt};
+use gw_common::{
+    h256_ext::H256Ext,
+    merkle_utils::calculate_state_checkpoint,
+    state::{
+        build_account_field_key, build_script_hash_to_account_id_key,
+        build_short_script_hash_to_script_hash_key, State, DEFAULT_SHORT_SCRIPT_HASH_LEN,
+        GW_ACCOUNT_NONCE_TYPE, GW_ACCOUNT_SCRIPT_HASH_TYPE,
+    },
+    H256,
+};
+use gw_store::{
+    state_db::{CheckPoint, StateDBMode, StateDBTransaction},
+    Store,
+};
+use gw_types::offchain::RunResult;
+
+#[test]
+fn test_revert_run_result() {
+    let mut state = DummyState::default();
+    state
+        .update_raw(H256::from_u32(1), H256::from_u32(100))
+        .unwrap();
+    state.set_account_count(2).unwrap();
+    let prev_root = state.calculate_root().unwrap();
+
+    let mut run_result = RunResult::default();
+    // overwrite an existing key
+    run_result
+        .write_values
+        .insert(H256::from_u32(1), H256::from_u32(200));
+    run_result
+        .prev_values
+        .insert(H256::from_u32(1), H256::from_u32(100));
+    // insert a new key
+    run_result
+        .write_values
+        .insert(H256::from_u32(2), H256::from_u32(300));
+    run_result
+        .prev_values
+        .insert(H256::from_u32(2), H256::zero());
+    run_result.account_count = Some(3);
+    run_result.prev_account_count = Some(2);
+
+    state.apply_run_result(&run_result).unwrap();
+    assert_ne!(state.calculate_root().unwrap(), prev_root);
+    assert_eq!(state.get_account_count().unwrap(), 3);
+
+    state.revert_run_result(&run_result).unwrap();
+    assert_eq!(state.calculate_root().unwrap(), prev_root);
+    assert_eq!(state.get_account_count().unwrap(), 2);
+    assert_eq!(
+        state.get_raw(&H256::from_u32(1)).unwrap(),
+        H256::from_u32(100)
+    );
+    assert_eq!(state.get_raw(&H256::from_u32(2)).unwrap(), H256::zero());
+}
+
+#[test]
+fn test_revert_account_creation_on_state_tree() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db =
+        StateDBTransaction::from_checkpoint(&db, CheckPoint::from_genesis(), StateDBMode::Genesis)
+            .unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    tree.create_account([1u8; 32].into()).unwrap();
+    let prev_root = tree.calculate_root().unwrap();
+
+    // Run result creating account 1
+    let script_hash: H256 = [2u8; 32].into();
+    let mut run_result = RunResult::default();
+    let writes = vec![
+        (
+            build_account_field_key(1, GW_ACCOUNT_NONCE_TYPE),
+            H256::zero(),
+        ),
+        (
+            build_account_field_key(1, GW_ACCOUNT_SCRIPT_HASH_TYPE),
+            script_hash,
+        ),
+        (
+            build_script_hash_to_account_id_key(script_hash.as_slice()),
+            H256::from_u32(1),
+        ),
+        (
+            build_short_script_hash_to_script_hash_key(
+                &script_hash.as_slice()[..DEFAULT_SHORT_SCRIPT_HASH_LEN],
+            ),
+            script_hash,
+        ),
+    ];
+    for (key, value) in writes {
+        run_result.write_values.insert(key, value);
+        run_result.prev_values.insert(key, H256::zero());
+    }
+    run_result.account_count = Some(2);
+    run_result.prev_account_count = Some(1);
+
+    tree.apply_run_result(&run_result).unwrap();
+    assert_eq!(tree.get_account_count().unwrap(), 2);
+    assert_eq!(tree.get_script_hash(1).unwrap(), script_hash);
+
+    tree.revert_run_result(&run_result).unwrap();
+    assert_eq!(tree.get_account_count().unwrap(), 1);
+    assert_eq!(tree.calculate_root().unwrap(), prev_root);
+    assert!(tree.get_script_hash(1).unwrap().is_zero());
+}
+
+#[test]
+fn test_calculate_state_checkpoint() {
+    let mut state = DummyState::default();
+    state
+        .update_raw(H256::from_u32(1), H256::from_u32(100))
+        .unwrap();
+    state.set_account_count(3).unwrap();
+
+    let root = state.calculate_root().unwrap();
+    let checkpoint = calculate_state_checkpoint(&root, 3);
+    assert_eq!(state.calculate_state_checkpoint().unwrap(), checkpoint);
+}
@@ -0,0 +1,152 @@
+use crate::{
+    account_lock_manage::AccountLockManage,
+    backend_manage::BackendManage,
+    dummy_state::DummyState,
+    error::{AccountError, Error, WithdrawalError},
+    generator::WithdrawalRequestParams,
+    sudt::build_l2_sudt_script,
+    traits::StateExt,
+    Generator, RollupContext,
+};
+use gw_common::{
+    builtins::CKB_SUDT_ACCOUNT_ID,
+    state::{to_short_address, State},
+    CKB_SUDT_SCRIPT_ARGS, H256,
+};
+use gw_types::{
+    core::ScriptHashType,
+    packed::{RollupConfig, Script},
+    prelude::*,
+};
+
+const CKB: u64 = 100000000;
+
+fn setup() -> (Generator, DummyState, H256) {
+    let rollup_context = RollupContext {
+        rollup_script_hash: [42u8; 32].into(),
+        rollup_config: RollupConfig::new_builder()
+            .l2_sudt_validator_script_type_hash([2u8; 32].pack())
+            .build(),
+    };
+    let mut state = DummyState::default();
+
+    // meta contract
+    let meta_script = Script::new_builder()
+        .hash_type(ScriptHashType::Type.into())
+        .args(vec![0u8; 32].pack())
+        .build();
+    state.create_account_from_script(meta_script).unwrap();
+    // CKB simple UDT
+    let ckb_sudt_script = build_l2_sudt_script(&rollup_context, &CKB_SUDT_SCRIPT_ARGS.into());
+    let ckb_sudt_id = state.create_account_from_script(ckb_sudt_script).unwrap();
+    assert_eq!(ckb_sudt_id, CKB_SUDT_ACCOUNT_ID);
+
+    let user_script = Script::new_builder()
+        .hash_type(ScriptHashType::Type.into())
+        .args(vec![3u8; 20].pack())
+        .build();
+    let user_script_hash: H256 = user_script.hash().into();
+    state.create_account_from_script(user_script).unwrap();
+    state
+        .mint_sudt(
+            CKB_SUDT_ACCOUNT_ID,
+            to_short_address(&user_script_hash),
+            1000 * CKB as u128,
+        )
+        .unwrap();
+
+    let generator = Generator::new(
+        BackendManage::from_config(vec![]).unwrap(),
+        AccountLockManage::default(),
+        rollup_context,
+    );
+    (generator, state, user_script_hash)
+}
+
+#[test]
+fn test_build_withdrawal_request() {
+    let (generator, state, user_script_hash) = setup();
+    let params = WithdrawalRequestParams {
+        account_script_hash: user_script_hash,
+        capacity: 500 * CKB,
+        sudt_script_hash: CKB_SUDT_SCRIPT_ARGS.into(),
+        owner_lock_hash: [4u8; 32].into(),
+        fee_sudt_id: CKB_SUDT_ACCOUNT_ID,
+        fee_amount: 1000,
+        ..Default::default()
+    };
+
+    let request = generator
+        .build_withdrawal_request(&state, params.clone())
+        .unwrap();
+    let raw = request.raw();
+    let account_script_hash: H256 = raw.account_script_hash().unpack();
+    let capacity: u64 = raw.capacity().unpack();
+    let owner_lock_hash: H256 = raw.owner_lock_hash().unpack();
+    let fee_amount: u128 = raw.fee().amount().unpack();
+    assert_eq!(account_script_hash, user_script_hash);
+    assert_eq!(capacity, 500 * CKB);
+    assert_eq!(owner_lock_hash, params.owner_lock_hash);
+    assert_eq!(fee_amount, 1000);
+    assert!(request.signature().is_empty());
+
+    // insufficient capacity
+    let err = generator
+        .build_withdrawal_request(
+            &state,
+            WithdrawalRequestParams {
+                capacity: 99 * CKB,
+                ..params.clone()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        Error::Account(AccountError::InsufficientCapacity {
+            expected: 100 * CKB,
+            actual: 99 * CKB,
+        })
+    );
+
+    // invalid owner lock
+    let err = generator
+        .build_withdrawal_request(
+            &state,
+            WithdrawalRequestParams {
+                owner_lock_hash: H256::zero(),
+                ..params.clone()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, Error::Withdrawal(WithdrawalError::InvalidOwnerLock));
+
+    // overdraft, fee is counted
+    let err = generator
+        .build_withdrawal_request(
+            &state,
+            WithdrawalRequestParams {
+                capacity: 1000 * CKB,
+                ..params.clone()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, Error::Withdrawal(WithdrawalError::Overdraft));
+
+    // wrong nonce
+    let err = generator
+        .build_withdrawal_request(
+            &state,
+            WithdrawalRequestParams {
+                nonce: 1,
+                ..params.clone()
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        Error::Withdrawal(WithdrawalError::Nonce {
+            expected: 0,
+            actual: 1,
+        })
+    );
+}
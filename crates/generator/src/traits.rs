@@ -20,6 +20,7 @@ use gw_types::{
 pub trait StateExt {
     fn create_account_from_script(&mut self, script: Script) -> Result<u32, Error>;
     fn apply_run_result(&mut self, run_result: &RunResult) -> Result<(), Error>;
+    fn revert_run_result(&mut self, run_result: &RunResult) -> Result<(), Error>;
     fn apply_deposit_request(
         &mut self,
         ctx: &RollupContext,
@@ -97,6 +98,18 @@ impl<S: State + CodeStore> StateExt for S {
         Ok(())
     }
 
+    fn revert_run_result(&mut self, run_result: &RunResult) -> Result<(), Error> {
+        for (k, v) in &run_result.prev_values {
+            self.update_raw(*k, *v)?;
+        }
+        if let Some(count) = run_result.prev_account_count {
+            self.set_account_count(count)?;
+        }
+        // NOTE: scripts and data are indexed by hash, keep them since the
+        // reverted keys already unlink them from state
+        Ok(())
+    }
+
     fn pay_fee(
         &mut self,
         payer_short_address: &[u8],
@@ -1,9 +1,12 @@
 use crate::blockchain::Script;
+use crate::h256::to_jsonh256;
 use anyhow::{anyhow, Error as JsonError};
 use ckb_fixed_hash::H256;
 use ckb_jsonrpc_types::{JsonBytes, Uint128, Uint32, Uint64};
+use gw_common::H256 as SmtH256;
 use gw_types::{bytes::Bytes, offchain, packed, prelude::*};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
@@ -904,23 +907,191 @@ impl From<packed::RollupConfig> for RollupConfig {
     }
 }
 
+/// Active rollup parameters, for tools to build deposits and withdrawals
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct RollupInfo {
+    pub rollup_type_hash: H256,
+    pub rollup_config: RollupConfig,
+}
+
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum WithdrawalStatus {
+    Pending,
+    Finalized,
+}
+
+/// Withdrawal of an owner on main chain
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
+pub struct WithdrawalInfo {
+    pub block_number: Uint64,
+    pub block_hash: H256,
+    pub withdrawal_index: Uint32,
+    pub capacity: Uint64,
+    pub amount: Uint128,
+    pub sudt_script_hash: H256,
+    pub status: WithdrawalStatus,
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
 #[serde(rename_all = "snake_case")]
 pub struct RunResult {
     // return data
     pub return_data: JsonBytes,
+    // return data is truncated to the rpc server limit
+    #[serde(default)]
+    pub return_data_truncated: bool,
     // log data
     pub logs: Vec<LogItem>,
+    // gas of executed cycles, if the node derives gas from cycles
+    #[serde(default)]
+    pub cycles_gas_used: Option<Uint64>,
+}
+
+impl RunResult {
+    pub fn truncate_return_data(&mut self, max_size: usize) {
+        self.return_data_truncated |= truncate_bytes(&mut self.return_data, max_size);
+    }
+}
+
+fn truncate_bytes(data: &mut JsonBytes, max_size: usize) -> bool {
+    if data.len() <= max_size {
+        return false;
+    }
+    let truncated = data.clone().into_bytes().slice(..max_size);
+    *data = JsonBytes::from_bytes(truncated);
+    true
 }
 
 impl From<offchain::RunResult> for RunResult {
     fn from(data: offchain::RunResult) -> RunResult {
         let offchain::RunResult {
-            return_data, logs, ..
+            return_data,
+            logs,
+            cycles_gas_used,
+            ..
         } = data;
         RunResult {
             return_data: JsonBytes::from_vec(return_data),
+            return_data_truncated: false,
+            logs: logs.into_iter().map(Into::into).collect(),
+            cycles_gas_used: cycles_gas_used.map(Into::into),
+        }
+    }
+}
+
+/// RunResult with the touched states, for debugging and testing
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct FullRunResult {
+    // return data
+    pub return_data: JsonBytes,
+    // return data is truncated to the rpc server limit
+    #[serde(default)]
+    pub return_data_truncated: bool,
+    // log data
+    pub logs: Vec<LogItem>,
+    // sorted by key
+    pub read_values: Vec<KVPair>,
+    pub write_values: Vec<KVPair>,
+    // values of written keys before execution
+    pub prev_values: Vec<KVPair>,
+    pub account_count: Option<Uint32>,
+    pub prev_account_count: Option<Uint32>,
+    pub new_scripts: Vec<JsonBytes>,
+    pub write_data: Vec<JsonBytes>,
+    // hashes of read data
+    pub read_data: Vec<H256>,
+    // gas of executed cycles, if the node derives gas from cycles
+    #[serde(default)]
+    pub cycles_gas_used: Option<Uint64>,
+}
+
+impl FullRunResult {
+    pub fn truncate_return_data(&mut self, max_size: usize) {
+        self.return_data_truncated |= truncate_bytes(&mut self.return_data, max_size);
+    }
+}
+
+impl From<offchain::RunResult> for FullRunResult {
+    fn from(data: offchain::RunResult) -> FullRunResult {
+        fn sorted<V>(map: HashMap<SmtH256, V>) -> Vec<(SmtH256, V)> {
+            let mut items: Vec<_> = map.into_iter().collect();
+            items.sort_unstable_by_key(|(k, _)| *k);
+            items
+        }
+
+        fn to_kv_pairs(values: HashMap<SmtH256, SmtH256>) -> Vec<KVPair> {
+            let to_kv = |(k, v): (SmtH256, SmtH256)| KVPair {
+                k: to_jsonh256(k),
+                v: to_jsonh256(v),
+            };
+            sorted(values).into_iter().map(to_kv).collect()
+        }
+
+        let offchain::RunResult {
+            read_values,
+            write_values,
+            prev_values,
+            return_data,
+            account_count,
+            prev_account_count,
+            new_scripts,
+            write_data,
+            read_data,
+            logs,
+            used_cycles: _,
+            cycles_gas_used,
+        } = data;
+        FullRunResult {
+            return_data: JsonBytes::from_vec(return_data),
+            return_data_truncated: false,
             logs: logs.into_iter().map(Into::into).collect(),
+            read_values: to_kv_pairs(read_values),
+            write_values: to_kv_pairs(write_values),
+            prev_values: to_kv_pairs(prev_values),
+            account_count: account_count.map(Into::into),
+            prev_account_count: prev_account_count.map(Into::into),
+            new_scripts: sorted(new_scripts)
+                .into_iter()
+                .map(|(_, script)| JsonBytes::from_vec(script))
+                .collect(),
+            write_data: sorted(write_data)
+                .into_iter()
+                .map(|(_, data)| JsonBytes::from_vec(data))
+                .collect(),
+            read_data: sorted(read_data)
+                .into_iter()
+                .map(|(hash, _)| to_jsonh256(hash))
+                .collect(),
+            cycles_gas_used: cycles_gas_used.map(Into::into),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_return_data() {
+        let run_result = offchain::RunResult {
+            return_data: vec![1u8; 64],
+            ..Default::default()
+        };
+
+        let mut truncated: RunResult = run_result.clone().into();
+        truncated.truncate_return_data(64);
+        assert!(!truncated.return_data_truncated);
+        truncated.truncate_return_data(16);
+        assert!(truncated.return_data_truncated);
+        assert_eq!(truncated.return_data.as_bytes(), &[1u8; 16][..]);
+
+        let mut truncated: FullRunResult = run_result.into();
+        truncated.truncate_return_data(16);
+        assert!(truncated.return_data_truncated);
+        assert_eq!(truncated.return_data.len(), 16);
+    }
+}
@@ -0,0 +1,35 @@
+//! Conversions between `ckb_fixed_hash::H256` used by json types and
+//! `gw_common::H256` used by state and store
+
+use ckb_fixed_hash::H256 as JsonH256;
+use gw_common::H256;
+
+pub fn to_h256(v: JsonH256) -> H256 {
+    let h: [u8; 32] = v.into();
+    h.into()
+}
+
+pub fn to_jsonh256(v: H256) -> JsonH256 {
+    let h: [u8; 32] = v.into();
+    h.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_h256_round_trip() {
+        let mut bytes = [0u8; 32];
+        bytes.iter_mut().enumerate().for_each(|(i, b)| *b = i as u8);
+
+        let h256: H256 = bytes.into();
+        let json_h256 = to_jsonh256(h256);
+        assert_eq!(json_h256.as_bytes(), &bytes[..]);
+        assert_eq!(to_h256(json_h256.clone()), h256);
+
+        let json_bytes: [u8; 32] = json_h256.into();
+        assert_eq!(json_bytes, bytes);
+        assert_eq!(h256.as_slice(), &bytes[..]);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod blockchain;
 pub mod godwoken;
+pub mod h256;
 // re-exports
 pub use ckb_jsonrpc_types;
 pub mod debugger;
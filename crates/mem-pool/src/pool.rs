@@ -208,6 +208,10 @@ impl MemPool {
         // we should introduce queue manchanism and only remove tx when tx.nonce is lower
         // reject tx if nonce is not equals account.nonce
         let state = state_db.account_state_tree()?;
+
+        // reject tx to unknown receiver before it enters the pool
+        self.generator.check_transaction_receiver(&state, tx)?;
+
         let account_id: u32 = tx.raw().from_id().unpack();
         let nonce = state.get_nonce(account_id)?;
         let tx_nonce: u32 = tx.raw().nonce().unpack();
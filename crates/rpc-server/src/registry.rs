@@ -2,19 +2,25 @@ use anyhow::Result;
 use async_trait::async_trait;
 use ckb_types::prelude::{Builder, Entity};
 use gw_common::{state::State, H256};
-use gw_generator::{sudt::build_l2_sudt_script, Generator};
+use gw_generator::{sudt::build_l2_sudt_script, Generator, RollupContext};
 use gw_jsonrpc_types::{
     blockchain::Script,
     ckb_jsonrpc_types::{JsonBytes, Uint128, Uint32},
-    godwoken::{GlobalState, L2BlockView, RunResult, TxReceipt},
+    godwoken::{
+        FullRunResult, GlobalState, L2BlockView, RollupInfo, RunResult, TxReceipt, WithdrawalInfo,
+        WithdrawalStatus,
+    },
+    h256::{to_h256, to_jsonh256},
     test_mode::{ShouldProduceBlock, TestModePayload},
 };
 use gw_store::{
     state_db::{CheckPoint, StateDBMode, StateDBTransaction, SubState},
+    transaction::{OwnerWithdrawal, StoreTransaction},
     Store,
 };
 use gw_traits::CodeStore;
 use gw_types::{
+    offchain,
     packed::{self, BlockInfo},
     prelude::*,
 };
@@ -39,23 +45,23 @@ pub trait TestModeRPC {
     async fn should_produce_block(&self) -> Result<ShouldProduceBlock>;
 }
 
-fn to_h256(v: JsonH256) -> H256 {
-    let h: [u8; 32] = v.into();
-    h.into()
-}
-
-fn to_jsonh256(v: H256) -> JsonH256 {
-    let h: [u8; 32] = v.into();
-    h.into()
-}
-
 pub struct Registry {
     generator: Arc<Generator>,
     mem_pool: Arc<MemPool>,
     store: Store,
     tests_rpc_impl: Option<Arc<BoxedTestsRPCImpl>>,
+    max_return_data_size: Option<usize>,
+    min_gas_price: u64,
 }
 
+/// Max size of return data in execution responses
+#[derive(Clone, Copy)]
+struct ReturnDataLimit(Option<usize>);
+
+/// Minimum gas price of polyjuice transactions
+#[derive(Clone, Copy)]
+struct MinGasPrice(u64);
+
 impl Registry {
     pub fn new<T>(
         store: Store,
@@ -72,9 +78,21 @@ impl Registry {
             generator,
             tests_rpc_impl: tests_rpc_impl
                 .map(|r| Arc::new(r as Box<dyn TestModeRPC + Sync + Send + 'static>)),
+            max_return_data_size: None,
+            min_gas_price: 0,
         }
     }
 
+    pub fn with_max_return_data_size(mut self, max_return_data_size: Option<usize>) -> Self {
+        self.max_return_data_size = max_return_data_size;
+        self
+    }
+
+    pub fn with_min_gas_price(mut self, min_gas_price: u64) -> Self {
+        self.min_gas_price = min_gas_price;
+        self
+    }
+
     pub fn build_rpc_server(self) -> Result<RPCServer> {
         let mut server = JsonrpcServer::new();
 
@@ -82,6 +100,8 @@ impl Registry {
             .with_data(Data(self.mem_pool.clone()))
             .with_data(Data(self.generator.clone()))
             .with_data(Data::new(self.store))
+            .with_data(Data::new(ReturnDataLimit(self.max_return_data_size)))
+            .with_data(Data::new(MinGasPrice(self.min_gas_price)))
             .with_method("gw_ping", ping)
             .with_method("gw_get_tip_block_hash", get_tip_block_hash)
             .with_method("gw_get_block_hash", get_block_hash)
@@ -89,6 +109,10 @@ impl Registry {
             .with_method("gw_get_block_by_number", get_block_by_number)
             .with_method("gw_get_balance", get_balance)
             .with_method("gw_get_storage_at", get_storage_at)
+            .with_method(
+                "gw_get_storage_at_by_short_address",
+                get_storage_at_by_short_address,
+            )
             .with_method(
                 "gw_get_account_id_by_script_hash",
                 get_account_id_by_script_hash,
@@ -104,12 +128,19 @@ impl Registry {
             .with_method("gw_get_transaction_receipt", get_transaction_receipt)
             .with_method("gw_execute_l2transaction", execute_l2transaction)
             .with_method("gw_execute_raw_l2transaction", execute_raw_l2transaction)
+            .with_method(
+                "gw_execute_raw_l2transaction_full",
+                execute_raw_l2transaction_full,
+            )
             .with_method("gw_submit_l2transaction", submit_l2transaction)
             .with_method("gw_submit_withdrawal_request", submit_withdrawal_request)
             .with_method(
                 "gw_compute_l2_sudt_script_hash",
                 compute_l2_sudt_script_hash,
-            );
+            )
+            .with_method("gw_get_rollup_config", get_rollup_config)
+            .with_method("gw_get_withdrawals", get_withdrawals)
+            .with_method("eth_gasPrice", gas_price);
 
         // Tests
         if let Some(tests_rpc_impl) = self.tests_rpc_impl {
@@ -173,6 +204,51 @@ async fn get_tip_block_hash(store: Data<Store>) -> Result<JsonH256> {
     Ok(to_jsonh256(tip_block_hash))
 }
 
+async fn get_rollup_config(generator: Data<Generator>) -> Result<RollupInfo> {
+    Ok(to_rollup_info(generator.rollup_context()))
+}
+
+fn to_rollup_info(rollup_context: &RollupContext) -> RollupInfo {
+    RollupInfo {
+        rollup_type_hash: to_jsonh256(rollup_context.rollup_script_hash),
+        rollup_config: rollup_context.rollup_config.clone().into(),
+    }
+}
+
+async fn gas_price(min_gas_price: Data<MinGasPrice>) -> Result<Uint128> {
+    Ok((min_gas_price.0 as u128).into())
+}
+
+// Withdrawals of owner lock hash, empty for unknown owner
+async fn get_withdrawals(
+    Params((owner_lock_hash,)): Params<(JsonH256,)>,
+    store: Data<Store>,
+) -> Result<Vec<WithdrawalInfo>> {
+    let withdrawals = store.get_owner_withdrawals(&to_h256(owner_lock_hash))?;
+    Ok(withdrawals.into_iter().map(to_withdrawal_info).collect())
+}
+
+fn to_withdrawal_info(withdrawal: OwnerWithdrawal) -> WithdrawalInfo {
+    let raw = withdrawal.request.raw();
+    let capacity: u64 = raw.capacity().unpack();
+    let amount: u128 = raw.amount().unpack();
+    let sudt_script_hash: H256 = raw.sudt_script_hash().unpack();
+    let status = if withdrawal.finalized {
+        WithdrawalStatus::Finalized
+    } else {
+        WithdrawalStatus::Pending
+    };
+    WithdrawalInfo {
+        block_number: withdrawal.block_number.into(),
+        block_hash: to_jsonh256(withdrawal.block_hash),
+        withdrawal_index: withdrawal.index.into(),
+        capacity: capacity.into(),
+        amount: amount.into(),
+        sudt_script_hash: to_jsonh256(sudt_script_hash),
+        status,
+    }
+}
+
 async fn get_transaction_receipt(
     Params((tx_hash,)): Params<(JsonH256,)>,
     store: Data<Store>,
@@ -190,6 +266,7 @@ async fn execute_l2transaction(
     Params((l2tx,)): Params<(JsonBytes,)>,
     mem_pool: Data<MemPool>,
     store: Data<Store>,
+    return_data_limit: Data<ReturnDataLimit>,
 ) -> Result<RunResult> {
     let l2tx_bytes = l2tx.into_bytes();
     let tx = packed::L2Transaction::from_slice(&l2tx_bytes)?;
@@ -208,7 +285,10 @@ async fn execute_l2transaction(
         .number(number.pack())
         .build();
 
-    let run_result: RunResult = mem_pool.lock().execute_transaction(tx, &block_info)?.into();
+    let mut run_result: RunResult = mem_pool.lock().execute_transaction(tx, &block_info)?.into();
+    if let Some(max_size) = return_data_limit.0 {
+        run_result.truncate_return_data(max_size);
+    }
     Ok(run_result)
 }
 
@@ -224,7 +304,33 @@ async fn execute_raw_l2transaction(
     Params(params): Params<ExecuteRawL2TransactionParams>,
     mem_pool: Data<MemPool>,
     store: Data<Store>,
+    return_data_limit: Data<ReturnDataLimit>,
 ) -> Result<RunResult, RpcError> {
+    let mut run_result: RunResult = run_raw_l2transaction(params, &mem_pool, &store)?.into();
+    if let Some(max_size) = return_data_limit.0 {
+        run_result.truncate_return_data(max_size);
+    }
+    Ok(run_result)
+}
+
+async fn execute_raw_l2transaction_full(
+    Params(params): Params<ExecuteRawL2TransactionParams>,
+    mem_pool: Data<MemPool>,
+    store: Data<Store>,
+    return_data_limit: Data<ReturnDataLimit>,
+) -> Result<FullRunResult, RpcError> {
+    let mut run_result: FullRunResult = run_raw_l2transaction(params, &mem_pool, &store)?.into();
+    if let Some(max_size) = return_data_limit.0 {
+        run_result.truncate_return_data(max_size);
+    }
+    Ok(run_result)
+}
+
+fn run_raw_l2transaction(
+    params: ExecuteRawL2TransactionParams,
+    mem_pool: &MemPool,
+    store: &Store,
+) -> Result<offchain::RunResult, RpcError> {
     let (raw_l2tx, block_number) = match params {
         ExecuteRawL2TransactionParams::Tip(p) => (p.0, None),
         ExecuteRawL2TransactionParams::Number(p) => p,
@@ -264,10 +370,10 @@ async fn execute_raw_l2transaction(
         .number(number.pack())
         .build();
 
-    let run_result: RunResult = mem_pool
-        .lock()
-        .execute_raw_transaction(raw_l2tx, &block_info, block_number)?
-        .into();
+    let run_result =
+        mem_pool
+            .lock()
+            .execute_raw_transaction(raw_l2tx, &block_info, block_number)?;
     Ok(run_result)
 }
 
@@ -293,6 +399,31 @@ async fn submit_withdrawal_request(
     Ok(())
 }
 
+/// Read only state of the block `block_number`, or of the tip block if
+/// it's not given
+fn state_db_at_block(
+    db: &StoreTransaction,
+    block_number: Option<GwUint64>,
+) -> Result<StateDBTransaction<'_>, RpcError> {
+    let tip_block_number = db.get_tip_block()?.raw().number().unpack();
+    let block_number = match block_number {
+        Some(num) => num.value(),
+        None => tip_block_number,
+    };
+    if block_number > tip_block_number {
+        return Err(RpcError::Provided {
+            code: HEADER_NOT_FOUND_ERR_CODE,
+            message: "header not found",
+        });
+    }
+    let state_db = StateDBTransaction::from_checkpoint(
+        db,
+        CheckPoint::new(block_number, SubState::Block),
+        StateDBMode::ReadOnly,
+    )?;
+    Ok(state_db)
+}
+
 // short_address, sudt_id, block_number
 #[derive(serde::Serialize, serde::Deserialize)]
 #[serde(untagged)]
@@ -311,23 +442,7 @@ async fn get_balance(
     };
 
     let db = store.begin_transaction();
-    let tip_block_number = db.get_tip_block()?.raw().number().unpack();
-    let block_number = match block_number {
-        Some(num) => num.value(),
-        None => tip_block_number,
-    };
-    if block_number > tip_block_number {
-        return Err(RpcError::Provided {
-            code: HEADER_NOT_FOUND_ERR_CODE,
-            message: "header not found",
-        });
-    }
-    let state_db = StateDBTransaction::from_checkpoint(
-        &db,
-        CheckPoint::new(block_number, SubState::Block),
-        StateDBMode::ReadOnly,
-    )?;
-
+    let state_db = state_db_at_block(&db, block_number)?;
     let tree = state_db.account_state_tree()?;
     let balance = tree.get_sudt_balance(sudt_id.into(), short_address.as_bytes())?;
     Ok(balance.into())
@@ -351,23 +466,7 @@ async fn get_storage_at(
     };
 
     let db = store.begin_transaction();
-    let tip_block_number = db.get_tip_block()?.raw().number().unpack();
-    let block_number = match block_number {
-        Some(num) => num.value(),
-        None => tip_block_number,
-    };
-    if block_number > tip_block_number {
-        return Err(RpcError::Provided {
-            code: HEADER_NOT_FOUND_ERR_CODE,
-            message: "header not found",
-        });
-    }
-    let state_db = StateDBTransaction::from_checkpoint(
-        &db,
-        CheckPoint::new(block_number, SubState::Block),
-        StateDBMode::ReadOnly,
-    )?;
-
+    let state_db = state_db_at_block(&db, block_number)?;
     let tree = state_db.account_state_tree()?;
     let key: H256 = to_h256(key);
     let value = tree.get_value(account_id.into(), &key)?;
@@ -376,6 +475,48 @@ async fn get_storage_at(
     Ok(json_value)
 }
 
+// short_address, slot, block_number
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum GetStorageAtByShortAddressParams {
+    Tip((JsonBytes, JsonH256)),
+    Number((JsonBytes, JsonH256, Option<GwUint64>)),
+}
+
+async fn get_storage_at_by_short_address(
+    Params(params): Params<GetStorageAtByShortAddressParams>,
+    store: Data<Store>,
+) -> Result<JsonH256, RpcError> {
+    let (short_address, slot, block_number) = match params {
+        GetStorageAtByShortAddressParams::Tip(p) => (p.0, p.1, None),
+        GetStorageAtByShortAddressParams::Number(p) => p,
+    };
+
+    let db = store.begin_transaction();
+    let state_db = state_db_at_block(&db, block_number)?;
+    let tree = state_db.account_state_tree()?;
+    let value = get_contract_storage(&tree, short_address.as_bytes(), &to_h256(slot))?;
+    Ok(to_jsonh256(value))
+}
+
+/// Polyjuice saves contract storage slots as kv of the contract account,
+/// unknown contract or empty slot returns zero
+fn get_contract_storage<S: State + CodeStore>(
+    state: &S,
+    short_address: &[u8],
+    slot: &H256,
+) -> Result<H256> {
+    let script_hash = match state.get_script_hash_by_short_address(short_address) {
+        Some(script_hash) => script_hash,
+        None => return Ok(H256::zero()),
+    };
+    let account_id = match state.get_account_id_by_script_hash(&script_hash)? {
+        Some(account_id) => account_id,
+        None => return Ok(H256::zero()),
+    };
+    Ok(state.get_value(account_id, slot)?)
+}
+
 async fn get_account_id_by_script_hash(
     Params((script_hash,)): Params<(JsonH256,)>,
     store: Data<Store>,
@@ -416,22 +557,7 @@ async fn get_nonce(
     };
 
     let db = store.begin_transaction();
-    let tip_block_number = db.get_tip_block()?.raw().number().unpack();
-    let block_number = match block_number {
-        Some(num) => num.value(),
-        None => tip_block_number,
-    };
-    if block_number > tip_block_number {
-        return Err(RpcError::Provided {
-            code: HEADER_NOT_FOUND_ERR_CODE,
-            message: "header not found",
-        });
-    }
-    let state_db = StateDBTransaction::from_checkpoint(
-        &db,
-        CheckPoint::new(block_number, SubState::Block),
-        StateDBMode::ReadOnly,
-    )?;
+    let state_db = state_db_at_block(&db, block_number)?;
     let tree = state_db.account_state_tree()?;
 
     let nonce = tree.get_nonce(account_id.into())?;
@@ -509,22 +635,7 @@ async fn get_data(
     };
 
     let db = store.begin_transaction();
-    let tip_block_number = db.get_tip_block()?.raw().number().unpack();
-    let block_number = match block_number {
-        Some(num) => num.value(),
-        None => tip_block_number,
-    };
-    if block_number > tip_block_number {
-        return Err(RpcError::Provided {
-            code: HEADER_NOT_FOUND_ERR_CODE,
-            message: "header not found",
-        });
-    }
-    let state_db = StateDBTransaction::from_checkpoint(
-        &db,
-        CheckPoint::new(block_number, SubState::Block),
-        StateDBMode::ReadOnly,
-    )?;
+    let state_db = state_db_at_block(&db, block_number)?;
     let tree = state_db.account_state_tree()?;
 
     let data_opt = tree
@@ -559,3 +670,239 @@ async fn tests_should_produce_block(
 ) -> Result<ShouldProduceBlock> {
     tests_rpc_impl.should_produce_block().await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use gw_common::blake2b::new_blake2b;
+    use gw_generator::dummy_state::DummyState;
+
+    #[test]
+    fn test_get_contract_storage() {
+        let mut state = DummyState::default();
+        // Returns the contract account id and its short address, i.e. the
+        // first 20 bytes of the script hash
+        let mut create_contract = |args: [u8; 20]| {
+            let contract_script = packed::Script::new_builder()
+                .args(args.to_vec().pack())
+                .build();
+            let script_hash: H256 = contract_script.hash().into();
+            state.insert_script(script_hash, contract_script);
+            let account_id = state.create_account(script_hash).unwrap();
+            let mut short_address = [0u8; 20];
+            short_address.copy_from_slice(&script_hash.as_slice()[..20]);
+            (account_id, short_address)
+        };
+        let (id_a, short_address_a) = create_contract([1u8; 20]);
+        let (id_b, short_address_b) = create_contract([6u8; 20]);
+        assert_eq!((id_a, id_b), (0, 1));
+
+        // Polyjuice storage key: blake2b(account id le || kv flag 0 || slot)
+        let storage_key = |account_id: u32, slot: &H256| {
+            let mut hasher = new_blake2b();
+            hasher.update(&account_id.to_le_bytes());
+            hasher.update(&[0u8]);
+            hasher.update(slot.as_slice());
+            let mut key = [0u8; 32];
+            hasher.finalize(&mut key);
+            H256::from(key)
+        };
+        let slot: H256 = [2u8; 32].into();
+        let value_a: H256 = [3u8; 32].into();
+        let value_b: H256 = [7u8; 32].into();
+        state.update_raw(storage_key(0, &slot), value_a).unwrap();
+        state.update_raw(storage_key(1, &slot), value_b).unwrap();
+
+        // Same slot of different contracts
+        let stored = get_contract_storage(&state, &short_address_a, &slot).unwrap();
+        assert_eq!(stored, value_a);
+        let stored = get_contract_storage(&state, &short_address_b, &slot).unwrap();
+        assert_eq!(stored, value_b);
+
+        // Empty slot
+        let empty_slot: H256 = [4u8; 32].into();
+        let stored = get_contract_storage(&state, &short_address_a, &empty_slot).unwrap();
+        assert_eq!(stored, H256::zero());
+
+        // Unknown contract
+        let stored = get_contract_storage(&state, &[5u8; 20], &slot).unwrap();
+        assert_eq!(stored, H256::zero());
+    }
+
+    #[test]
+    fn test_get_storage_at_by_short_address() {
+        use gw_config::GenesisConfig;
+        use gw_generator::genesis::init_genesis;
+        use gw_store::state_db::WriteContext;
+        use gw_types::{
+            bytes::Bytes,
+            packed::{AccountMerkleState, L2Block, L2BlockCommittedInfo, RawL2Block},
+        };
+
+        let rollup_config = packed::RollupConfig::new_builder()
+            .finality_blocks(100u64.pack())
+            .build();
+        let genesis_config = GenesisConfig {
+            timestamp: 0,
+            meta_contract_validator_type_hash: Default::default(),
+            rollup_config: rollup_config.clone().into(),
+            rollup_type_hash: [42u8; 32].into(),
+            secp_data_dep: Default::default(),
+        };
+        let store = Store::open_tmp().unwrap();
+        let genesis_committed_info = L2BlockCommittedInfo::default();
+        init_genesis(
+            &store,
+            &genesis_config,
+            genesis_committed_info,
+            Bytes::default(),
+        )
+        .unwrap();
+
+        let contract_script = packed::Script::new_builder()
+            .args(vec![1u8; 20].pack())
+            .build();
+        let script_hash: H256 = contract_script.hash().into();
+        let short_address = JsonBytes::from_vec(script_hash.as_slice()[..20].to_vec());
+        let slot: H256 = [2u8; 32].into();
+
+        // Attach a block which sets the contract slot to `value`, the contract
+        // is created in the first one
+        let attach_block = |number: u64, value: H256| {
+            let db = store.begin_transaction();
+            let post_account = {
+                let state_db = StateDBTransaction::from_checkpoint(
+                    &db,
+                    CheckPoint::new(number, SubState::Block),
+                    StateDBMode::Write(WriteContext::new(0)),
+                )
+                .unwrap();
+                let mut tree = state_db.account_state_tree().unwrap();
+                let account_id = match tree.get_account_id_by_script_hash(&script_hash).unwrap() {
+                    Some(account_id) => account_id,
+                    None => {
+                        tree.insert_script(script_hash, contract_script.clone());
+                        tree.create_account(script_hash).unwrap()
+                    }
+                };
+                tree.update_value(account_id, &slot, value).unwrap();
+                AccountMerkleState::new_builder()
+                    .merkle_root(tree.calculate_root().unwrap().pack())
+                    .count(tree.get_account_count().unwrap().pack())
+                    .build()
+            };
+            let raw = RawL2Block::new_builder()
+                .number(number.pack())
+                .parent_block_hash(db.get_tip_block_hash().unwrap().pack())
+                .post_account(post_account)
+                .build();
+            let block = L2Block::new_builder().raw(raw).build();
+            db.insert_block(
+                block.clone(),
+                Default::default(),
+                Default::default(),
+                vec![],
+                Default::default(),
+                vec![],
+                vec![],
+            )
+            .unwrap();
+            db.attach_block(block, &rollup_config).unwrap();
+            db.commit().unwrap();
+        };
+        let value_1: H256 = [3u8; 32].into();
+        let value_2: H256 = [7u8; 32].into();
+        attach_block(1, value_1);
+        attach_block(2, value_2);
+
+        let get_storage = |block_number: Option<u64>| {
+            let params = match block_number {
+                Some(number) => GetStorageAtByShortAddressParams::Number((
+                    short_address.clone(),
+                    to_jsonh256(slot),
+                    Some(number.into()),
+                )),
+                None => GetStorageAtByShortAddressParams::Tip((
+                    short_address.clone(),
+                    to_jsonh256(slot),
+                )),
+            };
+            let storage = get_storage_at_by_short_address(Params(params), Data::new(store.clone()));
+            smol::block_on(storage).map(to_h256)
+        };
+
+        // Tip state
+        assert_eq!(get_storage(None).unwrap(), value_2);
+        assert_eq!(get_storage(Some(2)).unwrap(), value_2);
+        // Historical states
+        assert_eq!(get_storage(Some(1)).unwrap(), value_1);
+        assert_eq!(get_storage(Some(0)).unwrap(), H256::zero());
+        // Future block
+        assert!(get_storage(Some(3)).is_err());
+    }
+
+    #[test]
+    fn test_to_rollup_info() {
+        let rollup_config = packed::RollupConfig::new_builder()
+            .custodian_script_type_hash([1u8; 32].pack())
+            .finality_blocks(100u64.pack())
+            .allowed_eoa_type_hashes(vec![[2u8; 32]].pack())
+            .build();
+        let rollup_context = RollupContext {
+            rollup_script_hash: [3u8; 32].into(),
+            rollup_config: rollup_config.clone(),
+        };
+
+        let rollup_info = to_rollup_info(&rollup_context);
+        assert_eq!(rollup_info.rollup_type_hash, [3u8; 32].into());
+        assert_eq!(rollup_info.rollup_config.finality_blocks.value(), 100);
+        let json_rollup_config: packed::RollupConfig = rollup_info.rollup_config.into();
+        assert_eq!(json_rollup_config, rollup_config);
+    }
+
+    #[test]
+    fn test_gas_price() {
+        let price = smol::block_on(gas_price(Data::new(MinGasPrice(1000)))).unwrap();
+        assert_eq!(price.value(), 1000);
+        assert_eq!(
+            serde_json::to_value(price).unwrap(),
+            serde_json::json!("0x3e8")
+        );
+    }
+
+    #[test]
+    fn test_to_withdrawal_info() {
+        let raw = packed::RawWithdrawalRequest::new_builder()
+            .capacity(500_00000000u64.pack())
+            .amount(42u128.pack())
+            .sudt_script_hash([1u8; 32].pack())
+            .owner_lock_hash([2u8; 32].pack())
+            .build();
+        let withdrawal = OwnerWithdrawal {
+            block_number: 3,
+            block_hash: [4u8; 32].into(),
+            index: 5,
+            request: packed::WithdrawalRequest::new_builder().raw(raw).build(),
+            finalized: false,
+        };
+
+        let info = to_withdrawal_info(withdrawal.clone());
+        assert_eq!(info.block_number.value(), 3);
+        assert_eq!(info.block_hash, [4u8; 32].into());
+        assert_eq!(info.withdrawal_index.value(), 5);
+        assert_eq!(info.capacity.value(), 500_00000000);
+        assert_eq!(info.amount.value(), 42);
+        assert_eq!(info.sudt_script_hash, [1u8; 32].into());
+        assert_eq!(info.status, WithdrawalStatus::Pending);
+
+        let finalized = OwnerWithdrawal {
+            finalized: true,
+            ..withdrawal
+        };
+        assert_eq!(
+            to_withdrawal_info(finalized).status,
+            WithdrawalStatus::Finalized
+        );
+    }
+}
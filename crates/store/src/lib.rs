@@ -1,4 +1,7 @@
 pub mod chain_view;
+mod lock;
+pub mod migrate;
+mod read_only_store;
 pub mod smt_store_impl;
 pub mod state_db;
 mod store_impl;
@@ -6,6 +9,7 @@ pub mod traits;
 pub mod transaction;
 mod write_batch;
 
+pub use read_only_store::ReadOnlyStore;
 pub use store_impl::Store;
 
 #[cfg(test)]
@@ -0,0 +1,44 @@
+//! Process level lock of the store directory
+
+use anyhow::{anyhow, Context, Result};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+pub const LOCK_FILE_NAME: &str = "godwoken.lock";
+
+/// Exclusive lock on a store directory, released when dropped, the process
+/// exits or crashes
+pub struct StoreLock {
+    _file: File,
+}
+
+impl StoreLock {
+    pub fn acquire<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir).with_context(|| format!("create store dir {:?}", dir))?;
+
+        let lock_path = dir.join(LOCK_FILE_NAME);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&lock_path)
+            .with_context(|| format!("open store lock file {:?}", lock_path))?;
+
+        let ret = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+        if ret != 0 {
+            let mut pid = String::new();
+            file.read_to_string(&mut pid)?;
+            return Err(anyhow!("store already in use by PID {}", pid.trim()));
+        }
+
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{}", std::process::id())?;
+        file.sync_all()?;
+
+        Ok(StoreLock { _file: file })
+    }
+}
@@ -0,0 +1,121 @@
+//! Database schema version, checked on open. Older databases are migrated
+//! forward, newer ones are refused since this binary may corrupt them.
+
+use crate::traits::KVStore;
+use crate::transaction::StoreTransaction;
+use crate::Store;
+use anyhow::{anyhow, bail, Result};
+use gw_db::schema::{COLUMN_META, META_TIP_BLOCK_HASH_KEY, MIGRATION_VERSION_KEY};
+use gw_types::prelude::Unpack;
+
+/// Version of databases created before the version stamp
+pub const INIT_DB_VERSION: &str = "20211229181750";
+
+pub trait Migration {
+    /// Timestamp in `%Y%m%d%H%M%S` format, so versions compare as strings
+    fn version(&self) -> &str;
+
+    fn migrate(&self, db: &StoreTransaction) -> Result<()>;
+}
+
+/// Forward migrations, ordered by version
+pub fn migrations() -> Vec<Box<dyn Migration>> {
+    vec![Box::new(BackfillOwnerWithdrawals)]
+}
+
+/// Index withdrawals of main chain blocks attached before the owner
+/// withdrawals index. Blocks up to the last finalized block number in tip
+/// global state are finalized, same as attaching them one by one.
+pub struct BackfillOwnerWithdrawals;
+
+impl Migration for BackfillOwnerWithdrawals {
+    fn version(&self) -> &str {
+        "20220110000000"
+    }
+
+    fn migrate(&self, db: &StoreTransaction) -> Result<()> {
+        let tip_block_hash = db.get_tip_block_hash()?;
+        let tip_block = db
+            .get_block(&tip_block_hash)?
+            .ok_or_else(|| anyhow!("tip block not found"))?;
+        let last_finalized_block_number: u64 = db
+            .get_block_post_global_state(&tip_block_hash)?
+            .ok_or_else(|| anyhow!("tip block global state not found"))?
+            .last_finalized_block_number()
+            .unpack();
+
+        let tip_number: u64 = tip_block.raw().number().unpack();
+        for number in 0..=tip_number {
+            let block_hash = db
+                .get_block_hash_by_number(number)?
+                .ok_or_else(|| anyhow!("block {} hash not found", number))?;
+            let block = db
+                .get_block(&block_hash)?
+                .ok_or_else(|| anyhow!("block {} not found", number))?;
+            let finalized = number > 0 && number <= last_finalized_block_number;
+            db.insert_owner_withdrawals(&block, finalized)?;
+        }
+        log::info!("Backfill owner withdrawals of {} blocks", tip_number + 1);
+        Ok(())
+    }
+}
+
+pub fn latest_version(migrations: &[Box<dyn Migration>]) -> &str {
+    migrations
+        .last()
+        .map(|m| m.version())
+        .unwrap_or(INIT_DB_VERSION)
+}
+
+pub(crate) fn get_db_version(db: &StoreTransaction) -> Result<Option<String>> {
+    match db.get(COLUMN_META, MIGRATION_VERSION_KEY) {
+        Some(slice) => {
+            let version = String::from_utf8(slice.to_vec())
+                .map_err(|err| anyhow!("invalid db version: {}", err))?;
+            Ok(Some(version))
+        }
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn set_db_version(db: &StoreTransaction, version: &str) -> Result<()> {
+    db.insert_raw(COLUMN_META, MIGRATION_VERSION_KEY, version.as_bytes())?;
+    Ok(())
+}
+
+/// Stamp new database with the latest version, run migrations newer than
+/// the version of existing one
+pub(crate) fn migrate(store: &Store, migrations: &[Box<dyn Migration>]) -> Result<()> {
+    let latest = latest_version(migrations);
+    let db = store.begin_transaction();
+    let db_version = match get_db_version(&db)? {
+        Some(version) => version,
+        None if db.get(COLUMN_META, META_TIP_BLOCK_HASH_KEY).is_none() => {
+            set_db_version(&db, latest)?;
+            db.commit()?;
+            log::info!("Init db version: {}", latest);
+            return Ok(());
+        }
+        None => INIT_DB_VERSION.to_string(),
+    };
+
+    if db_version.as_str() > latest {
+        bail!(
+            "db version {} is newer than the supported version {}, please upgrade godwoken",
+            db_version,
+            latest
+        );
+    }
+
+    for migration in migrations
+        .iter()
+        .filter(|m| m.version() > db_version.as_str())
+    {
+        log::info!("Migrate db to version {}", migration.version());
+        migration.migrate(&db)?;
+        set_db_version(&db, migration.version())?;
+    }
+    set_db_version(&db, latest)?;
+    db.commit()?;
+    Ok(())
+}
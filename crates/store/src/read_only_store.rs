@@ -0,0 +1,102 @@
+//! Read-only storage, opened alongside the node which owns the database
+
+use crate::Store;
+use anyhow::Result;
+use gw_common::{error::Error, smt::H256};
+use gw_db::{
+    schema::{
+        Col, COLUMNS, COLUMN_BLOCK, COLUMN_INDEX, COLUMN_META, META_CHAIN_ID_KEY,
+        META_TIP_BLOCK_HASH_KEY,
+    },
+    DBPinnableSlice, ReadOnlyDB,
+};
+use gw_types::{
+    packed::{self, L2Block},
+    prelude::*,
+};
+use std::path::{Path, PathBuf};
+
+/// Read-only view of a store written by another process. It has no write
+/// methods and doesn't take the database lock, so the node can keep writing.
+pub struct ReadOnlyStore {
+    db: ReadOnlyDB,
+    path: PathBuf,
+}
+
+impl Store {
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<ReadOnlyStore> {
+        ReadOnlyStore::open(path)
+    }
+}
+
+impl<'a> ReadOnlyStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let db = ReadOnlyDB::open_cf(&path, COLUMNS)?;
+        Ok(ReadOnlyStore { db, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read-only instance is frozen at open time, reopen it to see the writes
+    /// of primary since then. Call it before reading the latest tip.
+    pub fn try_catch_up_with_primary(&mut self) -> Result<()> {
+        self.db = ReadOnlyDB::open_cf(&self.path, COLUMNS)?;
+        Ok(())
+    }
+
+    fn get(&'a self, col: Col, key: &[u8]) -> Option<DBPinnableSlice<'a>> {
+        self.db
+            .get_pinned(col, key)
+            .expect("db operation should be ok")
+    }
+
+    pub fn get_chain_id(&self) -> Result<H256, Error> {
+        let slice = self
+            .get(COLUMN_META, META_CHAIN_ID_KEY)
+            .expect("must has chain_id");
+        debug_assert_eq!(slice.len(), 32);
+        let mut chain_id = [0u8; 32];
+        chain_id.copy_from_slice(&slice);
+        Ok(chain_id.into())
+    }
+
+    pub fn get_tip_block_hash(&self) -> Result<H256, Error> {
+        let slice = self
+            .get(COLUMN_META, META_TIP_BLOCK_HASH_KEY)
+            .expect("get tip block hash");
+        Ok(
+            packed::Byte32Reader::from_slice_should_be_ok(&slice.as_ref())
+                .to_entity()
+                .unpack(),
+        )
+    }
+
+    pub fn get_tip_block(&self) -> Result<L2Block, Error> {
+        let tip_block_hash = self.get_tip_block_hash()?;
+        Ok(self.get_block(&tip_block_hash)?.expect("get tip block"))
+    }
+
+    pub fn get_block(&self, block_hash: &H256) -> Result<Option<L2Block>, Error> {
+        match self.get(COLUMN_BLOCK, block_hash.as_slice()) {
+            Some(slice) => Ok(Some(
+                packed::L2BlockReader::from_slice_should_be_ok(&slice.as_ref()).to_entity(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_block_hash_by_number(&self, number: u64) -> Result<Option<H256>, Error> {
+        let block_number: packed::Uint64 = number.pack();
+        match self.get(COLUMN_INDEX, block_number.as_slice()) {
+            Some(slice) => Ok(Some(
+                packed::Byte32Reader::from_slice_should_be_ok(&slice.as_ref())
+                    .to_entity()
+                    .unpack(),
+            )),
+            None => Ok(None),
+        }
+    }
+}
@@ -3,7 +3,12 @@
 use crate::{smt_store_impl::SMTStore, traits::KVStore, transaction::StoreTransaction};
 use anyhow::{anyhow, Result};
 use gw_common::merkle_utils::calculate_state_checkpoint;
-use gw_common::{error::Error as StateError, smt::SMT, state::State, H256};
+use gw_common::{
+    error::Error as StateError,
+    smt::SMT,
+    state::{build_account_field_key, State, GW_ACCOUNT_SCRIPT_HASH_TYPE},
+    H256,
+};
 use gw_db::schema::{
     Col, COLUMN_ACCOUNT_SMT_BRANCH, COLUMN_ACCOUNT_SMT_LEAF, COLUMN_DATA, COLUMN_SCRIPT,
     COLUMN_SCRIPT_PREFIX,
@@ -143,6 +148,32 @@ impl CheckPoint {
         })
     }
 
+    pub fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    pub fn sub_state(&self) -> &SubState {
+        &self.sub_state
+    }
+
+    /// Check that checkpoint built by block number refers to the state of
+    /// `block_hash`, i.e. the block is on the main chain
+    pub fn validate_block_hash(&self, db: &StoreTransaction, block_hash: &H256) -> Result<()> {
+        let block = db
+            .get_block(block_hash)?
+            .ok_or_else(|| anyhow!("block isn't exist"))?;
+        self.sub_state.validate_in_block(&block)?;
+
+        let main_chain_block_hash = db
+            .get_block_hash_by_number(self.block_number)?
+            .ok_or_else(|| anyhow!("block number isn't exist"))?;
+        if &main_chain_block_hash != block_hash {
+            return Err(anyhow!("checkpoint block hash mismatch"));
+        }
+
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn do_extract_block_number_and_index_number(
         &self,
@@ -221,6 +252,7 @@ pub struct StateDBTransaction<'db> {
     inner: &'db StoreTransaction,
     checkpoint: CheckPoint,
     mode: StateDBMode,
+    discardable_writes: bool,
 }
 
 impl<'db> KVStore for StateDBTransaction<'db> {
@@ -237,6 +269,7 @@ impl<'db> KVStore for StateDBTransaction<'db> {
     }
 
     fn insert_raw(&self, col: Col, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.check_writable()?;
         assert_ne!(
             value,
             &FLAG_DELETE_VALUE.to_be_bytes(),
@@ -249,6 +282,7 @@ impl<'db> KVStore for StateDBTransaction<'db> {
     }
 
     fn delete(&self, col: Col, key: &[u8]) -> Result<(), Error> {
+        self.check_writable()?;
         let raw_key = self.get_key_with_suffix(key);
         self.inner
             .insert_raw(col, &raw_key, &FLAG_DELETE_VALUE.to_be_bytes())
@@ -275,13 +309,33 @@ impl<'db> StateDBTransaction<'db> {
             inner,
             checkpoint,
             mode,
+            discardable_writes: false,
         })
     }
 
+    /// Allow writes on ReadOnly mode to execute on history state,
+    /// caller must discard these writes by rolling back the StoreTransaction
+    pub fn with_discardable_writes(mut self) -> Self {
+        self.discardable_writes = true;
+        self
+    }
+
     pub fn mode(&self) -> StateDBMode {
         self.mode
     }
 
+    pub fn is_writable(&self) -> bool {
+        self.mode != StateDBMode::ReadOnly || self.discardable_writes
+    }
+
+    fn check_writable(&self) -> Result<(), Error> {
+        if self.is_writable() {
+            Ok(())
+        } else {
+            Err(Error::from("write on ReadOnly mode".to_string()))
+        }
+    }
+
     pub fn commit(&self) -> Result<(), Error> {
         if self.mode == StateDBMode::ReadOnly {
             Err(Error::from("commit on ReadOnly mode".to_string()))
@@ -536,6 +590,9 @@ impl<'a, 'db> State for StateTree<'a, 'db> {
     }
 
     fn update_raw(&mut self, key: H256, value: H256) -> Result<(), StateError> {
+        if !self.db.is_writable() {
+            return Err(StateError::Store);
+        }
         self.tracker.touch_key(&key);
         self.tree.update(key, value)?;
         Ok(())
@@ -545,7 +602,29 @@ impl<'a, 'db> State for StateTree<'a, 'db> {
         Ok(self.account_count)
     }
 
+    /// Account count covers created accounts only, i.e. accounts whose script
+    /// hash is set. It increases by accounts already created, and decreases
+    /// by accounts already unlinked, e.g. reverting a run result.
     fn set_account_count(&mut self, count: u32) -> Result<(), StateError> {
+        if !self.db.is_writable() {
+            return Err(StateError::Store);
+        }
+        let invalid_count = StateError::InvalidAccountCount {
+            current: self.account_count,
+            count,
+        };
+        let (ids, increase) = if count >= self.account_count {
+            (self.account_count..count, true)
+        } else {
+            (count..self.account_count, false)
+        };
+        for id in ids {
+            let key = build_account_field_key(id, GW_ACCOUNT_SCRIPT_HASH_TYPE);
+            let created = !self.tree.get(&key)?.is_zero();
+            if created != increase {
+                return Err(invalid_count);
+            }
+        }
         self.account_count = count;
         Ok(())
     }
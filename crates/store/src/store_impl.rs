@@ -1,14 +1,17 @@
 //! Storage implementation
 
-use crate::transaction::StoreTransaction;
+use crate::lock::StoreLock;
+use crate::migrate;
+use crate::transaction::{OwnerWithdrawal, StoreTransaction};
 use crate::write_batch::StoreWriteBatch;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use gw_common::{error::Error, smt::H256};
 use gw_db::{
+    config::Config as DBConfig,
     schema::{
-        Col, COLUMNS, COLUMN_BLOCK, COLUMN_BLOCK_GLOBAL_STATE, COLUMN_L2BLOCK_COMMITTED_INFO,
-        COLUMN_META, COLUMN_TRANSACTION, COLUMN_TRANSACTION_RECEIPT, META_CHAIN_ID_KEY,
-        META_TIP_BLOCK_HASH_KEY,
+        Col, COLUMNS, COLUMN_BLOCK, COLUMN_BLOCK_GLOBAL_STATE, COLUMN_INDEX,
+        COLUMN_L2BLOCK_COMMITTED_INFO, COLUMN_META, COLUMN_TRANSACTION, COLUMN_TRANSACTION_RECEIPT,
+        META_CHAIN_ID_KEY, META_TIP_BLOCK_HASH_KEY,
     },
     DBPinnableSlice, RocksDB,
 };
@@ -16,15 +19,34 @@ use gw_types::{
     packed::{self, GlobalState, L2Block, L2Transaction},
     prelude::*,
 };
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Store {
     db: RocksDB,
+    _lock: Option<Arc<StoreLock>>,
 }
 
 impl<'a> Store {
     pub fn new(db: RocksDB) -> Self {
-        Store { db }
+        Store { db, _lock: None }
+    }
+
+    /// Open store at `config.path`, fail if the directory is already used
+    /// by another opener. Use `Store::open_read_only` to read a store in use.
+    ///
+    /// Older databases are migrated to the latest version, newer ones are
+    /// refused.
+    pub fn open(config: &DBConfig, columns: u32) -> Result<Self> {
+        let lock = StoreLock::acquire(&config.path)?;
+        let db = RocksDB::open(config, columns);
+        let store = Store {
+            db,
+            _lock: Some(Arc::new(lock)),
+        };
+        migrate::migrate(&store, &migrate::migrations())?;
+        Ok(store)
     }
 
     pub fn open_tmp() -> Result<Self> {
@@ -114,6 +136,56 @@ impl<'a> Store {
         }
     }
 
+    /// Get blocks in number range, blocks are keyed by hash so each one is
+    /// located through the number index. A missing block is reported as error.
+    pub fn get_blocks(&self, range: RangeInclusive<u64>) -> Result<Vec<L2Block>> {
+        let mut blocks = Vec::new();
+        for number in range {
+            let block_number: packed::Uint64 = number.pack();
+            let block_hash = self
+                .get(COLUMN_INDEX, block_number.as_slice())
+                .ok_or_else(|| anyhow!("missing block hash of #{}", number))?;
+            let block = self
+                .get(COLUMN_BLOCK, &block_hash)
+                .ok_or_else(|| anyhow!("missing block #{}", number))?;
+            blocks
+                .push(packed::L2BlockReader::from_slice_should_be_ok(&block.as_ref()).to_entity());
+        }
+        Ok(blocks)
+    }
+
+    /// Unfinalized withdrawals of owner lock hash, indexed while attaching
+    /// blocks
+    pub fn get_pending_withdrawals(&self, owner_lock_hash: &H256) -> Result<Vec<OwnerWithdrawal>> {
+        let db = self.begin_transaction();
+        Ok(db.get_pending_withdrawals(owner_lock_hash)?)
+    }
+
+    /// Withdrawals of owner lock hash along with their finalization status
+    pub fn get_owner_withdrawals(&self, owner_lock_hash: &H256) -> Result<Vec<OwnerWithdrawal>> {
+        let db = self.begin_transaction();
+        Ok(db.get_owner_withdrawals(owner_lock_hash)?)
+    }
+
+    /// Get `(block number, block producer id)` of blocks in number range, a
+    /// missing block is reported as error.
+    pub fn get_block_producers(&self, range: RangeInclusive<u64>) -> Result<Vec<(u64, u32)>> {
+        let mut producers = Vec::new();
+        for number in range {
+            let block_number: packed::Uint64 = number.pack();
+            let block_hash = self
+                .get(COLUMN_INDEX, block_number.as_slice())
+                .ok_or_else(|| anyhow!("missing block hash of #{}", number))?;
+            let block = self
+                .get(COLUMN_BLOCK, &block_hash)
+                .ok_or_else(|| anyhow!("missing block #{}", number))?;
+            let block = packed::L2BlockReader::from_slice_should_be_ok(&block.as_ref());
+            let block_producer_id: u32 = block.raw().block_producer_id().unpack();
+            producers.push((number, block_producer_id));
+        }
+        Ok(producers)
+    }
+
     pub fn get_l2block_committed_info(
         &self,
         block_hash: &H256,
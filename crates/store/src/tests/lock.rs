@@ -0,0 +1,33 @@
+use crate::Store;
+use gw_db::{config::Config as DBConfig, schema::COLUMNS};
+
+#[test]
+fn test_store_lock() {
+    let tmp_dir = tempfile::Builder::new()
+        .prefix("test_store_lock")
+        .tempdir()
+        .unwrap();
+    let config = DBConfig {
+        path: tmp_dir.as_ref().to_path_buf(),
+        ..Default::default()
+    };
+
+    let store = Store::open(&config, COLUMNS).unwrap();
+    let err = Store::open(&config, COLUMNS)
+        .err()
+        .expect("store is locked");
+    assert_eq!(
+        err.to_string(),
+        format!("store already in use by PID {}", std::process::id())
+    );
+
+    // Read-only opener doesn't require the lock
+    assert!(Store::open_read_only(&config.path).is_ok());
+
+    // Lock is released after all clones are dropped
+    let store_clone = store.clone();
+    drop(store);
+    assert!(Store::open(&config, COLUMNS).is_err());
+    drop(store_clone);
+    assert!(Store::open(&config, COLUMNS).is_ok());
+}
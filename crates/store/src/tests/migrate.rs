@@ -0,0 +1,168 @@
+use crate::migrate::{
+    get_db_version, latest_version, migrate, migrations, set_db_version, Migration, INIT_DB_VERSION,
+};
+use crate::traits::KVStore;
+use crate::transaction::StoreTransaction;
+use crate::Store;
+use anyhow::Result;
+use gw_common::H256;
+use gw_db::{
+    config::Config as DBConfig,
+    schema::{COLUMNS, COLUMN_INDEX, COLUMN_META, META_TIP_BLOCK_HASH_KEY},
+};
+use gw_types::{
+    packed::{
+        GlobalState, L2Block, RawL2Block, RawWithdrawalRequest, WithdrawalRequest,
+        WithdrawalRequestVec,
+    },
+    prelude::*,
+};
+
+struct MarkerMigration(&'static str);
+
+impl Migration for MarkerMigration {
+    fn version(&self) -> &str {
+        self.0
+    }
+
+    fn migrate(&self, db: &StoreTransaction) -> Result<()> {
+        db.insert_raw(COLUMN_META, self.0.as_bytes(), &[1])?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_open_too_new_db_version() {
+    let tmp_dir = tempfile::Builder::new()
+        .prefix("test_open_too_new_db_version")
+        .tempdir()
+        .unwrap();
+    let config = DBConfig {
+        path: tmp_dir.as_ref().to_path_buf(),
+        ..Default::default()
+    };
+
+    {
+        let store = Store::open(&config, COLUMNS).unwrap();
+        let db = store.begin_transaction();
+        assert_eq!(
+            get_db_version(&db).unwrap().as_deref(),
+            Some(latest_version(&migrations()))
+        );
+        set_db_version(&db, "99991231235959").unwrap();
+        db.commit().unwrap();
+    }
+
+    let err = Store::open(&config, COLUMNS)
+        .err()
+        .expect("too new db version");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "db version 99991231235959 is newer than the supported version {}, please upgrade godwoken",
+            latest_version(&migrations())
+        )
+    );
+}
+
+#[test]
+fn test_migrate_old_db_version() {
+    let store = Store::open_tmp().unwrap();
+    let migrations: Vec<Box<dyn Migration>> = vec![
+        Box::new(MarkerMigration("20220101000000")),
+        Box::new(MarkerMigration("20220201000000")),
+    ];
+
+    // Databases created before the version stamp have a tip block
+    let db = store.begin_transaction();
+    db.insert_raw(COLUMN_META, META_TIP_BLOCK_HASH_KEY, &[0u8; 32])
+        .unwrap();
+    db.commit().unwrap();
+    let db = store.begin_transaction();
+    set_db_version(&db, "20220101000000").unwrap();
+    db.commit().unwrap();
+
+    migrate(&store, &migrations).unwrap();
+    let db = store.begin_transaction();
+    assert_eq!(
+        get_db_version(&db).unwrap().as_deref(),
+        Some("20220201000000")
+    );
+    assert!(db.get(COLUMN_META, b"20220101000000").is_none());
+    assert!(db.get(COLUMN_META, b"20220201000000").is_some());
+
+    // Unstamped database runs all migrations
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    db.insert_raw(COLUMN_META, META_TIP_BLOCK_HASH_KEY, &[0u8; 32])
+        .unwrap();
+    db.commit().unwrap();
+
+    migrate(&store, &migrations).unwrap();
+    let db = store.begin_transaction();
+    assert_eq!(
+        get_db_version(&db).unwrap().as_deref(),
+        Some("20220201000000")
+    );
+    assert!(db.get(COLUMN_META, b"20220101000000").is_some());
+}
+
+#[test]
+fn test_backfill_owner_withdrawals() {
+    let store = Store::open_tmp().unwrap();
+    let owner_lock_hash = H256::from([7u8; 32]);
+    let withdrawal = {
+        let raw = RawWithdrawalRequest::new_builder()
+            .owner_lock_hash(owner_lock_hash.pack())
+            .build();
+        WithdrawalRequest::new_builder().raw(raw).build()
+    };
+
+    // Blocks attached before owner withdrawals index, block 1 is finalized
+    let db = store.begin_transaction();
+    for number in 0..3u64 {
+        let withdrawals = match number {
+            0 => WithdrawalRequestVec::default(),
+            _ => vec![withdrawal.clone()].pack(),
+        };
+        let block = L2Block::new_builder()
+            .raw(RawL2Block::new_builder().number(number.pack()).build())
+            .withdrawals(withdrawals)
+            .build();
+        let global_state = GlobalState::new_builder()
+            .last_finalized_block_number(number.saturating_sub(1).pack())
+            .build();
+        db.insert_block(
+            block.clone(),
+            Default::default(),
+            global_state,
+            vec![],
+            Default::default(),
+            vec![],
+            vec![],
+        )
+        .unwrap();
+        db.insert_raw(COLUMN_INDEX, number.pack().as_slice(), &block.hash())
+            .unwrap();
+        db.set_tip_block_hash(block.hash().into()).unwrap();
+    }
+    set_db_version(&db, INIT_DB_VERSION).unwrap();
+    db.commit().unwrap();
+    assert!(store
+        .get_owner_withdrawals(&owner_lock_hash)
+        .unwrap()
+        .is_empty());
+
+    migrate(&store, &migrations()).unwrap();
+    let withdrawals = store.get_owner_withdrawals(&owner_lock_hash).unwrap();
+    let numbers_and_finalized: Vec<(u64, bool)> = withdrawals
+        .iter()
+        .map(|w| (w.block_number, w.finalized))
+        .collect();
+    assert_eq!(numbers_and_finalized, vec![(1, true), (2, false)]);
+    assert_eq!(withdrawals[0].request.as_slice(), withdrawal.as_slice());
+
+    let pending = store.get_pending_withdrawals(&owner_lock_hash).unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].block_number, 2);
+}
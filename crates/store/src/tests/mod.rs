@@ -1,3 +1,7 @@
+mod lock;
+mod migrate;
+mod read_only_store;
 mod state_db;
+mod store;
 mod transaction;
 mod transaction_clear_block_state;
@@ -0,0 +1,100 @@
+use crate::{traits::KVStore, Store};
+use gw_db::{
+    config::Config as DBConfig,
+    schema::{COLUMNS, COLUMN_BLOCK, COLUMN_INDEX, COLUMN_META, META_TIP_BLOCK_HASH_KEY},
+    RocksDB,
+};
+use gw_types::{
+    packed::{L2Block, RawL2Block},
+    prelude::*,
+};
+
+fn insert_tip_block(store: &Store, number: u64) -> L2Block {
+    let raw = RawL2Block::new_builder().number(number.pack()).build();
+    let block = L2Block::new_builder().raw(raw).build();
+    let block_number = number.pack();
+
+    let store_txn = store.begin_transaction();
+    store_txn
+        .insert_raw(COLUMN_BLOCK, &block.hash(), block.as_slice())
+        .unwrap();
+    store_txn
+        .insert_raw(COLUMN_INDEX, block_number.as_slice(), &block.hash())
+        .unwrap();
+    store_txn
+        .insert_raw(COLUMN_META, META_TIP_BLOCK_HASH_KEY, &block.hash())
+        .unwrap();
+    store_txn.commit().unwrap();
+    block
+}
+
+#[test]
+fn open_read_only() {
+    let tmp_dir = tempfile::Builder::new()
+        .prefix("test_open_read_only")
+        .tempdir()
+        .unwrap();
+    let config = DBConfig {
+        path: tmp_dir.as_ref().to_path_buf(),
+        ..Default::default()
+    };
+
+    // Missing database isn't created in read-only mode
+    assert!(Store::open_read_only(tmp_dir.path().join("missing")).is_err());
+    assert!(!tmp_dir.path().join("missing").exists());
+
+    let store = Store::new(RocksDB::open(&config, COLUMNS));
+    let block = insert_tip_block(&store, 0);
+
+    // Opened while the primary store is still alive
+    let read_only_store = Store::open_read_only(&config.path).unwrap();
+    assert_eq!(
+        read_only_store.get_tip_block_hash().unwrap(),
+        block.hash().into()
+    );
+    assert_eq!(
+        read_only_store.get_tip_block().unwrap().hash(),
+        block.hash()
+    );
+    assert_eq!(
+        read_only_store.get_block_hash_by_number(0).unwrap(),
+        Some(block.hash().into())
+    );
+    assert_eq!(read_only_store.get_block_hash_by_number(1).unwrap(), None);
+
+    // Primary keeps writing, read-only store has no lock on the database
+    insert_tip_block(&store, 1);
+}
+
+#[test]
+fn try_catch_up_with_primary() {
+    let tmp_dir = tempfile::Builder::new()
+        .prefix("test_try_catch_up_with_primary")
+        .tempdir()
+        .unwrap();
+    let config = DBConfig {
+        path: tmp_dir.as_ref().to_path_buf(),
+        ..Default::default()
+    };
+
+    let store = Store::new(RocksDB::open(&config, COLUMNS));
+    let block0 = insert_tip_block(&store, 0);
+    let mut read_only_store = Store::open_read_only(&config.path).unwrap();
+
+    let block1 = insert_tip_block(&store, 1);
+    assert_eq!(
+        read_only_store.get_tip_block_hash().unwrap(),
+        block0.hash().into()
+    );
+    assert_eq!(read_only_store.get_block_hash_by_number(1).unwrap(), None);
+
+    read_only_store.try_catch_up_with_primary().unwrap();
+    assert_eq!(
+        read_only_store.get_tip_block_hash().unwrap(),
+        block1.hash().into()
+    );
+    assert_eq!(
+        read_only_store.get_block_hash_by_number(1).unwrap(),
+        Some(block1.hash().into())
+    );
+}
@@ -4,7 +4,12 @@ use crate::{
     transaction::StoreTransaction,
     Store,
 };
-use gw_common::{merkle_utils::calculate_state_checkpoint, H256};
+use gw_common::{
+    error::Error as StateError,
+    merkle_utils::calculate_state_checkpoint,
+    state::{build_account_field_key, State, GW_ACCOUNT_SCRIPT_HASH_TYPE},
+    H256,
+};
 use gw_db::schema::COLUMN_INDEX;
 use gw_types::{
     packed::{
@@ -212,6 +217,59 @@ fn construct_state_db_from_sub_state() {
     );
 }
 
+#[test]
+fn checkpoint_from_block_hash_and_number_agree() {
+    let store = Store::open_tmp().unwrap();
+    let store_txn = store.begin_transaction();
+
+    let post_account = AccountMerkleState::new_builder()
+        .merkle_root([1u8; 32].pack())
+        .count(3u32.pack())
+        .build();
+    let raw_block = RawL2Block::new_builder()
+        .number(0u64.pack())
+        .post_account(post_account.clone())
+        .build();
+    let block = L2Block::new_builder().raw(raw_block).build();
+    let block_hash: H256 = block.raw().hash().into();
+    store_txn
+        .insert_block(
+            block,
+            L2BlockCommittedInfo::default(),
+            GlobalState::default(),
+            Vec::new(),
+            AccountMerkleState::default(),
+            Vec::new(),
+            Vec::new(),
+        )
+        .unwrap();
+    store_txn
+        .insert_raw(COLUMN_INDEX, 0u64.pack().as_slice(), block_hash.as_slice())
+        .unwrap();
+    store_txn.commit().unwrap();
+
+    let db = store.begin_transaction();
+    let by_hash = CheckPoint::from_block_hash(&db, block_hash, SubState::Block).unwrap();
+    let by_number = CheckPoint::new(0, SubState::Block);
+    assert_eq!(by_hash, by_number);
+    by_number.validate_block_hash(&db, &block_hash).unwrap();
+
+    let expected_root: [u8; 32] = post_account.merkle_root().unpack();
+    for checkpoint in vec![by_hash, by_number] {
+        let state_db =
+            StateDBTransaction::from_checkpoint(&db, checkpoint, StateDBMode::ReadOnly).unwrap();
+        let tree = state_db.account_state_tree().unwrap();
+        assert_eq!(tree.calculate_root().unwrap(), expected_root.into());
+        assert_eq!(tree.get_account_count().unwrap(), 3);
+    }
+
+    // not the main chain block at this number
+    let err = CheckPoint::new(1, SubState::Block)
+        .validate_block_hash(&db, &block_hash)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "block number isn't exist");
+}
+
 #[test]
 fn commit_on_readonly_mode() {
     let store = Store::open_tmp().unwrap();
@@ -697,3 +755,124 @@ fn insert_special_value_0u8() {
         .insert_raw(1, &[2], &0u8.to_be_bytes())
         .unwrap();
 }
+
+#[test]
+fn forbid_writes_on_read_only_state_db() {
+    let store = Store::open_tmp().unwrap();
+    let store_txn = store.begin_transaction();
+    let block = L2Block::default();
+    store_txn
+        .insert_block(
+            block.clone(),
+            L2BlockCommittedInfo::default(),
+            GlobalState::default(),
+            Vec::new(),
+            AccountMerkleState::default(),
+            Vec::new(),
+            Vec::new(),
+        )
+        .unwrap();
+    store_txn.commit().unwrap();
+
+    let db = store.begin_transaction();
+    let checkpoint =
+        CheckPoint::from_block_hash(&db, block.hash().into(), SubState::Block).unwrap();
+    let state_db =
+        StateDBTransaction::from_checkpoint(&db, checkpoint.clone(), StateDBMode::ReadOnly)
+            .unwrap();
+    assert!(!state_db.is_writable());
+    assert!(state_db.insert_raw(COLUMN_INDEX, b"k", b"v").is_err());
+
+    let mut tree = state_db.account_state_tree().unwrap();
+    assert!(tree.update_raw(H256::zero(), H256::zero()).is_err());
+    assert!(tree.set_account_count(1).is_err());
+
+    let state_db = StateDBTransaction::from_checkpoint(&db, checkpoint, StateDBMode::ReadOnly)
+        .unwrap()
+        .with_discardable_writes();
+    let mut tree = state_db.account_state_tree().unwrap();
+    // Account count only covers created accounts
+    tree.update_raw(
+        build_account_field_key(0, GW_ACCOUNT_SCRIPT_HASH_TYPE),
+        [1u8; 32].into(),
+    )
+    .unwrap();
+    assert!(tree.set_account_count(1).is_ok());
+    db.rollback().unwrap();
+}
+
+#[test]
+fn get_raw_many() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db =
+        StateDBTransaction::from_checkpoint(&db, CheckPoint::from_genesis(), StateDBMode::Genesis)
+            .unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+
+    let keys: Vec<H256> = (1u8..=4).map(|i| [i; 32].into()).collect();
+    for (i, key) in keys.iter().take(3).enumerate() {
+        tree.update_raw(*key, [i as u8 + 10; 32].into()).unwrap();
+    }
+
+    tree.tracker_mut().enable();
+    let values = tree.get_raw_many(&keys).unwrap();
+    let expected: Vec<H256> = keys.iter().map(|k| tree.get_raw(k).unwrap()).collect();
+    assert_eq!(values, expected);
+    assert_eq!(values[3], H256::zero());
+
+    let touched_keys = tree.tracker_mut().touched_keys().unwrap().borrow().clone();
+    assert_eq!(touched_keys.len(), keys.len());
+}
+
+#[test]
+fn reject_invalid_account_count() {
+    let store = Store::open_tmp().unwrap();
+    let db = store.begin_transaction();
+    let state_db =
+        StateDBTransaction::from_checkpoint(&db, CheckPoint::from_genesis(), StateDBMode::Genesis)
+            .unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    tree.create_account([1u8; 32].into()).unwrap();
+    tree.create_account([2u8; 32].into()).unwrap();
+    assert_eq!(tree.get_account_count().unwrap(), 2);
+
+    // decrease
+    assert_eq!(
+        tree.set_account_count(1),
+        Err(StateError::InvalidAccountCount {
+            current: 2,
+            count: 1
+        })
+    );
+    // increase without created accounts
+    assert_eq!(
+        tree.set_account_count(5),
+        Err(StateError::InvalidAccountCount {
+            current: 2,
+            count: 5
+        })
+    );
+    assert_eq!(tree.get_account_count().unwrap(), 2);
+
+    // increase by an account created in the operation, e.g. a run result
+    tree.update_raw(
+        build_account_field_key(2, GW_ACCOUNT_SCRIPT_HASH_TYPE),
+        [3u8; 32].into(),
+    )
+    .unwrap();
+    assert!(tree.set_account_count(4).is_err());
+    assert!(tree.set_account_count(3).is_ok());
+    assert!(tree.set_account_count(3).is_ok());
+    assert_eq!(tree.get_account_count().unwrap(), 3);
+
+    // decrease by an unlinked account, e.g. a reverted run result
+    tree.update_raw(
+        build_account_field_key(2, GW_ACCOUNT_SCRIPT_HASH_TYPE),
+        H256::zero(),
+    )
+    .unwrap();
+    assert!(tree.set_account_count(1).is_err());
+    assert!(tree.set_account_count(2).is_ok());
+    assert_eq!(tree.get_account_count().unwrap(), 2);
+}
@@ -0,0 +1,69 @@
+use crate::{traits::KVStore, Store};
+use gw_db::schema::{COLUMN_BLOCK, COLUMN_INDEX};
+use gw_types::{
+    packed::{L2Block, RawL2Block},
+    prelude::*,
+};
+
+fn insert_block(store: &Store, number: u64) -> L2Block {
+    insert_block_by_producer(store, number, 0)
+}
+
+fn insert_block_by_producer(store: &Store, number: u64, block_producer_id: u32) -> L2Block {
+    let raw = RawL2Block::new_builder()
+        .number(number.pack())
+        .block_producer_id(block_producer_id.pack())
+        .build();
+    let block = L2Block::new_builder().raw(raw).build();
+    let block_number = number.pack();
+
+    let store_txn = store.begin_transaction();
+    store_txn
+        .insert_raw(COLUMN_BLOCK, &block.hash(), block.as_slice())
+        .unwrap();
+    store_txn
+        .insert_raw(COLUMN_INDEX, block_number.as_slice(), &block.hash())
+        .unwrap();
+    store_txn.commit().unwrap();
+    block
+}
+
+#[test]
+fn get_blocks() {
+    let store = Store::open_tmp().unwrap();
+    let blocks: Vec<L2Block> = (0..5).map(|number| insert_block(&store, number)).collect();
+
+    let hashes = |blocks: &[L2Block]| blocks.iter().map(|b| b.hash()).collect::<Vec<_>>();
+
+    let got = store.get_blocks(1..=3).unwrap();
+    assert_eq!(hashes(&got), hashes(&blocks[1..=3]));
+
+    let got = store.get_blocks(4..=4).unwrap();
+    assert_eq!(hashes(&got), hashes(&blocks[4..=4]));
+}
+
+#[test]
+fn get_blocks_with_gap() {
+    let store = Store::open_tmp().unwrap();
+    for number in [0u64, 1, 3].iter() {
+        insert_block(&store, *number);
+    }
+
+    let err = store.get_blocks(0..=3).unwrap_err();
+    assert!(err.to_string().contains("#2"), "{}", err);
+}
+
+#[test]
+fn get_block_producers() {
+    let store = Store::open_tmp().unwrap();
+    let producer_ids = [0u32, 1, 1, 2, 0];
+    for (number, id) in producer_ids.iter().enumerate() {
+        insert_block_by_producer(&store, number as u64, *id);
+    }
+
+    let producers = store.get_block_producers(1..=4).unwrap();
+    assert_eq!(producers, vec![(1, 1), (2, 1), (3, 2), (4, 0)]);
+
+    let err = store.get_block_producers(3..=5).unwrap_err();
+    assert!(err.to_string().contains("#5"), "{}", err);
+}
@@ -4,10 +4,10 @@ use gw_db::schema::{
     Col, COLUMN_BLOCK, COLUMN_BLOCK_DEPOSIT_REQUESTS, COLUMN_BLOCK_GLOBAL_STATE,
     COLUMN_BLOCK_SMT_BRANCH, COLUMN_BLOCK_SMT_LEAF, COLUMN_BLOCK_STATE_RECORD, COLUMN_CHECKPOINT,
     COLUMN_CUSTODIAN_ASSETS, COLUMN_INDEX, COLUMN_L2BLOCK_COMMITTED_INFO, COLUMN_META,
-    COLUMN_REVERTED_BLOCK_SMT_BRANCH, COLUMN_REVERTED_BLOCK_SMT_LEAF, COLUMN_TRANSACTION,
-    COLUMN_TRANSACTION_INFO, COLUMN_TRANSACTION_RECEIPT, META_ACCOUNT_SMT_COUNT_KEY,
-    META_ACCOUNT_SMT_ROOT_KEY, META_BLOCK_SMT_ROOT_KEY, META_CHAIN_ID_KEY,
-    META_REVERTED_BLOCK_SMT_ROOT_KEY, META_TIP_BLOCK_HASH_KEY,
+    COLUMN_OWNER_WITHDRAWALS, COLUMN_REVERTED_BLOCK_SMT_BRANCH, COLUMN_REVERTED_BLOCK_SMT_LEAF,
+    COLUMN_TRANSACTION, COLUMN_TRANSACTION_INFO, COLUMN_TRANSACTION_RECEIPT,
+    META_ACCOUNT_SMT_COUNT_KEY, META_ACCOUNT_SMT_ROOT_KEY, META_BLOCK_SMT_ROOT_KEY,
+    META_CHAIN_ID_KEY, META_REVERTED_BLOCK_SMT_ROOT_KEY, META_TIP_BLOCK_HASH_KEY,
 };
 use gw_db::{
     error::Error, iter::DBIter, DBIterator, Direction::Forward, IteratorMode, RocksDBTransaction,
@@ -21,6 +21,17 @@ use std::{borrow::BorrowMut, collections::HashMap};
 
 const NUMBER_OF_CONFIRMATION: u64 = 100;
 
+/// Withdrawal of an attached block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnerWithdrawal {
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub index: u32,
+    pub request: packed::WithdrawalRequest,
+    /// Block of withdrawal is past finality blocks
+    pub finalized: bool,
+}
+
 pub struct StoreTransaction {
     pub(crate) inner: RocksDBTransaction,
 }
@@ -513,6 +524,9 @@ impl StoreTransaction {
             self.insert_raw(COLUMN_TRANSACTION_INFO, &tx_hash, info.as_slice())?;
         }
 
+        // index withdrawals by owner
+        self.insert_owner_withdrawals(&block, false)?;
+
         // update finalized custodian assets
         let finality_blocks = rollup_config.finality_blocks().unpack();
         let last_finalized_block_number = raw_number.unpack().saturating_sub(finality_blocks);
@@ -532,6 +546,7 @@ impl StoreTransaction {
                         last_finalized_block_number
                     ))
                 })?;
+            self.insert_owner_withdrawals(&last_finalized_block, true)?;
 
             let deposit_assets = self
                 .get_block_deposit_requests(&last_finalized_block_hash)?
@@ -585,6 +600,7 @@ impl StoreTransaction {
         }
 
         let block_hash: H256 = block.hash().into();
+        self.delete_owner_withdrawals(block)?;
 
         // update finalized custodian assets
         let finality_blocks = rollup_config.finality_blocks().unpack();
@@ -608,6 +624,7 @@ impl StoreTransaction {
                         last_finalized_block_number
                     ))
                 })?;
+            self.insert_owner_withdrawals(&last_finalized_block, false)?;
 
             let deposit_assets = self
                 .get_block_deposit_requests(&last_finalized_block_hash)?
@@ -667,6 +684,94 @@ impl StoreTransaction {
         Ok(())
     }
 
+    // Value is block hash followed by a finalized flag byte
+    pub(crate) fn insert_owner_withdrawals(
+        &self,
+        block: &packed::L2Block,
+        finalized: bool,
+    ) -> Result<(), Error> {
+        let block_number: u64 = block.raw().number().unpack();
+        let mut value = block.hash().to_vec();
+        value.push(finalized as u8);
+        for (index, withdrawal) in block.withdrawals().into_iter().enumerate() {
+            let owner_lock_hash: H256 = withdrawal.raw().owner_lock_hash().unpack();
+            let key = OwnerWithdrawalKey::new(&owner_lock_hash, block_number, index as u32);
+            self.insert_raw(COLUMN_OWNER_WITHDRAWALS, key.as_slice(), &value)?;
+        }
+        Ok(())
+    }
+
+    fn delete_owner_withdrawals(&self, block: &packed::L2Block) -> Result<(), Error> {
+        let block_number: u64 = block.raw().number().unpack();
+        for (index, withdrawal) in block.withdrawals().into_iter().enumerate() {
+            let owner_lock_hash: H256 = withdrawal.raw().owner_lock_hash().unpack();
+            let key = OwnerWithdrawalKey::new(&owner_lock_hash, block_number, index as u32);
+            self.delete(COLUMN_OWNER_WITHDRAWALS, key.as_slice())?;
+        }
+        Ok(())
+    }
+
+    /// Unfinalized withdrawals of `owner_lock_hash` on main chain, ordered by
+    /// block number and withdrawal index
+    pub fn get_pending_withdrawals(
+        &self,
+        owner_lock_hash: &H256,
+    ) -> Result<Vec<OwnerWithdrawal>, Error> {
+        let withdrawals = self.get_owner_withdrawals(owner_lock_hash)?;
+        Ok(withdrawals.into_iter().filter(|w| !w.finalized).collect())
+    }
+
+    /// Withdrawals of `owner_lock_hash` on main chain, ordered by block number
+    /// and withdrawal index
+    pub fn get_owner_withdrawals(
+        &self,
+        owner_lock_hash: &H256,
+    ) -> Result<Vec<OwnerWithdrawal>, Error> {
+        let start_key = OwnerWithdrawalKey::new(owner_lock_hash, 0, 0);
+        let keys: Vec<(OwnerWithdrawalKey, Box<[u8]>)> = self
+            .get_iter(
+                COLUMN_OWNER_WITHDRAWALS,
+                IteratorMode::From(start_key.as_slice(), Forward),
+            )
+            .map(|(key, value)| (OwnerWithdrawalKey::from_vec(key.to_vec()), value))
+            .take_while(|(key, _)| key.is_same_owner(owner_lock_hash))
+            .collect();
+
+        let mut withdrawals = Vec::with_capacity(keys.len());
+        for (key, value) in keys {
+            let block_hash = {
+                let mut buf = [0u8; 32];
+                buf.copy_from_slice(&value[..32]);
+                H256::from(buf)
+            };
+            let finalized = value[32] != 0;
+            let block = self.get_block(&block_hash)?.ok_or_else(|| {
+                Error::from(format!(
+                    "block {} of withdrawal not found",
+                    key.block_number()
+                ))
+            })?;
+            let request = block
+                .withdrawals()
+                .get(key.index() as usize)
+                .ok_or_else(|| {
+                    Error::from(format!(
+                        "withdrawal {} not found in block {}",
+                        key.index(),
+                        key.block_number()
+                    ))
+                })?;
+            withdrawals.push(OwnerWithdrawal {
+                block_number: key.block_number(),
+                block_hash,
+                index: key.index(),
+                request,
+                finalized,
+            });
+        }
+        Ok(withdrawals)
+    }
+
     pub fn record_block_state(
         &self,
         block_number: u64,
@@ -727,6 +832,43 @@ struct CustodianChange {
     amount: u128,
 }
 
+// owner_lock_hash(32 bytes) | block_number(8 bytes) | withdrawal_index(4 bytes)
+struct OwnerWithdrawalKey(Vec<u8>);
+
+impl OwnerWithdrawalKey {
+    fn new(owner_lock_hash: &H256, block_number: u64, index: u32) -> Self {
+        let mut key = Vec::with_capacity(44);
+        key.extend_from_slice(owner_lock_hash.as_slice());
+        key.extend_from_slice(&block_number.to_be_bytes());
+        key.extend_from_slice(&index.to_be_bytes());
+        OwnerWithdrawalKey(key)
+    }
+
+    fn from_vec(key: Vec<u8>) -> Self {
+        OwnerWithdrawalKey(key)
+    }
+
+    fn is_same_owner(&self, owner_lock_hash: &H256) -> bool {
+        self.0.len() == 44 && &self.0[..32] == owner_lock_hash.as_slice()
+    }
+
+    fn block_number(&self) -> u64 {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(&self.0[32..40]);
+        u64::from_be_bytes(buf)
+    }
+
+    fn index(&self) -> u32 {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(&self.0[40..44]);
+        u32::from_be_bytes(buf)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+}
+
 // block_number(8 bytes) | tx_index(4 bytes) | col (1 byte) | key (n bytes)
 struct BlockStateRecordKey(Vec<u8>);
 
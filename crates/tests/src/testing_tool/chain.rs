@@ -1,22 +1,31 @@
 use gw_block_producer::produce_block::{produce_block, ProduceBlockParam, ProduceBlockResult};
 use gw_block_producer::withdrawal::AvailableCustodians;
 use gw_chain::chain::{Chain, L1Action, L1ActionContext, SyncParam};
-use gw_common::{blake2b::new_blake2b, H256};
+use gw_common::{
+    blake2b::new_blake2b, builtins::CKB_SUDT_ACCOUNT_ID, state::to_short_address, H256,
+};
 use gw_config::{BackendConfig, GenesisConfig};
 use gw_generator::{
     account_lock_manage::{always_success::AlwaysSuccess, AccountLockManage},
     backend_manage::BackendManage,
+    generator::{StateTransitionArgs, StateTransitionResult},
     genesis::init_genesis,
     types::RollupContext,
     Generator,
 };
 use gw_mem_pool::pool::MemPool;
-use gw_store::Store;
+use gw_store::{
+    chain_view::ChainView,
+    state_db::{CheckPoint, StateDBMode, StateDBTransaction, SubState, WriteContext},
+    Store,
+};
 use gw_types::{
     bytes::Bytes,
+    core::ScriptHashType,
     packed::{
-        CellOutput, DepositRequest, L2BlockCommittedInfo, RawTransaction, RollupAction,
-        RollupActionUnion, RollupConfig, RollupSubmitBlock, Script, Transaction, WitnessArgs,
+        AccountMerkleState, CellOutput, DepositRequest, L2Block, L2BlockCommittedInfo,
+        L2Transaction, RawL2Transaction, RawTransaction, RollupAction, RollupActionUnion,
+        RollupConfig, RollupSubmitBlock, SUDTArgs, SUDTTransfer, Script, Transaction, WitnessArgs,
     },
     prelude::*,
 };
@@ -162,17 +171,16 @@ pub fn build_sync_tx(
         .build()
 }
 
-pub fn apply_block_result(
-    chain: &mut Chain,
+pub fn build_submit_block_action(
     rollup_cell: CellOutput,
     block_result: ProduceBlockResult,
     deposit_requests: Vec<DepositRequest>,
-) {
+) -> L1Action {
     let l2block = block_result.block.clone();
     let transaction = build_sync_tx(rollup_cell, block_result);
     let l2block_committed_info = L2BlockCommittedInfo::default();
 
-    let update = L1Action {
+    L1Action {
         context: L1ActionContext::SubmitBlock {
             l2block,
             deposit_requests,
@@ -180,7 +188,16 @@ pub fn apply_block_result(
         },
         transaction,
         l2block_committed_info,
-    };
+    }
+}
+
+pub fn apply_block_result(
+    chain: &mut Chain,
+    rollup_cell: CellOutput,
+    block_result: ProduceBlockResult,
+    deposit_requests: Vec<DepositRequest>,
+) {
+    let update = build_submit_block_action(rollup_cell, block_result, deposit_requests);
     let param = SyncParam {
         updates: vec![update],
         reverts: Default::default(),
@@ -243,3 +260,139 @@ pub fn construct_block(
     };
     produce_block(param)
 }
+
+pub fn apply_block(
+    chain: &Chain,
+    generator: &Generator,
+    l2block: L2Block,
+) -> (H256, Vec<AccountMerkleState>) {
+    let (result, root) = try_apply_block(chain, generator, l2block, false);
+    let tx_receipts = match result {
+        StateTransitionResult::Success { tx_receipts, .. } => tx_receipts,
+        StateTransitionResult::Challenge { error, .. } => panic!("challenge {}", error),
+        StateTransitionResult::Error(err) => panic!("error {}", err),
+    };
+    let post_states = tx_receipts.into_iter().map(|r| r.post_state()).collect();
+    (root, post_states)
+}
+
+pub fn try_apply_block(
+    chain: &Chain,
+    generator: &Generator,
+    l2block: L2Block,
+    skip_signature_check: bool,
+) -> (StateTransitionResult, H256) {
+    // Not committed, so the block can be applied again
+    let db = chain.store().begin_transaction();
+    let tip_block_hash = db.get_tip_block_hash().unwrap();
+    let chain_view = ChainView::new(&db, tip_block_hash);
+    let state_db = StateDBTransaction::from_checkpoint(
+        &db,
+        CheckPoint::new(l2block.raw().number().unpack(), SubState::Block),
+        StateDBMode::Write(WriteContext::new(0)),
+    )
+    .unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+
+    let args = StateTransitionArgs {
+        l2block,
+        deposit_requests: vec![],
+        skip_signature_check,
+    };
+    let result = generator.verify_and_apply_state_transition(&chain_view, &mut tree, args);
+    (result, tree.calculate_root().unwrap())
+}
+
+// Chain with a deposited user, and a block of multiple transactions on top of it
+pub fn setup_multi_tx_block() -> (Chain, ProduceBlockResult) {
+    let chain = setup_chain(Script::default());
+    let (chain, _, block_result) = setup_multi_tx_block_on(chain);
+    (chain, block_result)
+}
+
+// Same as `setup_multi_tx_block` on a chain of default rollup type script,
+// along with the deposit action to sync other chains
+pub fn setup_multi_tx_block_on(mut chain: Chain) -> (Chain, L1Action, ProduceBlockResult) {
+    let rollup_type_script = Script::default();
+    let rollup_script_hash = rollup_type_script.hash();
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(rollup_type_script).pack())
+        .build();
+
+    // deposit
+    let user_script = Script::new_builder()
+        .code_hash(ALWAYS_SUCCESS_CODE_HASH.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args({
+            let mut args = rollup_script_hash.to_vec();
+            args.push(42);
+            args.pack()
+        })
+        .build();
+    let user_script_hash: H256 = user_script.hash().into();
+    let deposit_requests = vec![DepositRequest::new_builder()
+        .capacity(600_00000000u64.pack())
+        .script(user_script)
+        .build()];
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block(&chain, &mem_pool, deposit_requests.clone()).unwrap()
+    };
+    let deposit_action = build_submit_block_action(rollup_cell, block_result, deposit_requests);
+    let param = SyncParam {
+        updates: vec![deposit_action.clone()],
+        reverts: Default::default(),
+    };
+    chain.sync(param).unwrap();
+    assert_eq!(chain.last_sync_event().is_success(), true);
+
+    // block with multiple transactions
+    let block_result = {
+        let mut mem_pool = chain.mem_pool().lock();
+        let user_id = {
+            let db = chain.store().begin_transaction();
+            let state_db = mem_pool.fetch_state_db(&db).unwrap();
+            let state = state_db.account_state_tree().unwrap();
+            state
+                .get_account_id_by_script_hash(&user_script_hash)
+                .unwrap()
+                .expect("account exists")
+        };
+        for nonce in 0..3u32 {
+            let args = SUDTArgs::new_builder()
+                .set(
+                    SUDTTransfer::new_builder()
+                        .to(to_short_address(&H256::from([3u8; 32])).to_vec().pack())
+                        .amount(100u128.pack())
+                        .fee(1u128.pack())
+                        .build(),
+                )
+                .build();
+            let raw = RawL2Transaction::new_builder()
+                .from_id(user_id.pack())
+                .to_id(CKB_SUDT_ACCOUNT_ID.pack())
+                .nonce(nonce.pack())
+                .args(args.as_bytes().pack())
+                .build();
+            let tx = L2Transaction::new_builder().raw(raw).build();
+            mem_pool.push_transaction(tx).unwrap();
+        }
+        construct_block(&chain, &mem_pool, vec![]).unwrap()
+    };
+    assert_eq!(block_result.block.transactions().len(), 3);
+    (chain, deposit_action, block_result)
+}
+
+pub fn build_generator(chain: &Chain) -> Generator {
+    let rollup_context = chain.generator().rollup_context().clone();
+    let mut account_lock_manage = AccountLockManage::default();
+    account_lock_manage.register_lock_algorithm(
+        ALWAYS_SUCCESS_CODE_HASH.clone().into(),
+        Box::new(AlwaysSuccess),
+    );
+    Generator::new(
+        build_backend_manage(&rollup_context.rollup_config),
+        account_lock_manage,
+        rollup_context,
+    )
+}
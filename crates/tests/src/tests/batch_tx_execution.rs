@@ -0,0 +1,18 @@
+use crate::testing_tool::chain::{apply_block, build_generator, setup_multi_tx_block};
+use gw_common::H256;
+use gw_types::prelude::*;
+
+#[test]
+fn test_batch_tx_execution() {
+    let (chain, block_result) = setup_multi_tx_block();
+    let l2block = block_result.block;
+    let batch_generator = build_generator(&chain).with_batch_tx_execution(true);
+
+    let (root, post_states) = apply_block(&chain, chain.generator(), l2block.clone());
+    let (batch_root, batch_post_states) = apply_block(&chain, &batch_generator, l2block.clone());
+    assert_eq!(batch_root, root);
+    assert_eq!(batch_post_states, post_states);
+
+    let post_root: H256 = l2block.raw().post_account().merkle_root().unpack();
+    assert_eq!(root, post_root);
+}
@@ -1,18 +1,30 @@
 use crate::testing_tool::chain::{
-    build_sync_tx, construct_block, setup_chain, ALWAYS_SUCCESS_CODE_HASH,
+    build_generator, build_submit_block_action, build_sync_tx, construct_block, setup_chain,
+    setup_multi_tx_block_on, ALWAYS_SUCCESS_CODE_HASH,
+};
+use gw_block_producer::produce_block::ProduceBlockResult;
+use gw_chain::chain::{
+    Chain, ChallengeCell, L1Action, L1ActionContext, ReorgDetected, RevertedL1Action, SyncEvent,
+    SyncParam, UnsafeReorg,
 };
-use gw_chain::chain::{Chain, L1Action, L1ActionContext, RevertedL1Action, SyncParam};
 use gw_common::{
     builtins::CKB_SUDT_ACCOUNT_ID,
     state::{to_short_address, State},
     H256,
 };
+use gw_mem_pool::pool::MemPool;
 use gw_store::state_db::{CheckPoint, StateDBMode, StateDBTransaction, SubState};
 use gw_types::{
-    core::ScriptHashType,
-    packed::{CellOutput, DepositRequest, GlobalState, L2BlockCommittedInfo, Script},
+    core::{ScriptHashType, Status},
+    packed::{
+        CellOutput, DepositRequest, GlobalState, L2Block, L2BlockCommittedInfo, L2Transaction,
+        RawTransaction, Script, Transaction,
+    },
     prelude::*,
 };
+use parking_lot::Mutex;
+use std::sync::Arc;
+use tracing::{field, span};
 
 fn produce_a_block(
     chain: &mut Chain,
@@ -148,11 +160,90 @@ fn test_produce_blocks() {
     drop(chain);
 }
 
+/// Collect names and `number` fields of created spans
+#[derive(Clone, Default)]
+struct SpanRecorder {
+    spans: Arc<Mutex<Vec<(&'static str, Option<u64>)>>>,
+}
+
+impl tracing::Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        struct NumberVisitor(Option<u64>);
+        impl field::Visit for NumberVisitor {
+            fn record_u64(&mut self, field: &field::Field, value: u64) {
+                if field.name() == "number" {
+                    self.0 = Some(value);
+                }
+            }
+            fn record_debug(&mut self, _field: &field::Field, _value: &dyn std::fmt::Debug) {}
+        }
+
+        let mut visitor = NumberVisitor(None);
+        span.record(&mut visitor);
+        let mut spans = self.spans.lock();
+        spans.push((span.metadata().name(), visitor.0));
+        span::Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+    fn event(&self, _event: &tracing::Event<'_>) {}
+    fn enter(&self, _span: &span::Id) {}
+    fn exit(&self, _span: &span::Id) {}
+}
+
+#[test]
+fn test_sync_block_spans() {
+    let rollup_type_script = Script::default();
+    let rollup_script_hash = rollup_type_script.hash();
+    let mut chain = setup_chain(rollup_type_script.clone());
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(rollup_type_script).pack())
+        .build();
+    let user_script = Script::new_builder()
+        .code_hash(ALWAYS_SUCCESS_CODE_HASH.clone().pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args({
+            let mut args = rollup_script_hash.to_vec();
+            args.push(42);
+            args.pack()
+        })
+        .build();
+    let deposit = DepositRequest::new_builder()
+        .capacity(100u64.pack())
+        .script(user_script)
+        .build();
+
+    let recorder = SpanRecorder::default();
+    tracing::subscriber::with_default(recorder.clone(), || {
+        produce_a_block(&mut chain, deposit.clone(), rollup_cell.clone(), 1);
+        produce_a_block(&mut chain, deposit, rollup_cell, 2);
+    });
+
+    let spans = recorder.spans.lock();
+    let synced_blocks: Vec<_> = spans
+        .iter()
+        .filter(|(name, _)| *name == "sync_block")
+        .map(|(_, number)| *number)
+        .collect();
+    assert_eq!(synced_blocks, vec![Some(1), Some(2)]);
+    let applied_blocks = spans
+        .iter()
+        .filter(|(name, _)| *name == "apply_state_transition")
+        .count();
+    assert_eq!(applied_blocks, 2);
+}
+
 #[test]
 fn test_layer1_fork() {
     let rollup_type_script = Script::default();
     let rollup_script_hash = rollup_type_script.hash();
     let mut chain = setup_chain(rollup_type_script.clone());
+    let genesis_block_hash: H256 = chain.local_state().tip().hash().into();
     let rollup_cell = CellOutput::new_builder()
         .type_(Some(rollup_type_script.clone()).pack())
         .build();
@@ -262,27 +353,61 @@ fn test_layer1_fork() {
     let tip_block = chain.store().get_tip_block().unwrap();
     let tip_block_number: u64 = tip_block.raw().number().unpack();
     assert_eq!(tip_block_number, 2);
+    assert!(chain.last_reorg().is_none());
+    let old_tip_block_hash: H256 = tip_block.hash().into();
+
+    // fork block must not be applied before reverting local blocks
+    let param = SyncParam {
+        updates: vec![fork_action.clone()],
+        reverts: Default::default(),
+    };
+    let err = chain.sync(param).unwrap_err();
+    assert!(err.to_string().starts_with("fork detected"));
+    assert_eq!(
+        chain.store().get_tip_block_hash().unwrap(),
+        old_tip_block_hash
+    );
+
+    let to_reverts = |updates: Vec<L1Action>| {
+        updates
+            .into_iter()
+            .rev()
+            .map(|action| {
+                let prev_global_state = GlobalState::default();
+                let L1Action {
+                    transaction,
+                    l2block_committed_info,
+                    context,
+                } = action;
+                RevertedL1Action {
+                    prev_global_state,
+                    transaction,
+                    l2block_committed_info,
+                    context,
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // fork detected after reverting part of local blocks, reverts are
+    // dropped along with local state
+    let param = SyncParam {
+        updates: vec![fork_action.clone()],
+        reverts: to_reverts(vec![action2.clone()]),
+    };
+    let err = chain.sync(param).unwrap_err();
+    assert!(err.to_string().starts_with("fork detected"));
+    assert_eq!(
+        chain.store().get_tip_block_hash().unwrap(),
+        old_tip_block_hash
+    );
+    let local_tip_block_hash: H256 = chain.local_state().tip().hash().into();
+    assert_eq!(local_tip_block_hash, old_tip_block_hash);
+    let last_synced_number: u64 = chain.local_state().last_synced().number().unpack();
+    assert_eq!(last_synced_number, 2);
 
     // revert blocks
-    let updates = vec![action1, action2];
-    let reverts = updates
-        .into_iter()
-        .rev()
-        .map(|action| {
-            let prev_global_state = GlobalState::default();
-            let L1Action {
-                transaction,
-                l2block_committed_info,
-                context,
-            } = action;
-            RevertedL1Action {
-                prev_global_state,
-                transaction,
-                l2block_committed_info,
-                context,
-            }
-        })
-        .collect::<Vec<_>>();
+    let reverts = to_reverts(vec![action1, action2]);
     let forks = vec![fork_action];
 
     let param = SyncParam {
@@ -295,6 +420,14 @@ fn test_layer1_fork() {
     let tip_block = chain.store().get_tip_block().unwrap();
     let tip_block_number: u64 = tip_block.raw().number().unpack();
     assert_eq!(tip_block_number, 1);
+    assert_eq!(
+        chain.last_reorg(),
+        Some(&ReorgDetected {
+            common_ancestor: genesis_block_hash,
+            old_tip: old_tip_block_hash,
+            new_tip: tip_block.hash().into(),
+        })
+    );
 
     // check account SMT, should be able to calculate account state root
     {
@@ -315,6 +448,266 @@ fn test_layer1_fork() {
     }
 }
 
+#[test]
+fn test_restore_bad_blocks_on_failed_sync() {
+    let rollup_type_script = Script::default();
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(rollup_type_script.clone()).pack())
+        .build();
+    let chain = setup_chain(rollup_type_script);
+    let (mut chain, _, block_result) = setup_multi_tx_block_on(chain);
+    let parent_global_state = chain.local_state().last_global_state().clone();
+    let build_action = |global_state: GlobalState, context: L1ActionContext| {
+        let raw = RawTransaction::new_builder()
+            .outputs(vec![rollup_cell.clone()].pack())
+            .outputs_data(vec![global_state.as_bytes()].pack())
+            .build();
+        L1Action {
+            transaction: Transaction::new_builder().raw(raw).build(),
+            l2block_committed_info: L2BlockCommittedInfo::default(),
+            context,
+        }
+    };
+    let with_block = |block: L2Block| ProduceBlockResult {
+        block,
+        global_state: block_result.global_state.clone(),
+        unused_transactions: vec![],
+        unused_withdrawal_requests: vec![],
+    };
+
+    // bad block, its first tx has a wrong nonce
+    let bad_block = {
+        let mut txs: Vec<L2Transaction> = block_result.block.transactions().into_iter().collect();
+        let raw = txs[0].raw().as_builder().nonce(7u32.pack()).build();
+        txs[0] = txs[0].clone().as_builder().raw(raw).build();
+        let block = block_result.block.clone().as_builder();
+        block.transactions(txs.pack()).build()
+    };
+    let bad_action = build_submit_block_action(rollup_cell.clone(), with_block(bad_block), vec![]);
+    chain
+        .sync(SyncParam {
+            updates: vec![bad_action],
+            reverts: vec![],
+        })
+        .unwrap();
+    let bad_block_context = match chain.last_sync_event() {
+        SyncEvent::BadBlock { context } => context.clone(),
+        event => panic!("unexpected sync event {:?}", event),
+    };
+
+    // challenge the bad block
+    let challenge_action = {
+        let halting_status: u8 = Status::Halting.into();
+        let global_state = block_result.global_state.clone().as_builder();
+        let global_state = global_state.status(halting_status.into()).build();
+        let context = L1ActionContext::Challenge {
+            cell: ChallengeCell {
+                input: Default::default(),
+                output: Default::default(),
+                output_data: Default::default(),
+            },
+            target: bad_block_context.target.clone(),
+            witness: bad_block_context.witness.clone(),
+        };
+        build_action(global_state, context)
+    };
+    chain
+        .sync(SyncParam {
+            updates: vec![challenge_action],
+            reverts: vec![],
+        })
+        .unwrap();
+    let post_reverted_block_root = match chain.last_sync_event() {
+        SyncEvent::WaitChallenge { context, .. } => context.post_reverted_block_root,
+        event => panic!("unexpected sync event {:?}", event),
+    };
+
+    // revert the bad block, followed by a fork in the same sync
+    let revert_action = {
+        let global_state = parent_global_state.as_builder();
+        let global_state = global_state
+            .reverted_block_root(post_reverted_block_root.pack())
+            .build();
+        let context = L1ActionContext::Revert {
+            reverted_blocks: vec![bad_block_context.witness.raw_l2block()],
+        };
+        build_action(global_state, context)
+    };
+    let fork_action = {
+        let raw = block_result.block.raw().as_builder();
+        let raw = raw.parent_block_hash([9u8; 32].pack()).build();
+        let fork_block = block_result.block.clone().as_builder().raw(raw).build();
+        build_submit_block_action(rollup_cell.clone(), with_block(fork_block), vec![])
+    };
+    let param = SyncParam {
+        updates: vec![revert_action.clone(), fork_action],
+        reverts: vec![],
+    };
+    let err = chain.sync(param).unwrap_err();
+    assert!(err.to_string().starts_with("fork detected"));
+
+    // bad blocks and pending revert blocks are restored along with local state
+    assert!(chain.pending_revert_blocks().is_empty());
+    assert_eq!(chain.local_state().status(), Status::Halting);
+    assert!(matches!(
+        chain.last_sync_event(),
+        SyncEvent::WaitChallenge { .. }
+    ));
+
+    // the bad block is still known, so the revert can be synced again
+    chain
+        .sync(SyncParam {
+            updates: vec![revert_action],
+            reverts: vec![],
+        })
+        .unwrap();
+    assert!(chain.last_sync_event().is_success());
+    let pending_revert_blocks: Vec<[u8; 32]> = chain
+        .pending_revert_blocks()
+        .iter()
+        .map(|block| block.hash())
+        .collect();
+    assert_eq!(
+        pending_revert_blocks,
+        vec![bad_block_context.witness.raw_l2block().hash()]
+    );
+}
+
+fn to_reverts(params: Vec<SyncParam>) -> Vec<RevertedL1Action> {
+    let updates = params.into_iter().flat_map(|param| param.updates);
+    updates
+        .rev()
+        .map(|action| {
+            let L1Action {
+                transaction,
+                l2block_committed_info,
+                context,
+            } = action;
+            RevertedL1Action {
+                prev_global_state: GlobalState::default(),
+                transaction,
+                l2block_committed_info,
+                context,
+            }
+        })
+        .collect()
+}
+
+// Open another chain on the same store, like a node restart
+fn reopen_chain(chain: &Chain, rollup_type_script: &Script) -> Chain {
+    let generator = Arc::new(build_generator(chain));
+    let mem_pool = MemPool::create(chain.store().clone(), Arc::clone(&generator)).unwrap();
+    let rollup_config = generator.rollup_context().rollup_config.clone();
+    Chain::create(
+        &rollup_config,
+        rollup_type_script,
+        chain.store().clone(),
+        generator,
+        Arc::new(Mutex::new(mem_pool)),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_max_safe_reorg_depth() {
+    let rollup_type_script = Script::default();
+    let rollup_script_hash = rollup_type_script.hash();
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(rollup_type_script.clone()).pack())
+        .build();
+    let user_script = Script::new_builder()
+        .code_hash(ALWAYS_SUCCESS_CODE_HASH.clone().pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args({
+            let mut args = rollup_script_hash.to_vec();
+            args.push(42);
+            args.pack()
+        })
+        .build();
+    let produce_blocks = |chain: &mut Chain| -> Vec<SyncParam> {
+        (1..=3u64)
+            .map(|number| {
+                let deposit = DepositRequest::new_builder()
+                    .capacity((100u64 * number).pack())
+                    .script(user_script.clone())
+                    .build();
+                produce_a_block(chain, deposit, rollup_cell.clone(), number)
+            })
+            .collect()
+    };
+
+    // reorg within the limit reverts normally
+    let mut chain = setup_chain(rollup_type_script.clone()).with_max_safe_reorg_depth(Some(1));
+    let mut params = produce_blocks(&mut chain);
+    let param = SyncParam {
+        updates: Default::default(),
+        reverts: to_reverts(params.split_off(2)),
+    };
+    chain.sync(param).unwrap();
+    assert!(chain.unsafe_reorg().is_none());
+    let tip_block_number: u64 = chain.local_state().tip().raw().number().unpack();
+    assert_eq!(tip_block_number, 2);
+
+    // reorg beyond the limit halts the chain
+    let mut chain = setup_chain(rollup_type_script.clone()).with_max_safe_reorg_depth(Some(1));
+    let mut params = produce_blocks(&mut chain);
+    let tip_block_hash: H256 = chain.local_state().tip().hash().into();
+    let unsafe_reverts = to_reverts(params.split_off(1));
+    let param = SyncParam {
+        updates: Default::default(),
+        reverts: unsafe_reverts.clone(),
+    };
+    let err = chain.sync(param).unwrap_err();
+    assert!(err.to_string().starts_with("chain halted by unsafe reorg"));
+    assert_eq!(
+        chain.unsafe_reorg(),
+        Some(&UnsafeReorg {
+            depth: 2,
+            max_safe_depth: 1,
+            tip: tip_block_hash,
+        })
+    );
+    assert_eq!(chain.store().get_tip_block_hash().unwrap(), tip_block_hash);
+    // block producer and challenger only act on success
+    match chain.last_sync_event() {
+        SyncEvent::Halted { reorg } => assert_eq!(Some(reorg), chain.unsafe_reorg()),
+        event => panic!("unexpected sync event {:?}", event),
+    }
+
+    // stay halted, even without reverts
+    let param = SyncParam {
+        updates: Default::default(),
+        reverts: to_reverts(params.split_off(1)),
+    };
+    assert!(chain.sync(param).is_err());
+    assert_eq!(chain.store().get_tip_block_hash().unwrap(), tip_block_hash);
+
+    // stay halted after restart, the same reorg is synced again
+    let unsafe_reorg = chain.unsafe_reorg().cloned();
+    let mut chain = reopen_chain(&chain, &rollup_type_script);
+    assert_eq!(chain.unsafe_reorg().cloned(), unsafe_reorg);
+    assert!(matches!(chain.last_sync_event(), SyncEvent::Halted { .. }));
+    let param = SyncParam {
+        updates: Default::default(),
+        reverts: unsafe_reverts.clone(),
+    };
+    assert!(chain.sync(param).is_err());
+    assert_eq!(chain.store().get_tip_block_hash().unwrap(), tip_block_hash);
+
+    // operator clears the halt, the reorg is reverted after restart
+    chain.clear_unsafe_reorg().unwrap();
+    let mut chain = reopen_chain(&chain, &rollup_type_script);
+    assert!(chain.unsafe_reorg().is_none());
+    assert!(chain.last_sync_event().is_success());
+    let param = SyncParam {
+        updates: Default::default(),
+        reverts: unsafe_reverts,
+    };
+    chain.sync(param).unwrap();
+    let tip_block_number: u64 = chain.local_state().tip().raw().number().unpack();
+    assert_eq!(tip_block_number, 1);
+}
+
 #[test]
 fn test_layer1_revert() {
     let rollup_type_script = Script::default();
@@ -0,0 +1,98 @@
+use crate::testing_tool::chain::{apply_block_result, build_generator, setup_multi_tx_block};
+use gw_chain::challenge::{build_verify_context_with_checks, ChallengeError, VerifyWitness};
+use gw_db::schema::COLUMN_BLOCK;
+use gw_store::traits::KVStore;
+use gw_types::{
+    core::ChallengeTargetType,
+    packed::{CellOutput, ChallengeTarget, Script},
+    prelude::*,
+};
+use std::sync::Arc;
+
+#[test]
+fn test_build_verify_context_without_checkpoint_checks() {
+    let (mut chain, block_result) = setup_multi_tx_block();
+    let l2block = block_result.block.clone();
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(Script::default()).pack())
+        .build();
+    apply_block_result(&mut chain, rollup_cell, block_result, vec![]);
+
+    let target = ChallengeTarget::new_builder()
+        .block_hash(l2block.hash().pack())
+        .target_index(1u32.pack())
+        .target_type(ChallengeTargetType::TxExecution.into())
+        .build();
+    let generator = Arc::new(build_generator(&chain));
+    let build_witness = |verify_checkpoints: bool| {
+        let db = chain.store().begin_transaction();
+        let generator = Arc::clone(&generator);
+        let context =
+            build_verify_context_with_checks(generator, &db, &target, verify_checkpoints).unwrap();
+        match context.verify_witness {
+            VerifyWitness::TxExecution(witness) => witness,
+            _ => panic!("unexpected verify witness"),
+        }
+    };
+
+    let checked_witness = build_witness(true);
+    let unchecked_witness = build_witness(false);
+    assert_eq!(checked_witness.as_slice(), unchecked_witness.as_slice());
+}
+
+#[test]
+fn test_skip_checks_of_tampered_checkpoint() {
+    let (mut chain, block_result) = setup_multi_tx_block();
+    let l2block = block_result.block.clone();
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(Script::default()).pack())
+        .build();
+    apply_block_result(&mut chain, rollup_cell, block_result, vec![]);
+
+    // Replace stored block checkpoints, keep block hash
+    let tampered_block = {
+        let raw_block = l2block.raw();
+        let checkpoint_count = raw_block.state_checkpoint_list().len();
+        let checkpoints = vec![[0xffu8; 32]; checkpoint_count];
+        let raw_block = raw_block
+            .as_builder()
+            .state_checkpoint_list(checkpoints.pack())
+            .build();
+        l2block.clone().as_builder().raw(raw_block).build()
+    };
+    {
+        let db = chain.store().begin_transaction();
+        db.insert_raw(COLUMN_BLOCK, &l2block.hash(), tampered_block.as_slice())
+            .unwrap();
+        db.commit().unwrap();
+    }
+
+    let target = ChallengeTarget::new_builder()
+        .block_hash(l2block.hash().pack())
+        .target_index(1u32.pack())
+        .target_type(ChallengeTargetType::TxExecution.into())
+        .build();
+    let generator = Arc::new(build_generator(&chain));
+    let db = chain.store().begin_transaction();
+
+    let err =
+        build_verify_context_with_checks(Arc::clone(&generator), &db, &target, true).unwrap_err();
+    match err.downcast_ref::<ChallengeError>() {
+        Some(ChallengeError::CheckpointMismatch { kind, block, .. }) => {
+            assert_eq!(*kind, "prev tx");
+            assert_eq!(*block, [0xffu8; 32]);
+        }
+        _ => panic!("unexpected error {}", err),
+    }
+
+    let context = build_verify_context_with_checks(generator, &db, &target, false).unwrap();
+    match context.verify_witness {
+        VerifyWitness::TxExecution(witness) => {
+            assert_eq!(
+                witness.l2tx().hash(),
+                l2block.transactions().get(1).unwrap().hash()
+            );
+        }
+        _ => panic!("unexpected verify witness"),
+    }
+}
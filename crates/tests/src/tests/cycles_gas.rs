@@ -0,0 +1,53 @@
+use crate::testing_tool::chain::{build_generator, setup_multi_tx_block};
+use gw_generator::Generator;
+use gw_store::{
+    chain_view::ChainView,
+    state_db::{CheckPoint, StateDBMode, StateDBTransaction, SubState},
+};
+use gw_types::{packed::BlockInfo, prelude::*};
+
+#[test]
+fn test_cycles_gas_used() {
+    let (chain, block_result) = setup_multi_tx_block();
+    let raw_block = block_result.block.raw();
+    let block_info = BlockInfo::new_builder()
+        .block_producer_id(raw_block.block_producer_id())
+        .number(raw_block.number())
+        .timestamp(raw_block.timestamp())
+        .build();
+    let raw_tx = block_result.block.transactions().get(0).unwrap().raw();
+
+    // Execute the first tx of the block on the tip state
+    let db = chain.store().begin_transaction();
+    let tip_block_hash = db.get_tip_block_hash().unwrap();
+    let chain_view = ChainView::new(&db, tip_block_hash);
+    let state_db = StateDBTransaction::from_checkpoint(
+        &db,
+        CheckPoint::from_block_hash(&db, tip_block_hash, SubState::Block).unwrap(),
+        StateDBMode::ReadOnly,
+    )
+    .unwrap();
+    let state = state_db.account_state_tree().unwrap();
+    let execute = |generator: &Generator| {
+        generator
+            .execute_transaction(&chain_view, &state, &block_info, &raw_tx)
+            .unwrap()
+    };
+
+    // Cycles aren't metered by default
+    let unmetered_run_result = execute(&build_generator(&chain));
+    assert_eq!(unmetered_run_result.cycles_gas_used, None);
+
+    let cycles_per_gas = 1000;
+    let generator = build_generator(&chain)
+        .with_cycles_per_gas(Some(cycles_per_gas))
+        .unwrap();
+    let run_result = execute(&generator);
+    assert!(run_result.used_cycles > 0);
+    let expected_gas = (run_result.used_cycles + cycles_per_gas - 1) / cycles_per_gas;
+    assert_eq!(run_result.cycles_gas_used, Some(expected_gas));
+
+    // Metering doesn't change the execution itself
+    assert_eq!(run_result.write_values, unmetered_run_result.write_values);
+    assert_eq!(run_result.return_data, unmetered_run_result.return_data);
+}
@@ -18,7 +18,7 @@ use gw_types::{
 use crate::testing_tool::chain::{
     apply_block_result, construct_block, setup_chain, ALWAYS_SUCCESS_CODE_HASH,
 };
-use gw_chain::chain::Chain;
+use gw_chain::{chain::Chain, challenge::get_withdrawal_sender_script};
 
 fn deposite_to_chain(
     chain: &mut Chain,
@@ -271,3 +271,163 @@ fn test_deposit_faked_ckb() {
     let err: Error = err.downcast().unwrap();
     assert_eq!(err, Error::Deposit(DepositError::DepositFakedCKB));
 }
+
+#[test]
+fn test_get_withdrawal_sender_script() {
+    let rollup_type_script = Script::default();
+    let rollup_script_hash = rollup_type_script.hash();
+    let mut chain = setup_chain(rollup_type_script.clone());
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(rollup_type_script).pack())
+        .build();
+    let user_script = Script::new_builder()
+        .code_hash(ALWAYS_SUCCESS_CODE_HASH.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args({
+            let mut args = rollup_script_hash.to_vec();
+            args.push(42);
+            args.pack()
+        })
+        .build();
+    let user_script_hash = user_script.hash();
+
+    // block 1: deposit, block 2: empty, block 3: withdrawal
+    let capacity = 600_00000000;
+    deposite_to_chain(
+        &mut chain,
+        rollup_cell.clone(),
+        user_script.clone(),
+        capacity,
+        H256::zero(),
+        0,
+    )
+    .unwrap();
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+    };
+    apply_block_result(&mut chain, rollup_cell.clone(), block_result, Vec::new());
+    withdrawal_from_chain(
+        &mut chain,
+        rollup_cell,
+        user_script_hash.into(),
+        300_00000000,
+        H256::zero(),
+        0,
+    )
+    .unwrap();
+
+    let db = chain.store().begin_transaction();
+    let block = db.get_tip_block().unwrap();
+    assert_eq!(block.raw().number().unpack(), 3);
+    assert_eq!(block.withdrawals().len(), 1);
+
+    let sender_script = get_withdrawal_sender_script(&db, &block, 0).unwrap();
+    assert_eq!(sender_script.as_slice(), user_script.as_slice());
+    assert!(get_withdrawal_sender_script(&db, &block, 1).is_err());
+}
+
+#[test]
+fn test_pending_withdrawals_index() {
+    let rollup_type_script = Script::default();
+    let rollup_script_hash = rollup_type_script.hash();
+    let mut chain = setup_chain(rollup_type_script.clone());
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(rollup_type_script).pack())
+        .build();
+    let user_script = Script::new_builder()
+        .code_hash(ALWAYS_SUCCESS_CODE_HASH.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args({
+            let mut args = rollup_script_hash.to_vec();
+            args.push(42);
+            args.pack()
+        })
+        .build();
+    let user_script_hash: H256 = user_script.hash().into();
+    let owner_lock_hash = H256::from([7u8; 32]);
+
+    // block 1: deposit, block 2: withdrawal
+    deposite_to_chain(
+        &mut chain,
+        rollup_cell.clone(),
+        user_script,
+        600_00000000,
+        H256::zero(),
+        0,
+    )
+    .unwrap();
+    let withdrawal = {
+        let raw = RawWithdrawalRequest::new_builder()
+            .capacity(300_00000000u64.pack())
+            .account_script_hash(user_script_hash.pack())
+            .owner_lock_hash(owner_lock_hash.pack())
+            .build();
+        WithdrawalRequest::new_builder().raw(raw).build()
+    };
+    let block_result = {
+        let mut mem_pool = chain.mem_pool().lock();
+        mem_pool
+            .push_withdrawal_request(withdrawal.clone())
+            .unwrap();
+        construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+    };
+    apply_block_result(&mut chain, rollup_cell.clone(), block_result, Vec::new());
+
+    let withdrawal_block_hash = chain.store().get_tip_block_hash().unwrap();
+    let pending = chain
+        .store()
+        .get_pending_withdrawals(&owner_lock_hash)
+        .unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].block_number, 2);
+    assert_eq!(pending[0].block_hash, withdrawal_block_hash);
+    assert_eq!(pending[0].index, 0);
+    assert_eq!(pending[0].request.as_slice(), withdrawal.as_slice());
+    assert!(!pending[0].finalized);
+    let other_owner = H256::from([8u8; 32]);
+    assert!(chain
+        .store()
+        .get_pending_withdrawals(&other_owner)
+        .unwrap()
+        .is_empty());
+    assert!(chain
+        .store()
+        .get_owner_withdrawals(&other_owner)
+        .unwrap()
+        .is_empty());
+
+    // finalized after 6 blocks
+    for number in 3..=8u64 {
+        assert_eq!(
+            chain
+                .store()
+                .get_pending_withdrawals(&owner_lock_hash)
+                .unwrap()
+                .len(),
+            1,
+            "pending before block {}",
+            number
+        );
+        let block_result = {
+            let mem_pool = chain.mem_pool().lock();
+            construct_block(&chain, &mem_pool, Vec::default()).unwrap()
+        };
+        apply_block_result(&mut chain, rollup_cell.clone(), block_result, Vec::new());
+    }
+    assert!(chain
+        .store()
+        .get_pending_withdrawals(&owner_lock_hash)
+        .unwrap()
+        .is_empty());
+
+    // still indexed, as finalized
+    let withdrawals = chain
+        .store()
+        .get_owner_withdrawals(&owner_lock_hash)
+        .unwrap();
+    assert_eq!(withdrawals.len(), 1);
+    assert_eq!(withdrawals[0].block_number, 2);
+    assert_eq!(withdrawals[0].block_hash, withdrawal_block_hash);
+    assert!(withdrawals[0].finalized);
+}
@@ -0,0 +1,147 @@
+use crate::testing_tool::chain::{apply_block, build_generator, setup_multi_tx_block};
+use gw_chain::chain::Chain;
+use gw_common::{
+    builtins::CKB_SUDT_ACCOUNT_ID,
+    error::Error as StateError,
+    h256_ext::H256Ext,
+    state::{build_account_key, build_sudt_key, to_short_address, State, SUDT_KEY_FLAG_BALANCE},
+    H256,
+};
+use gw_generator::{
+    error::Error,
+    generator::{StateTransitionArgs, StateTransitionResult},
+    Generator,
+};
+use gw_store::{
+    chain_view::ChainView,
+    state_db::{CheckPoint, StateDBMode, StateDBTransaction, SubState, WriteContext},
+};
+use gw_traits::CodeStore;
+use gw_types::{
+    bytes::Bytes,
+    packed::{L2Block, Script},
+    prelude::*,
+};
+use std::cell::Cell;
+
+#[test]
+fn test_double_tx_execution() {
+    let (chain, block_result) = setup_multi_tx_block();
+    let l2block = block_result.block;
+    let double_generator = build_generator(&chain)
+        .with_batch_tx_execution(true)
+        .with_double_tx_execution(true);
+
+    let (root, post_states) = apply_block(&chain, chain.generator(), l2block.clone());
+    let (double_root, double_post_states) = apply_block(&chain, &double_generator, l2block);
+    assert_eq!(double_root, root);
+    assert_eq!(double_post_states, post_states);
+}
+
+// Every read of `key` returns a larger value than the previous one
+struct NondeterministicState<'a, S> {
+    inner: &'a mut S,
+    key: H256,
+    reads: Cell<u128>,
+}
+
+impl<'a, S: State> State for NondeterministicState<'a, S> {
+    fn get_raw(&self, key: &H256) -> Result<H256, StateError> {
+        let value = self.inner.get_raw(key)?;
+        if key != &self.key {
+            return Ok(value);
+        }
+        let read = self.reads.get();
+        self.reads.set(read + 1);
+        Ok(H256::from_u128(value.to_u128() + read))
+    }
+    fn update_raw(&mut self, key: H256, value: H256) -> Result<(), StateError> {
+        self.inner.update_raw(key, value)
+    }
+    fn get_account_count(&self) -> Result<u32, StateError> {
+        self.inner.get_account_count()
+    }
+    fn set_account_count(&mut self, count: u32) -> Result<(), StateError> {
+        self.inner.set_account_count(count)
+    }
+    fn calculate_root(&self) -> Result<H256, StateError> {
+        self.inner.calculate_root()
+    }
+}
+
+impl<'a, S: CodeStore> CodeStore for NondeterministicState<'a, S> {
+    fn insert_script(&mut self, script_hash: H256, script: Script) {
+        self.inner.insert_script(script_hash, script)
+    }
+    fn get_script(&self, script_hash: &H256) -> Option<Script> {
+        self.inner.get_script(script_hash)
+    }
+    fn get_script_hash_by_short_address(&self, short_address: &[u8]) -> Option<H256> {
+        self.inner.get_script_hash_by_short_address(short_address)
+    }
+    fn insert_data(&mut self, data_hash: H256, code: Bytes) {
+        self.inner.insert_data(data_hash, code)
+    }
+    fn get_data(&self, data_hash: &H256) -> Option<Bytes> {
+        self.inner.get_data(data_hash)
+    }
+}
+
+fn apply_block_with_nondeterministic_reads(
+    chain: &Chain,
+    generator: &Generator,
+    l2block: L2Block,
+) -> StateTransitionResult {
+    let db = chain.store().begin_transaction();
+    let tip_block_hash = db.get_tip_block_hash().unwrap();
+    let chain_view = ChainView::new(&db, tip_block_hash);
+    let state_db = StateDBTransaction::from_checkpoint(
+        &db,
+        CheckPoint::new(l2block.raw().number().unpack(), SubState::Block),
+        StateDBMode::Write(WriteContext::new(0)),
+    )
+    .unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+
+    // Balance of transfer receiver, only read by sudt backend
+    let receiver_balance_key = {
+        let receiver = H256::from([3u8; 32]);
+        let sudt_key = build_sudt_key(SUDT_KEY_FLAG_BALANCE, to_short_address(&receiver));
+        build_account_key(CKB_SUDT_ACCOUNT_ID, &sudt_key)
+    };
+    let mut state = NondeterministicState {
+        inner: &mut tree,
+        key: receiver_balance_key,
+        reads: Cell::new(0),
+    };
+
+    let args = StateTransitionArgs {
+        l2block,
+        deposit_requests: vec![],
+        skip_signature_check: false,
+    };
+    generator.verify_and_apply_state_transition(&chain_view, &mut state, args)
+}
+
+#[test]
+fn test_double_tx_execution_detects_nondeterminism() {
+    let (chain, block_result) = setup_multi_tx_block();
+    let l2block = block_result.block;
+
+    // Single execution can't tell
+    let generator = build_generator(&chain);
+    let result = apply_block_with_nondeterministic_reads(&chain, &generator, l2block.clone());
+    assert!(matches!(result, StateTransitionResult::Success { .. }));
+
+    let double_generator = build_generator(&chain).with_double_tx_execution(true);
+    let result = apply_block_with_nondeterministic_reads(&chain, &double_generator, l2block);
+    match result {
+        StateTransitionResult::Error(Error::NonDeterministic { tx_index, diff }) => {
+            assert_eq!(tx_index, 0);
+            assert!(!diff.is_empty());
+        }
+        StateTransitionResult::Error(err) => panic!("unexpected error {:?}", err),
+        StateTransitionResult::Challenge { error, .. } => panic!("challenge {}", error),
+        StateTransitionResult::Success { .. } => panic!("unexpected success"),
+    }
+}
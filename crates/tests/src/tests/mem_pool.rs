@@ -0,0 +1,156 @@
+use gw_common::{
+    builtins::CKB_SUDT_ACCOUNT_ID,
+    state::{to_short_address, State},
+    H256,
+};
+use gw_generator::error::{AccountError, TransactionValidateError};
+use gw_jsonrpc_types::godwoken::FullRunResult;
+use gw_types::{
+    core::ScriptHashType,
+    packed::{
+        BlockInfo, CellOutput, DepositRequest, L2Transaction, RawL2Transaction, SUDTArgs,
+        SUDTQuery, SUDTTransfer, Script,
+    },
+    prelude::*,
+};
+
+use crate::testing_tool::chain::{
+    apply_block_result, construct_block, setup_chain, ALWAYS_SUCCESS_CODE_HASH,
+};
+
+#[test]
+fn test_reject_tx_to_unknown_receiver() {
+    let chain = setup_chain(Script::default());
+    let unknown_id = 42u32;
+    let raw = RawL2Transaction::new_builder()
+        .from_id(0u32.pack())
+        .to_id(unknown_id.pack())
+        .nonce(0u32.pack())
+        .build();
+    let tx = L2Transaction::new_builder().raw(raw).build();
+
+    let mut mem_pool = chain.mem_pool().lock();
+    let err = mem_pool.push_transaction(tx).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<TransactionValidateError>(),
+        Some(&TransactionValidateError::Account(
+            AccountError::UnknownReceiver {
+                account_id: unknown_id
+            }
+        ))
+    );
+    assert!(mem_pool.pending().is_empty());
+}
+
+#[test]
+fn test_execute_raw_transaction_full_run_result() {
+    let rollup_type_script = Script::default();
+    let rollup_script_hash = rollup_type_script.hash();
+    let mut chain = setup_chain(rollup_type_script.clone());
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(rollup_type_script).pack())
+        .build();
+
+    // deposit
+    let capacity = 600_00000000u64;
+    let user_script = Script::new_builder()
+        .code_hash(ALWAYS_SUCCESS_CODE_HASH.pack())
+        .hash_type(ScriptHashType::Type.into())
+        .args({
+            let mut args = rollup_script_hash.to_vec();
+            args.push(42);
+            args.pack()
+        })
+        .build();
+    let user_script_hash: H256 = user_script.hash().into();
+    let deposit_requests = vec![DepositRequest::new_builder()
+        .capacity(capacity.pack())
+        .script(user_script)
+        .build()];
+    let block_result = {
+        let mem_pool = chain.mem_pool().lock();
+        construct_block(&chain, &mem_pool, deposit_requests.clone()).unwrap()
+    };
+    apply_block_result(&mut chain, rollup_cell, block_result, deposit_requests);
+
+    let tip_block = chain.store().get_tip_block().unwrap();
+    let tip_number: u64 = tip_block.raw().number().unpack();
+    let block_info = BlockInfo::new_builder()
+        .block_producer_id(tip_block.raw().block_producer_id())
+        .timestamp(tip_block.raw().timestamp())
+        .number((tip_number + 1).pack())
+        .build();
+    let user_id = {
+        let mem_pool = chain.mem_pool().lock();
+        let db = chain.store().begin_transaction();
+        let state_db = mem_pool.fetch_state_db(&db).unwrap();
+        let state = state_db.account_state_tree().unwrap();
+        state
+            .get_account_id_by_script_hash(&user_script_hash)
+            .unwrap()
+            .expect("account exists")
+    };
+    let mem_pool = chain.mem_pool().lock();
+
+    // query returns balance
+    let args = SUDTArgs::new_builder()
+        .set(
+            SUDTQuery::new_builder()
+                .short_address(to_short_address(&user_script_hash).to_vec().pack())
+                .build(),
+        )
+        .build();
+    let raw_tx = RawL2Transaction::new_builder()
+        .from_id(user_id.pack())
+        .to_id(CKB_SUDT_ACCOUNT_ID.pack())
+        .args(args.as_bytes().pack())
+        .build();
+    let run_result: FullRunResult = mem_pool
+        .execute_raw_transaction(raw_tx, &block_info, tip_number)
+        .unwrap()
+        .into();
+    assert_eq!(
+        run_result.return_data.into_bytes().as_ref(),
+        &(capacity as u128).to_le_bytes()[..]
+    );
+    assert!(!run_result.read_values.is_empty());
+
+    // transfer emits logs
+    let args = SUDTArgs::new_builder()
+        .set(
+            SUDTTransfer::new_builder()
+                .to(vec![3u8; 20].pack())
+                .amount(100u128.pack())
+                .fee(1u128.pack())
+                .build(),
+        )
+        .build();
+    let raw_tx = RawL2Transaction::new_builder()
+        .from_id(user_id.pack())
+        .to_id(CKB_SUDT_ACCOUNT_ID.pack())
+        .args(args.as_bytes().pack())
+        .build();
+    let run_result: FullRunResult = mem_pool
+        .execute_raw_transaction(raw_tx, &block_info, tip_number)
+        .unwrap()
+        .into();
+    assert!(run_result.return_data.is_empty());
+    assert!(!run_result.logs.is_empty());
+    assert!(run_result
+        .logs
+        .iter()
+        .all(|log| log.account_id.value() == CKB_SUDT_ACCOUNT_ID));
+    assert!(!run_result.write_values.is_empty());
+    assert_eq!(run_result.write_values.len(), run_result.prev_values.len());
+
+    // not committed
+    let db = chain.store().begin_transaction();
+    let state_db = mem_pool.fetch_state_db(&db).unwrap();
+    let state = state_db.account_state_tree().unwrap();
+    assert_eq!(
+        state
+            .get_sudt_balance(CKB_SUDT_ACCOUNT_ID, to_short_address(&user_script_hash))
+            .unwrap(),
+        capacity as u128
+    );
+}
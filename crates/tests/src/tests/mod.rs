@@ -1,2 +1,10 @@
+mod batch_tx_execution;
 mod chain;
+mod challenge;
+mod cycles_gas;
 mod deposit_withdrawal;
+mod double_tx_execution;
+mod mem_pool;
+mod state_journal;
+mod store_retry;
+mod trust_mode;
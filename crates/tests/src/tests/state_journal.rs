@@ -0,0 +1,60 @@
+use crate::testing_tool::chain::{apply_block, build_generator, setup_multi_tx_block};
+use gw_common::H256;
+use gw_types::prelude::*;
+
+#[test]
+fn test_dump_state_journal_on_diverged_checkpoint() {
+    let (chain, block_result) = setup_multi_tx_block();
+    let l2block = block_result.block;
+    let block_number: u64 = l2block.raw().number().unpack();
+    let (expected_root, _) = apply_block(&chain, chain.generator(), l2block.clone());
+
+    // Tamper the post checkpoint of the second transaction, no withdrawals
+    let tampered_block = {
+        let raw = l2block.raw();
+        let mut checkpoints: Vec<H256> = raw
+            .state_checkpoint_list()
+            .into_iter()
+            .map(|checkpoint| checkpoint.unpack())
+            .collect();
+        checkpoints[1] = H256::from([0xffu8; 32]);
+        let raw = raw
+            .as_builder()
+            .state_checkpoint_list(checkpoints.pack())
+            .build();
+        l2block.as_builder().raw(raw).build()
+    };
+
+    let journal_dir = tempfile::tempdir().unwrap();
+    let generator =
+        build_generator(&chain).with_state_journal_dir(Some(journal_dir.path().to_path_buf()));
+
+    // Untampered block dumps nothing
+    apply_block(&chain, &generator, l2block);
+    assert_eq!(std::fs::read_dir(journal_dir.path()).unwrap().count(), 0);
+
+    // Journal is only for diagnosis, state transition is unchanged
+    let (root, _) = apply_block(&chain, &generator, tampered_block);
+    assert_eq!(root, expected_root);
+
+    let entries: Vec<_> = std::fs::read_dir(journal_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    let file_name = format!("{}-1-state-journal.txt", block_number);
+    assert_eq!(entries, vec![std::ffi::OsString::from(file_name.clone())]);
+
+    let content = std::fs::read_to_string(journal_dir.path().join(file_name)).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(
+        lines[3],
+        format!("block_checkpoint: {}", hex::encode([0xffu8; 32]))
+    );
+    // sender balance, receiver balance, sender nonce, etc
+    assert!(lines.len() > 4);
+    for line in &lines[4..] {
+        let values: Vec<&str> = line.split(' ').collect();
+        assert_eq!(values.len(), 3);
+        assert!(values.iter().all(|value| value.len() == 64));
+    }
+}
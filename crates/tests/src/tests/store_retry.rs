@@ -0,0 +1,120 @@
+use crate::testing_tool::chain::{apply_block, setup_multi_tx_block};
+use gw_chain::chain::Chain;
+use gw_common::{error::Error as StateError, state::State, H256};
+use gw_generator::generator::{StateTransitionArgs, StateTransitionResult};
+use gw_store::{
+    chain_view::ChainView,
+    state_db::{CheckPoint, StateDBMode, StateDBTransaction, SubState, WriteContext},
+};
+use gw_traits::CodeStore;
+use gw_types::{
+    bytes::Bytes,
+    packed::{L2Block, Script},
+    prelude::*,
+};
+use std::{cell::Cell, ops::Range};
+
+// Reads in `fail_reads` range, counted from the first read, fail with a
+// store error
+struct FlakyState<'a, S> {
+    inner: &'a mut S,
+    reads: Cell<usize>,
+    fail_reads: Range<usize>,
+}
+
+impl<'a, S: State> State for FlakyState<'a, S> {
+    fn get_raw(&self, key: &H256) -> Result<H256, StateError> {
+        let read = self.reads.get();
+        self.reads.set(read + 1);
+        if self.fail_reads.contains(&read) {
+            return Err(StateError::Store);
+        }
+        self.inner.get_raw(key)
+    }
+    fn update_raw(&mut self, key: H256, value: H256) -> Result<(), StateError> {
+        self.inner.update_raw(key, value)
+    }
+    fn get_account_count(&self) -> Result<u32, StateError> {
+        self.inner.get_account_count()
+    }
+    fn set_account_count(&mut self, count: u32) -> Result<(), StateError> {
+        self.inner.set_account_count(count)
+    }
+    fn calculate_root(&self) -> Result<H256, StateError> {
+        self.inner.calculate_root()
+    }
+}
+
+impl<'a, S: CodeStore> CodeStore for FlakyState<'a, S> {
+    fn insert_script(&mut self, script_hash: H256, script: Script) {
+        self.inner.insert_script(script_hash, script)
+    }
+    fn get_script(&self, script_hash: &H256) -> Option<Script> {
+        self.inner.get_script(script_hash)
+    }
+    fn get_script_hash_by_short_address(&self, short_address: &[u8]) -> Option<H256> {
+        self.inner.get_script_hash_by_short_address(short_address)
+    }
+    fn insert_data(&mut self, data_hash: H256, code: Bytes) {
+        self.inner.insert_data(data_hash, code)
+    }
+    fn get_data(&self, data_hash: &H256) -> Option<Bytes> {
+        self.inner.get_data(data_hash)
+    }
+}
+
+fn apply_block_with_flaky_reads(
+    chain: &Chain,
+    l2block: L2Block,
+    fail_reads: Range<usize>,
+) -> (StateTransitionResult, H256) {
+    let db = chain.store().begin_transaction();
+    let tip_block_hash = db.get_tip_block_hash().unwrap();
+    let chain_view = ChainView::new(&db, tip_block_hash);
+    let state_db = StateDBTransaction::from_checkpoint(
+        &db,
+        CheckPoint::new(l2block.raw().number().unpack(), SubState::Block),
+        StateDBMode::Write(WriteContext::new(0)),
+    )
+    .unwrap();
+    let mut tree = state_db.account_state_tree().unwrap();
+    let mut state = FlakyState {
+        inner: &mut tree,
+        reads: Cell::new(0),
+        fail_reads,
+    };
+
+    let args = StateTransitionArgs {
+        l2block,
+        deposit_requests: vec![],
+        skip_signature_check: false,
+    };
+    let result = chain
+        .generator()
+        .verify_and_apply_state_transition(&chain_view, &mut state, args);
+    (result, tree.calculate_root().unwrap())
+}
+
+#[test]
+fn test_retry_transient_store_error() {
+    let (chain, block_result) = setup_multi_tx_block();
+    let l2block = block_result.block;
+    let (expected_root, _) = apply_block(&chain, chain.generator(), l2block.clone());
+
+    // The first read fails once, then succeeds
+    let (result, root) = apply_block_with_flaky_reads(&chain, l2block.clone(), 0..1);
+    assert!(matches!(result, StateTransitionResult::Success { .. }));
+    assert_eq!(root, expected_root);
+
+    // Store keeps failing, report error instead of challenging the block
+    let (result, _) = apply_block_with_flaky_reads(&chain, l2block, 0..usize::MAX);
+    match result {
+        StateTransitionResult::Error(err) => assert!(
+            matches!(err, gw_generator::error::Error::State(StateError::Store)),
+            "unexpected error {:?}",
+            err
+        ),
+        StateTransitionResult::Challenge { error, .. } => panic!("challenge {}", error),
+        StateTransitionResult::Success { .. } => panic!("unexpected success"),
+    }
+}
@@ -0,0 +1,101 @@
+use crate::testing_tool::chain::{
+    apply_block, build_backend_manage, build_submit_block_action,
+    setup_chain_with_account_lock_manage, setup_multi_tx_block, setup_multi_tx_block_on,
+    try_apply_block, ALWAYS_SUCCESS_CODE_HASH,
+};
+use gw_chain::chain::{SyncEvent, SyncParam};
+use gw_generator::{
+    account_lock_manage::{always_success::AlwaysSuccess, AccountLockManage},
+    generator::StateTransitionResult,
+    Generator,
+};
+use gw_types::{
+    core::ChallengeTargetType,
+    packed::{CellOutput, RollupConfig, Script},
+    prelude::*,
+};
+
+#[test]
+fn test_skip_signature_check() {
+    let (chain, block_result) = setup_multi_tx_block();
+    let l2block = block_result.block;
+    // No lock algorithm registered, every signature check fails
+    let rollup_context = chain.generator().rollup_context().clone();
+    let generator = Generator::new(
+        build_backend_manage(&rollup_context.rollup_config),
+        AccountLockManage::default(),
+        rollup_context,
+    );
+
+    let (result, _) = try_apply_block(&chain, &generator, l2block.clone(), false);
+    assert!(matches!(result, StateTransitionResult::Challenge { .. }));
+
+    let (result, root) = try_apply_block(&chain, &generator, l2block.clone(), true);
+    assert!(matches!(result, StateTransitionResult::Success { .. }));
+    let (expected_root, _) = apply_block(&chain, chain.generator(), l2block);
+    assert_eq!(root, expected_root);
+}
+
+// Sync the multi tx block to a chain which fails every signature check
+fn sync_multi_tx_block(finality_blocks: u64, trust_mode: bool) -> SyncEvent {
+    let rollup_type_script = Script::default();
+    let rollup_config = RollupConfig::new_builder()
+        .allowed_eoa_type_hashes(vec![ALWAYS_SUCCESS_CODE_HASH.clone()].pack())
+        .finality_blocks(finality_blocks.pack())
+        .build();
+
+    let producer = {
+        let mut account_lock_manage = AccountLockManage::default();
+        account_lock_manage.register_lock_algorithm(
+            ALWAYS_SUCCESS_CODE_HASH.clone().into(),
+            Box::new(AlwaysSuccess),
+        );
+        setup_chain_with_account_lock_manage(
+            rollup_type_script.clone(),
+            rollup_config.clone(),
+            account_lock_manage,
+        )
+    };
+    let (_producer, deposit_action, block_result) = setup_multi_tx_block_on(producer);
+
+    let mut chain = setup_chain_with_account_lock_manage(
+        rollup_type_script.clone(),
+        rollup_config,
+        AccountLockManage::default(),
+    )
+    .with_trust_mode(trust_mode);
+    let param = SyncParam {
+        updates: vec![deposit_action],
+        reverts: Default::default(),
+    };
+    chain.sync(param).unwrap();
+    assert!(chain.last_sync_event().is_success());
+
+    let rollup_cell = CellOutput::new_builder()
+        .type_(Some(rollup_type_script).pack())
+        .build();
+    let param = SyncParam {
+        updates: vec![build_submit_block_action(rollup_cell, block_result, vec![])],
+        reverts: Default::default(),
+    };
+    chain.sync(param).unwrap();
+    chain.last_sync_event().to_owned()
+}
+
+#[test]
+fn test_trust_mode_skip_signature_check_of_finalized_block() {
+    let assert_bad_signature = |event: SyncEvent| match event {
+        SyncEvent::BadBlock { context } => {
+            let target_type: u8 = context.target.target_type().into();
+            assert_eq!(target_type, ChallengeTargetType::TxSignature as u8);
+        }
+        event => panic!("unexpected sync event {:?}", event),
+    };
+
+    // Block is finalized on sync without finality blocks
+    assert!(sync_multi_tx_block(0, true).is_success());
+    assert_bad_signature(sync_multi_tx_block(0, false));
+
+    // Block isn't finalized yet, signatures are checked in trust mode too
+    assert_bad_signature(sync_multi_tx_block(1, true));
+}
@@ -26,7 +26,7 @@ use ckb_types::{
     prelude::Unpack as CKBUnpack,
 };
 use gw_config::GenesisConfig;
-use gw_generator::genesis::build_genesis;
+use gw_generator::genesis::{build_genesis, validate_rollup_config};
 use gw_types::{
     packed as gw_packed, packed::RollupConfig, prelude::Entity as GwEntity,
     prelude::Pack as GwPack, prelude::PackVec as GwPackVec,
@@ -433,6 +433,7 @@ pub fn deploy_genesis(
         rollup_config: rollup_config.clone().into(),
         secp_data_dep,
     };
+    validate_rollup_config(&rollup_config).map_err(|err| err.to_string())?;
     let genesis_with_global_state =
         build_genesis(&genesis_config, secp_data).map_err(|err| err.to_string())?;
 
@@ -5,8 +5,10 @@ use anyhow::{anyhow, Result};
 use ckb_sdk::HttpRpcClient;
 use ckb_types::prelude::{Builder, Entity};
 use gw_config::{
-    BackendConfig, BlockProducerConfig, ChainConfig, ChallengerConfig, Config, GenesisConfig,
-    NodeMode, RPCClientConfig, RPCServerConfig, StoreConfig, WalletConfig, Web3IndexerConfig,
+    BackendConfig, BlockProducerConfig, ChainConfig, ChallengerConfig, Config, DumpFormat,
+    FeeConfig, GenesisConfig, NodeMode, RPCClientConfig, RPCServerConfig, StoreConfig,
+    WalletConfig, Web3IndexerConfig, DEFAULT_DEBUG_TX_DUMP_MAX_FILES,
+    DEFAULT_EVENT_CHANNEL_CAPACITY, DEFAULT_POLL_INTERVAL_MS,
 };
 use gw_jsonrpc_types::godwoken::L2BlockCommittedInfo;
 use gw_types::{core::ScriptHashType, packed::Script, prelude::*};
@@ -209,6 +211,7 @@ pub fn generate_config(
     let challenger_config = ChallengerConfig {
         rewards_receiver_lock: gw_types::packed::Script::default().into(),
         burn_lock: gw_types::packed::Script::default().into(),
+        skip_trusted_checkpoint_checks: false,
     };
 
     let wallet_config: WalletConfig = WalletConfig {
@@ -245,12 +248,24 @@ pub fn generate_config(
     let chain: ChainConfig = ChainConfig {
         genesis_committed_info,
         rollup_type_script,
+        batch_tx_execution: false,
+        double_tx_execution: false,
+        max_safe_reorg_depth: None,
+        clear_unsafe_reorg: false,
+        trust_mode: false,
+        state_journal_dir: None,
+        cycles_per_gas: None,
     };
     let rpc_client: RPCClientConfig = RPCClientConfig {
         indexer_url,
         ckb_url,
+        poll_interval_ms: DEFAULT_POLL_INTERVAL_MS,
+        event_channel_capacity: DEFAULT_EVENT_CHANNEL_CAPACITY,
+    };
+    let rpc_server = RPCServerConfig {
+        listen: server_url,
+        max_return_data_size: None,
     };
-    let rpc_server = RPCServerConfig { listen: server_url };
     let block_producer: Option<BlockProducerConfig> = Some(BlockProducerConfig {
         account_id,
         // cell deps
@@ -269,6 +284,13 @@ pub fn generate_config(
         challenger_config,
         wallet_config,
         debug_tx_dump_path: "debug-tx-dump".into(),
+        debug_tx_dump_max_files: DEFAULT_DEBUG_TX_DUMP_MAX_FILES,
+        debug_tx_dump_formats: vec![DumpFormat::Json],
+        payment_cells_config: Default::default(),
+        submission_confirmation_depth: 0,
+        dry_run_before_submit: false,
+        deposit_order: Default::default(),
+        idle_block_mode: Default::default(),
     });
     let genesis: GenesisConfig = GenesisConfig {
         timestamp: genesis.timestamp,
@@ -287,6 +309,8 @@ pub fn generate_config(
             database_url: database_url.to_owned(),
             polyjuice_script_type_hash: scripts_results.polyjuice_validator.script_type_hash,
             eth_account_lock_hash: eth_account_lock_hash.to_owned(),
+            store_raw_input: false,
+            lookup_concurrency: 0,
         }),
         None => None,
     };
@@ -301,6 +325,7 @@ pub fn generate_config(
         block_producer,
         web3_indexer,
         node_mode: NodeMode::ReadOnly,
+        fee: FeeConfig::default(),
     };
 
     let output_content = toml::to_string_pretty(&config).expect("serde toml to string pretty");
@@ -148,3 +148,36 @@ impl Into<packed::Byte> for DepType {
         (self as u8).into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenge_target_type_round_trip() {
+        let mut count = 0;
+        for v in 0..=u8::max_value() {
+            let target_type = match ChallengeTargetType::try_from(v) {
+                Ok(target_type) => target_type,
+                Err(n) => {
+                    assert_eq!(n, v);
+                    assert_eq!(ChallengeTargetType::try_from(Byte::new(v)), Err(v));
+                    continue;
+                }
+            };
+            count += 1;
+
+            // No catch-all arm, a new variant must be covered here
+            let expected = match target_type {
+                ChallengeTargetType::TxExecution => 0,
+                ChallengeTargetType::TxSignature => 1,
+                ChallengeTargetType::Withdrawal => 2,
+            };
+            assert_eq!(Into::<u8>::into(target_type), expected);
+
+            let byte: Byte = target_type.into();
+            assert_eq!(ChallengeTargetType::try_from(byte), Ok(target_type));
+        }
+        assert_eq!(count, 3);
+    }
+}
@@ -95,6 +95,8 @@ impl packed::RawL2Block {
 }
 
 impl packed::L2Block {
+    /// Block hash is the hash of raw block, which is checked by the layer1
+    /// scripts, all modules must use it
     pub fn hash(&self) -> [u8; 32] {
         self.raw().hash()
     }
@@ -143,3 +145,32 @@ impl packed::RollupConfig {
         self.as_reader().hash()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::packed;
+    use crate::prelude::*;
+
+    // Pinned hashes, update them only if the layer1 scripts change the
+    // block hash
+    const DEFAULT_RAW_BLOCK_HASH: &str =
+        "3e4a1fb201ecb06190c5478a0c448d5f8e29db800a6b9175445991879417b61f";
+    const RAW_BLOCK_1_HASH: &str =
+        "092d0892d3c3a554c12022ce47a5841d84e7be3e9cbc4bcc9316c711f17fef94";
+
+    fn to_hex(hash: [u8; 32]) -> String {
+        hash.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_pinned_l2_block_hash() {
+        let raw_block = packed::RawL2Block::default();
+        assert_eq!(to_hex(raw_block.hash()), DEFAULT_RAW_BLOCK_HASH);
+
+        let raw_block = raw_block.as_builder().number(1u64.pack()).build();
+        assert_eq!(to_hex(raw_block.hash()), RAW_BLOCK_1_HASH);
+
+        let block = packed::L2Block::new_builder().raw(raw_block).build();
+        assert_eq!(to_hex(block.hash()), RAW_BLOCK_1_HASH);
+    }
+}
@@ -6,12 +6,20 @@ use std::collections::HashMap;
 pub struct RunResult {
     pub read_values: HashMap<H256, H256>,
     pub write_values: HashMap<H256, H256>,
+    // values of written keys before execution
+    pub prev_values: HashMap<H256, H256>,
     pub return_data: Vec<u8>,
     pub account_count: Option<u32>,
+    pub prev_account_count: Option<u32>,
     pub new_scripts: HashMap<H256, Vec<u8>>,
     pub write_data: HashMap<H256, Vec<u8>>,
     // data hash -> data full size
     pub read_data: HashMap<H256, usize>,
     // log data
     pub logs: Vec<LogItem>,
+    // executed cycles, only metered if the generator derives gas from cycles
+    pub used_cycles: u64,
+    // gas of executed cycles under the generator's cycles per gas, fee is
+    // still charged by the gas used in polyjuice system log
+    pub cycles_gas_used: Option<u64>,
 }
@@ -0,0 +1,17 @@
+-- Add migration script here
+ALTER TABLE logs
+    ADD COLUMN topic0 TEXT,
+    ADD COLUMN topic1 TEXT,
+    ADD COLUMN topic2 TEXT,
+    ADD COLUMN topic3 TEXT;
+
+UPDATE logs SET
+    topic0 = topics[1],
+    topic1 = topics[2],
+    topic2 = topics[3],
+    topic3 = topics[4];
+
+CREATE INDEX ON logs (topic0);
+CREATE INDEX ON logs (topic1);
+CREATE INDEX ON logs (topic2);
+CREATE INDEX ON logs (topic3);
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE dead_letters (
+    id SERIAL PRIMARY KEY,
+    block_number NUMERIC NOT NULL,
+    transaction_hash TEXT,
+    field TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+
+CREATE INDEX ON dead_letters (block_number);
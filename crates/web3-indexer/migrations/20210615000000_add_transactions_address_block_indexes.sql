@@ -0,0 +1,3 @@
+-- Add migration script here
+CREATE INDEX transactions_from_address_block_idx ON transactions (from_address, block_number DESC, transaction_index DESC);
+CREATE INDEX transactions_to_address_block_idx ON transactions (to_address, block_number DESC, transaction_index DESC);
@@ -0,0 +1,130 @@
+//! Ethereum logs bloom, 2048 bits set by 3 keccak256 derived indexes per
+//! log address and topic
+
+use crate::hash::keccak256;
+use crate::types::Log;
+
+pub const BLOOM_BYTE_LENGTH: usize = 256;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Bloom([u8; BLOOM_BYTE_LENGTH]);
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom([0u8; BLOOM_BYTE_LENGTH])
+    }
+}
+
+impl std::fmt::Debug for Bloom {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Bloom({})", faster_hex::hex_string(&self.0).unwrap())
+    }
+}
+
+// Bit positions of input, the low 11 bits of the first three big endian
+// u16 of its hash. Bit 0 is the lowest bit of the last byte.
+fn bit_positions(input: &[u8]) -> [(usize, u8); 3] {
+    let hash = keccak256(input);
+    let mut positions = [(0, 0); 3];
+    for (i, position) in positions.iter_mut().enumerate() {
+        let bit = ((hash[2 * i] as usize) << 8 | hash[2 * i + 1] as usize) & 2047;
+        *position = (BLOOM_BYTE_LENGTH - 1 - bit / 8, 1 << (bit % 8));
+    }
+    positions
+}
+
+impl Bloom {
+    pub fn accrue(&mut self, input: &[u8]) {
+        for (byte, mask) in bit_positions(input).iter() {
+            self.0[*byte] |= mask;
+        }
+    }
+
+    pub fn accrue_log(&mut self, log: &Log) {
+        self.accrue(&log.address);
+        for topic in log.topics.iter() {
+            self.accrue(topic.as_slice());
+        }
+    }
+
+    pub fn accrue_bloom(&mut self, other: &Bloom) {
+        for (byte, other_byte) in self.0.iter_mut().zip(other.0.iter()) {
+            *byte |= other_byte;
+        }
+    }
+
+    /// False positives are possible, false negatives are not
+    pub fn contains_input(&self, input: &[u8]) -> bool {
+        bit_positions(input)
+            .iter()
+            .all(|(byte, mask)| self.0[*byte] & mask == *mask)
+    }
+
+    pub fn from_logs<'a>(logs: impl IntoIterator<Item = &'a Log>) -> Self {
+        let mut bloom = Bloom::default();
+        for log in logs {
+            bloom.accrue_log(log);
+        }
+        bloom
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_common::H256;
+
+    fn log(address: [u8; 20], topics: Vec<H256>) -> Log {
+        Log::new(H256::zero(), 0, 1, H256::zero(), address, vec![], 0, topics)
+    }
+
+    // go-ethereum core/types/bloom9_test.go
+    #[test]
+    fn test_bloom_reference_vectors() {
+        let mut bloom = Bloom::default();
+        for input in &["testtest", "test", "hallo", "other"] {
+            bloom.accrue(input.as_bytes());
+        }
+        for input in &["testtest", "test", "hallo", "other"] {
+            assert!(bloom.contains_input(input.as_bytes()));
+        }
+        for input in &["tes", "lo"] {
+            assert!(!bloom.contains_input(input.as_bytes()));
+        }
+
+        let mut bloom = Bloom::default();
+        for i in 0..100 {
+            bloom.accrue(format!("xxxxxxxxxx data {} yyyyyyyyyyyyyy", i).as_bytes());
+        }
+        assert_eq!(
+            faster_hex::hex_string(&keccak256(bloom.as_bytes())).unwrap(),
+            "c8d3ca65cdb4874300a9e39475508f23ed6da09fdbc487f89a2dcf50b09eb263"
+        );
+    }
+
+    #[test]
+    fn test_logs_bloom() {
+        assert_eq!(
+            Bloom::from_logs(&[]).as_bytes(),
+            &[0u8; BLOOM_BYTE_LENGTH][..]
+        );
+
+        let topic = H256::from([2u8; 32]);
+        let first_log = || log([1u8; 20], vec![topic]);
+        let second_log = || log([3u8; 20], vec![]);
+        let bloom = Bloom::from_logs(&[first_log(), second_log()]);
+        assert!(bloom.contains_input(&[1u8; 20]));
+        assert!(bloom.contains_input(topic.as_slice()));
+        assert!(bloom.contains_input(&[3u8; 20]));
+        assert!(!bloom.contains_input(&[4u8; 20]));
+
+        // block bloom is the union of its transaction blooms
+        let mut block_bloom = Bloom::from_logs(&[first_log()]);
+        block_bloom.accrue_bloom(&Bloom::from_logs(&[second_log()]));
+        assert_eq!(block_bloom, bloom);
+    }
+}
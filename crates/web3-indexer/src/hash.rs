@@ -0,0 +1,46 @@
+//! Hash functions used by the web3 indexer
+//!
+//! Godwoken native values (block hash, tx hash, script hash) are blake2b,
+//! ethereum compatible values (eth tx hash) must be keccak256 to match the
+//! values computed by eth tooling.
+
+use sha3::{Digest, Keccak256};
+
+/// Godwoken blake2b, with the CKB personalization
+pub fn blake2b_256<T: AsRef<[u8]>>(data: T) -> [u8; 32] {
+    ckb_hash::blake2b_256(data)
+}
+
+/// Ethereum keccak256
+pub fn keccak256<T: AsRef<[u8]>>(data: T) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(data.as_ref());
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(&hasher.finalize());
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blake2b_256() {
+        assert_eq!(
+            faster_hex::hex_string(&blake2b_256(b"")).unwrap(),
+            "44f4c69744d5f8c55d642062949dcae49bc4e7ef43d388c5a12f42b5633d163e"
+        );
+    }
+
+    #[test]
+    fn test_keccak256() {
+        assert_eq!(
+            faster_hex::hex_string(&keccak256(b"")).unwrap(),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            faster_hex::hex_string(&keccak256(b"abc")).unwrap(),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+}
@@ -1,13 +1,28 @@
+use crate::types::Log as Web3Log;
 use anyhow::{anyhow, Result};
 use gw_common::H256;
+use gw_types::offchain::RunResult;
 use gw_types::packed::LogItem;
 use gw_types::prelude::*;
-use std::{convert::TryInto, usize};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use sqlx::types::chrono::{naive::MAX_DATETIME, DateTime, NaiveDateTime, Utc};
+use std::{
+    convert::{TryFrom, TryInto},
+    usize,
+};
+use thiserror::Error;
+
+const MILLIS_PER_SEC: u64 = 1_000;
 
 pub const GW_LOG_SUDT_TRANSFER: u8 = 0x0;
 pub const GW_LOG_SUDT_PAY_FEE: u8 = 0x1;
 pub const GW_LOG_POLYJUICE_SYSTEM: u8 = 0x2;
 pub const GW_LOG_POLYJUICE_USER: u8 = 0x3;
+/// keccak256("Transfer(address,address,uint256)")
+pub const ERC20_TRANSFER_TOPIC: [u8; 32] = [
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+];
 #[derive(Default, Debug)]
 pub struct PolyjuiceArgs {
     pub is_create: bool,
@@ -70,31 +85,111 @@ pub enum GwLog {
     },
 }
 
-fn parse_sudt_log_data(data: &[u8]) -> ([u8; 20], [u8; 20], u128) {
-    assert_eq!(data[0], 20);
-    let mut from_address = [0u8; 20];
-    from_address.copy_from_slice(&data[1..21]);
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum LogParseError {
+    #[error("invalid log data length: {len}, expected: {expected}")]
+    InvalidDataLength { len: usize, expected: usize },
+    #[error("invalid address length: {0}")]
+    InvalidAddressLength(u8),
+    #[error("truncated log data: offset={offset}, data.len()={len}")]
+    Truncated { offset: usize, len: usize },
+    #[error("too many bytes for log data: offset={offset}, data.len()={len}")]
+    TrailingBytes { offset: usize, len: usize },
+    #[error("invalid log service flag: {0}")]
+    InvalidServiceFlag(u8),
+}
 
-    let mut to_address = [0u8; 20];
-    to_address.copy_from_slice(&data[21..41]);
+/// Bounds checked reader of log data
+struct LogDataReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> LogDataReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        LogDataReader { data, offset: 0 }
+    }
+
+    fn read(&mut self, len: usize) -> Result<&'a [u8], LogParseError> {
+        let offset = self.offset;
+        let bytes = offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or(LogParseError::Truncated {
+                offset,
+                len: self.data.len(),
+            })?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, LogParseError> {
+        Ok(self.read(1)?[0])
+    }
+
+    fn read_u32(&mut self) -> Result<u32, LogParseError> {
+        Ok(u32::from_le_bytes(self.read(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, LogParseError> {
+        Ok(u64::from_le_bytes(self.read(8)?.try_into().unwrap()))
+    }
+
+    fn read_u128(&mut self) -> Result<u128, LogParseError> {
+        Ok(u128::from_le_bytes(self.read(16)?.try_into().unwrap()))
+    }
+
+    fn read_address(&mut self) -> Result<[u8; 20], LogParseError> {
+        Ok(self.read(20)?.try_into().unwrap())
+    }
 
-    let mut u128_bytes = [0u8; 16];
-    u128_bytes.copy_from_slice(&data[41..57]);
-    let amount = u128::from_le_bytes(u128_bytes);
-    (from_address, to_address, amount)
+    fn read_h256(&mut self) -> Result<[u8; 32], LogParseError> {
+        Ok(self.read(32)?.try_into().unwrap())
+    }
+
+    fn finish(self) -> Result<(), LogParseError> {
+        if self.offset != self.data.len() {
+            return Err(LogParseError::TrailingBytes {
+                offset: self.offset,
+                len: self.data.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn check_data_length(data: &[u8], expected: usize) -> Result<(), LogParseError> {
+    if data.len() != expected {
+        return Err(LogParseError::InvalidDataLength {
+            len: data.len(),
+            expected,
+        });
+    }
+    Ok(())
+}
+
+fn parse_sudt_log_data(data: &[u8]) -> Result<([u8; 20], [u8; 20], u128), LogParseError> {
+    check_data_length(data, 1 + 20 + 20 + 16)?;
+    let mut reader = LogDataReader::new(data);
+    let address_len = reader.read_u8()?;
+    if address_len != 20 {
+        return Err(LogParseError::InvalidAddressLength(address_len));
+    }
+    let from_address = reader.read_address()?;
+    let to_address = reader.read_address()?;
+    let amount = reader.read_u128()?;
+    reader.finish()?;
+    Ok((from_address, to_address, amount))
 }
 
-pub fn parse_log(item: &LogItem) -> Result<GwLog> {
+pub fn parse_log(item: &LogItem) -> Result<GwLog, LogParseError> {
     let service_flag: u8 = item.service_flag().into();
     let raw_data = item.data().raw_data();
     let data = raw_data.as_ref();
     match service_flag {
         GW_LOG_SUDT_TRANSFER => {
             let sudt_id: u32 = item.account_id().unpack();
-            if data.len() != (1 + 20 + 20 + 16) {
-                return Err(anyhow!("Invalid data length: {}", data.len()));
-            }
-            let (from_address, to_address, amount) = parse_sudt_log_data(data);
+            let (from_address, to_address, amount) = parse_sudt_log_data(data)?;
             Ok(GwLog::SudtTransfer {
                 sudt_id,
                 from_address,
@@ -104,10 +199,7 @@ pub fn parse_log(item: &LogItem) -> Result<GwLog> {
         }
         GW_LOG_SUDT_PAY_FEE => {
             let sudt_id: u32 = item.account_id().unpack();
-            if data.len() != (1 + 20 + 20 + 16) {
-                return Err(anyhow!("Invalid data length: {}", data.len()));
-            }
-            let (from_address, block_producer_address, amount) = parse_sudt_log_data(data);
+            let (from_address, block_producer_address, amount) = parse_sudt_log_data(data)?;
             Ok(GwLog::SudtPayFee {
                 sudt_id,
                 from_address,
@@ -116,27 +208,13 @@ pub fn parse_log(item: &LogItem) -> Result<GwLog> {
             })
         }
         GW_LOG_POLYJUICE_SYSTEM => {
-            if data.len() != (8 + 8 + 20 + 4) {
-                return Err(anyhow!(
-                    "invalid system log raw data length: {}",
-                    data.len()
-                ));
-            }
-
-            let mut u64_bytes = [0u8; 8];
-            u64_bytes.copy_from_slice(&data[0..8]);
-            let gas_used = u64::from_le_bytes(u64_bytes);
-            u64_bytes.copy_from_slice(&data[8..16]);
-            let cumulative_gas_used = u64::from_le_bytes(u64_bytes);
-
-            let created_address = {
-                let mut buf = [0u8; 20];
-                buf.copy_from_slice(&data[16..36]);
-                buf
-            };
-            let mut u32_bytes = [0u8; 4];
-            u32_bytes.copy_from_slice(&data[36..40]);
-            let status_code = u32::from_le_bytes(u32_bytes);
+            check_data_length(data, 8 + 8 + 20 + 4)?;
+            let mut reader = LogDataReader::new(data);
+            let gas_used = reader.read_u64()?;
+            let cumulative_gas_used = reader.read_u64()?;
+            let created_address = reader.read_address()?;
+            let status_code = reader.read_u32()?;
+            reader.finish()?;
             Ok(GwLog::PolyjuiceSystem {
                 gas_used,
                 cumulative_gas_used,
@@ -145,48 +223,527 @@ pub fn parse_log(item: &LogItem) -> Result<GwLog> {
             })
         }
         GW_LOG_POLYJUICE_USER => {
-            let mut offset: usize = 0;
-            let mut address = [0u8; 20];
-            address.copy_from_slice(&data[offset..offset + 20]);
-            offset += 20;
-            let mut data_size_bytes = [0u8; 4];
-            data_size_bytes.copy_from_slice(&data[offset..offset + 4]);
-            offset += 4;
-            let data_size: u32 = u32::from_le_bytes(data_size_bytes);
-            let mut log_data = vec![0u8; data_size as usize];
-            log_data.copy_from_slice(&data[offset..offset + (data_size as usize)]);
-            offset += data_size as usize;
-            log::debug!("data_size: {}", data_size);
-
-            let mut topics_count_bytes = [0u8; 4];
-            topics_count_bytes.copy_from_slice(&data[offset..offset + 4]);
-            offset += 4;
-            let topics_count: u32 = u32::from_le_bytes(topics_count_bytes);
+            let mut reader = LogDataReader::new(data);
+            let address = reader.read_address()?;
+            let data_size = reader.read_u32()?;
+            log::trace!("data_size: {}", data_size);
+            let log_data = reader.read(data_size as usize)?.to_vec();
+
+            let topics_count = reader.read_u32()?;
+            log::trace!("topics_count: {}", topics_count);
             let mut topics = Vec::new();
-            log::debug!("topics_count: {}", topics_count);
             for _ in 0..topics_count {
-                let mut topic = [0u8; 32];
-                topic.copy_from_slice(&data[offset..offset + 32]);
-                offset += 32;
+                let topic = reader.read_h256()?;
                 topics.push(topic.into());
             }
-            if offset != data.len() {
-                return Err(anyhow!(
-                    "Too many bytes for polyjuice user log data: offset={}, data.len()={}",
-                    offset,
-                    data.len()
-                ));
-            }
+            reader.finish()?;
             Ok(GwLog::PolyjuiceUser {
                 address,
                 data: log_data,
                 topics,
             })
         }
-        _ => Err(anyhow!("invalid log service flag: {}", service_flag)),
+        _ => Err(LogParseError::InvalidServiceFlag(service_flag)),
+    }
+}
+
+/// Transaction which the logs are emitted by
+#[derive(Debug, Clone, Copy)]
+pub struct LogContext {
+    pub tx_hash: H256,
+    pub tx_index: u32,
+    pub block_number: u64,
+    pub block_hash: H256,
+}
+
+/// Eth addresses of accounts in sudt transfer logs
+pub trait EthAddressResolver {
+    /// Address of sudt account, emitter of the transfer log
+    fn sudt_address(&self, sudt_id: u32) -> Result<Option<[u8; 20]>>;
+    /// Address of transfer sender or receiver
+    fn account_address(&self, short_address: &[u8; 20]) -> Result<Option<[u8; 20]>>;
+}
+
+// ERC20 Transfer event, from and to are indexed
+fn to_erc20_transfer_topics(from: &[u8; 20], to: &[u8; 20]) -> Vec<H256> {
+    let to_topic = |address: &[u8; 20]| {
+        let mut buf = [0u8; 32];
+        buf[12..].copy_from_slice(address);
+        H256::from(buf)
+    };
+    vec![ERC20_TRANSFER_TOPIC.into(), to_topic(from), to_topic(to)]
+}
+
+// uint256 big endian
+fn to_erc20_transfer_data(amount: u128) -> Vec<u8> {
+    let mut data = vec![0u8; 32];
+    data[16..].copy_from_slice(&amount.to_be_bytes());
+    data
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolyjuiceTxResult {
+    pub gas_used: u64,
+    pub created_address: [u8; 20],
+    /// Exit code of polyjuice is 0
+    pub status: bool,
+}
+
+/// Read polyjuice transaction result from its system log, successful
+/// without gas used or contract created if there isn't one
+pub fn polyjuice_tx_result(log_items: &[LogItem]) -> Result<PolyjuiceTxResult> {
+    let system_log = log_items
+        .iter()
+        .find(|item| u8::from(item.service_flag()) == GW_LOG_POLYJUICE_SYSTEM);
+    match system_log.map(parse_log).transpose()? {
+        Some(GwLog::PolyjuiceSystem {
+            gas_used,
+            cumulative_gas_used: _,
+            created_address,
+            status_code,
+        }) => Ok(PolyjuiceTxResult {
+            gas_used,
+            created_address,
+            status: status_code == 0,
+        }),
+        _ => Ok(PolyjuiceTxResult {
+            gas_used: 0,
+            created_address: [0u8; 20],
+            status: true,
+        }),
+    }
+}
+
+/// Convert gw logs to web3 logs, polyjuice user logs and sudt transfer logs
+/// (as ERC20 Transfer events) are kept, malformed logs and transfers of
+/// unknown accounts are skipped
+pub fn to_web3_logs<I: IntoIterator<Item = LogItem>, R: EthAddressResolver>(
+    log_items: I,
+    context: &LogContext,
+    resolver: &R,
+) -> Result<Vec<Web3Log>> {
+    let mut logs = vec![];
+    let mut log_index = 0;
+    for log_item in log_items {
+        let log = match parse_log(&log_item) {
+            Ok(log) => log,
+            Err(err) => {
+                log::warn!(
+                    "skip malformed log of tx {}: {}",
+                    hex(context.tx_hash.as_slice()).unwrap_or_default(),
+                    err
+                );
+                continue;
+            }
+        };
+        match log {
+            GwLog::PolyjuiceSystem { .. } => {
+                // handled by transaction
+            }
+            GwLog::PolyjuiceUser {
+                address,
+                data,
+                topics,
+            } => {
+                logs.push(Web3Log::new(
+                    context.tx_hash,
+                    context.tx_index,
+                    context.block_number,
+                    context.block_hash,
+                    address,
+                    data,
+                    log_index,
+                    topics,
+                ));
+                log_index += 1;
+            }
+            GwLog::SudtTransfer {
+                sudt_id,
+                from_address,
+                to_address,
+                amount,
+            } => {
+                let addresses = (
+                    resolver.sudt_address(sudt_id)?,
+                    resolver.account_address(&from_address)?,
+                    resolver.account_address(&to_address)?,
+                );
+                let (address, from, to) = match addresses {
+                    (Some(address), Some(from), Some(to)) => (address, from, to),
+                    _ => {
+                        log::warn!(
+                            "skip sudt transfer log of unknown account in tx {}",
+                            hex(context.tx_hash.as_slice()).unwrap_or_default(),
+                        );
+                        continue;
+                    }
+                };
+                logs.push(Web3Log::new(
+                    context.tx_hash,
+                    context.tx_index,
+                    context.block_number,
+                    context.block_hash,
+                    address,
+                    to_erc20_transfer_data(amount),
+                    log_index,
+                    to_erc20_transfer_topics(&from, &to),
+                ));
+                log_index += 1;
+            }
+            // Fee is paid to block producer, not a transfer of the transaction
+            GwLog::SudtPayFee { .. } => {}
+        }
     }
+    Ok(logs)
+}
+
+/// Web3 logs of a fresh execution, same as the ones indexed once the
+/// transaction is committed at `context`
+pub fn run_result_to_web3_logs<R: EthAddressResolver>(
+    run_result: &RunResult,
+    context: &LogContext,
+    resolver: &R,
+) -> Result<Vec<Web3Log>> {
+    to_web3_logs(run_result.logs.iter().cloned(), context, resolver)
 }
 
 pub fn hex(raw: &[u8]) -> Result<String> {
     Ok(format!("0x{}", faster_hex::hex_string(raw)?))
 }
+
+pub fn unhex(hex_str: &str) -> Result<Vec<u8>> {
+    let raw = hex_str
+        .strip_prefix("0x")
+        .ok_or_else(|| anyhow!("hex string without 0x prefix: {}", hex_str))?
+        .as_bytes();
+    let mut buf = vec![0u8; raw.len() / 2];
+    faster_hex::hex_decode(raw, &mut buf)?;
+    Ok(buf)
+}
+
+/// Field value which doesn't fit in `Decimal`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OverflowedValue {
+    pub field: &'static str,
+    pub value: u128,
+}
+
+/// Convert value to `Decimal` for NUMERIC columns, values out of range are
+/// capped to `Decimal::MAX` and recorded to `overflowed`
+pub fn to_capped_decimal(
+    field: &'static str,
+    value: u128,
+    overflowed: &mut Vec<OverflowedValue>,
+) -> Decimal {
+    match Decimal::from_u128(value) {
+        Some(decimal) => decimal,
+        None => {
+            log::warn!(
+                "web3 indexer: {} {} exceeds decimal range, capped",
+                field,
+                value
+            );
+            overflowed.push(OverflowedValue { field, value });
+            Decimal::MAX
+        }
+    }
+}
+
+/// Convert block timestamp in milliseconds, timestamps out of range are
+/// clamped to the max datetime
+pub fn to_block_timestamp(epoch_time_as_millis: u64) -> DateTime<Utc> {
+    let secs = epoch_time_as_millis / MILLIS_PER_SEC;
+    let timestamp = i64::try_from(secs)
+        .ok()
+        .and_then(|secs| NaiveDateTime::from_timestamp_opt(secs, 0))
+        .unwrap_or_else(|| {
+            log::warn!(
+                "web3 indexer: block timestamp {} out of range, clamped",
+                epoch_time_as_millis
+            );
+            MAX_DATETIME
+        });
+    DateTime::<Utc>::from_utc(timestamp, Utc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_types::bytes::Bytes;
+    use gw_types::packed::TxReceipt;
+    use std::collections::HashMap;
+
+    fn log_item(service_flag: u8, data: Vec<u8>) -> LogItem {
+        LogItem::new_builder()
+            .service_flag(service_flag.into())
+            .data(Bytes::from(data).pack())
+            .build()
+    }
+
+    #[derive(Default)]
+    struct MockResolver {
+        sudts: HashMap<u32, [u8; 20]>,
+        accounts: HashMap<[u8; 20], [u8; 20]>,
+    }
+
+    impl EthAddressResolver for MockResolver {
+        fn sudt_address(&self, sudt_id: u32) -> Result<Option<[u8; 20]>> {
+            Ok(self.sudts.get(&sudt_id).cloned())
+        }
+
+        fn account_address(&self, short_address: &[u8; 20]) -> Result<Option<[u8; 20]>> {
+            Ok(self.accounts.get(short_address).cloned())
+        }
+    }
+
+    fn polyjuice_system_log(gas_used: u64, status_code: u32) -> LogItem {
+        let mut data = vec![];
+        data.extend_from_slice(&gas_used.to_le_bytes());
+        data.extend_from_slice(&gas_used.to_le_bytes());
+        data.extend_from_slice(&[1u8; 20]);
+        data.extend_from_slice(&status_code.to_le_bytes());
+        log_item(GW_LOG_POLYJUICE_SYSTEM, data)
+    }
+
+    #[test]
+    fn test_polyjuice_tx_result() {
+        let user_log = log_item(GW_LOG_POLYJUICE_USER, vec![0u8; 24]);
+
+        let result =
+            polyjuice_tx_result(&[user_log.clone(), polyjuice_system_log(21000, 0)]).unwrap();
+        assert_eq!(
+            result,
+            PolyjuiceTxResult {
+                gas_used: 21000,
+                created_address: [1u8; 20],
+                status: true,
+            }
+        );
+
+        // Reverted
+        let result = polyjuice_tx_result(&[polyjuice_system_log(21000, 2)]).unwrap();
+        assert!(!result.status);
+        assert_eq!(result.gas_used, 21000);
+
+        // No system log
+        let result = polyjuice_tx_result(&[user_log]).unwrap();
+        assert!(result.status);
+        assert_eq!(result.gas_used, 0);
+    }
+
+    #[test]
+    fn test_parse_truncated_polyjuice_system_log() {
+        let item = log_item(GW_LOG_POLYJUICE_SYSTEM, vec![0u8; 39]);
+        assert_eq!(
+            parse_log(&item).unwrap_err(),
+            LogParseError::InvalidDataLength {
+                len: 39,
+                expected: 40
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_truncated_polyjuice_user_log() {
+        // address + data size, but data is missing
+        let mut data = vec![0u8; 20];
+        data.extend_from_slice(&8u32.to_le_bytes());
+        let item = log_item(GW_LOG_POLYJUICE_USER, data);
+        assert_eq!(
+            parse_log(&item).unwrap_err(),
+            LogParseError::Truncated {
+                offset: 24,
+                len: 24
+            }
+        );
+
+        // topics count overflows data
+        let mut data = vec![0u8; 20];
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&[0u8; 32]);
+        let item = log_item(GW_LOG_POLYJUICE_USER, data);
+        assert!(matches!(
+            parse_log(&item),
+            Err(LogParseError::Truncated { .. })
+        ));
+
+        let item = log_item(GW_LOG_POLYJUICE_USER, vec![0u8; 3]);
+        assert!(matches!(
+            parse_log(&item),
+            Err(LogParseError::Truncated { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_polyjuice_user_log() {
+        let mut data = vec![1u8; 20];
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&[2u8; 2]);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[3u8; 32]);
+        let item = log_item(GW_LOG_POLYJUICE_USER, data);
+        match parse_log(&item).unwrap() {
+            GwLog::PolyjuiceUser {
+                address,
+                data,
+                topics,
+            } => {
+                assert_eq!(address, [1u8; 20]);
+                assert_eq!(data, vec![2u8; 2]);
+                assert_eq!(topics, vec![H256::from([3u8; 32])]);
+            }
+            log => panic!("unexpected log {:?}", log),
+        }
+    }
+
+    #[test]
+    fn test_run_result_to_web3_logs() {
+        let mut user_log_data = vec![1u8; 20];
+        user_log_data.extend_from_slice(&2u32.to_le_bytes());
+        user_log_data.extend_from_slice(&[2u8; 2]);
+        user_log_data.extend_from_slice(&1u32.to_le_bytes());
+        user_log_data.extend_from_slice(&[3u8; 32]);
+        let mut sudt_log_data = vec![20u8];
+        sudt_log_data.extend_from_slice(&[4u8; 56]);
+
+        let run_result = RunResult {
+            logs: vec![
+                log_item(GW_LOG_POLYJUICE_SYSTEM, vec![0u8; 40]),
+                log_item(GW_LOG_SUDT_TRANSFER, sudt_log_data),
+                log_item(GW_LOG_POLYJUICE_USER, vec![0u8; 3]),
+                log_item(GW_LOG_POLYJUICE_USER, user_log_data.clone()),
+                log_item(GW_LOG_POLYJUICE_USER, user_log_data),
+            ],
+            ..Default::default()
+        };
+        let context = LogContext {
+            tx_hash: [5u8; 32].into(),
+            tx_index: 1,
+            block_number: 2,
+            block_hash: [6u8; 32].into(),
+        };
+
+        // Sudt transfer of unknown accounts is skipped
+        let resolver = MockResolver::default();
+        let logs = run_result_to_web3_logs(&run_result, &context, &resolver).unwrap();
+        assert_eq!(logs.len(), 2);
+        assert_eq!(
+            logs[1],
+            Web3Log::new(
+                context.tx_hash,
+                1,
+                2,
+                context.block_hash,
+                [1u8; 20],
+                vec![2u8; 2],
+                1,
+                vec![[3u8; 32].into()],
+            )
+        );
+
+        // Logs indexed from the receipt of committed transaction
+        let tx_receipt = TxReceipt::new_builder()
+            .logs(run_result.logs.pack())
+            .build();
+        assert_eq!(
+            to_web3_logs(tx_receipt.logs(), &context, &resolver).unwrap(),
+            logs
+        );
+    }
+
+    #[test]
+    fn test_erc20_transfer_topic() {
+        assert_eq!(
+            crate::hash::keccak256("Transfer(address,address,uint256)"),
+            ERC20_TRANSFER_TOPIC
+        );
+    }
+
+    #[test]
+    fn test_sudt_transfer_web3_logs() {
+        let mut user_log_data = vec![1u8; 20];
+        user_log_data.extend_from_slice(&0u32.to_le_bytes());
+        user_log_data.extend_from_slice(&0u32.to_le_bytes());
+        let mut sudt_log_data = vec![20u8];
+        sudt_log_data.extend_from_slice(&[2u8; 20]);
+        sudt_log_data.extend_from_slice(&[3u8; 20]);
+        sudt_log_data.extend_from_slice(&1000u128.to_le_bytes());
+        let sudt_log = LogItem::new_builder()
+            .account_id(4u32.pack())
+            .service_flag(GW_LOG_SUDT_TRANSFER.into())
+            .data(Bytes::from(sudt_log_data).pack())
+            .build();
+
+        let log_items = vec![
+            log_item(GW_LOG_POLYJUICE_USER, user_log_data.clone()),
+            sudt_log,
+            log_item(GW_LOG_POLYJUICE_USER, user_log_data),
+        ];
+        let context = LogContext {
+            tx_hash: [5u8; 32].into(),
+            tx_index: 0,
+            block_number: 1,
+            block_hash: [6u8; 32].into(),
+        };
+        let mut resolver = MockResolver::default();
+        resolver.sudts.insert(4, [7u8; 20]);
+        resolver.accounts.insert([2u8; 20], [8u8; 20]);
+        resolver.accounts.insert([3u8; 20], [9u8; 20]);
+
+        let logs = to_web3_logs(log_items, &context, &resolver).unwrap();
+        let log_indexes: Vec<u32> = logs.iter().map(|log| log.log_index).collect();
+        assert_eq!(log_indexes, vec![0, 1, 2]);
+
+        let transfer_log = &logs[1];
+        assert_eq!(transfer_log.address, [7u8; 20]);
+        let mut from_topic = [0u8; 32];
+        from_topic[12..].copy_from_slice(&[8u8; 20]);
+        let mut to_topic = [0u8; 32];
+        to_topic[12..].copy_from_slice(&[9u8; 20]);
+        assert_eq!(
+            transfer_log.topics,
+            vec![
+                ERC20_TRANSFER_TOPIC.into(),
+                from_topic.into(),
+                to_topic.into()
+            ]
+        );
+        let mut data = [0u8; 32];
+        data[16..].copy_from_slice(&1000u128.to_be_bytes());
+        assert_eq!(transfer_log.data, data.to_vec());
+    }
+
+    #[test]
+    fn test_to_capped_decimal() {
+        let mut overflowed = vec![];
+        assert_eq!(
+            to_capped_decimal("gas_price", 42, &mut overflowed),
+            Decimal::from(42u64)
+        );
+        assert!(overflowed.is_empty());
+
+        assert_eq!(
+            to_capped_decimal("gas_price", u128::MAX, &mut overflowed),
+            Decimal::MAX
+        );
+        assert_eq!(
+            overflowed,
+            vec![OverflowedValue {
+                field: "gas_price",
+                value: u128::MAX,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_to_block_timestamp() {
+        let timestamp = to_block_timestamp(1620000000_123);
+        assert_eq!(timestamp.timestamp(), 1620000000);
+
+        // Out of range
+        assert_eq!(to_block_timestamp(u64::MAX).naive_utc(), MAX_DATETIME);
+        let max_millis = (MAX_DATETIME.timestamp() as u64 + 1) * MILLIS_PER_SEC;
+        assert_eq!(to_block_timestamp(max_millis).naive_utc(), MAX_DATETIME);
+    }
+}
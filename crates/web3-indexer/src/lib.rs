@@ -1,5 +1,9 @@
+pub mod bloom;
+pub mod hash;
 pub mod helper;
 pub mod indexer;
+pub mod query;
 pub mod types;
+pub mod verify;
 
 pub use indexer::Web3Indexer;
@@ -1,5 +1,5 @@
+use crate::hash::keccak256;
 use gw_common::H256;
-use sha3::{Digest, Keccak256};
 use sqlx::types::chrono::{DateTime, Utc};
 
 type Address = [u8; 20];
@@ -125,16 +125,11 @@ impl Transaction {
     pub fn compute_eth_tx_hash(&self) -> gw_common::H256 {
         // RLP encode
         let rlp_data = self.to_rlp();
-        let mut hasher = Keccak256::new();
-        hasher.update(&rlp_data);
-        let buf = hasher.finalize();
-        let mut tx_hash = [0u8; 32];
-        tx_hash.copy_from_slice(&buf);
-        tx_hash.into()
+        keccak256(rlp_data).into()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 pub struct Log {
     pub transaction_hash: H256,
     pub transaction_index: u32,
@@ -169,6 +164,15 @@ impl Log {
             topics,
         }
     }
+
+    /// topic0..topic3, stored in separate indexed columns
+    pub fn indexed_topics(&self) -> [Option<H256>; 4] {
+        let mut indexed_topics = [None; 4];
+        for (indexed_topic, topic) in indexed_topics.iter_mut().zip(self.topics.iter()) {
+            *indexed_topic = Some(*topic);
+        }
+        indexed_topics
+    }
 }
 
 #[derive(Debug)]
@@ -176,3 +180,54 @@ pub struct TransactionWithLogs {
     pub tx: Transaction,
     pub logs: Vec<Log>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_indexed_topics() {
+        let topics: Vec<H256> = (1u8..=5).map(|i| [i; 32].into()).collect();
+        let log = Log::new(
+            H256::zero(),
+            0,
+            1,
+            H256::zero(),
+            [0u8; 20],
+            vec![],
+            0,
+            topics[..2].to_vec(),
+        );
+        assert_eq!(
+            log.indexed_topics(),
+            [
+                Some(H256::from([1u8; 32])),
+                Some(H256::from([2u8; 32])),
+                None,
+                None
+            ]
+        );
+
+        // Topics after topic3 are only kept in `topics`
+        let log = Log::new(
+            H256::zero(),
+            0,
+            1,
+            H256::zero(),
+            [0u8; 20],
+            vec![],
+            0,
+            topics,
+        );
+        assert_eq!(
+            log.indexed_topics(),
+            [
+                Some(H256::from([1u8; 32])),
+                Some(H256::from([2u8; 32])),
+                Some(H256::from([3u8; 32])),
+                Some(H256::from([4u8; 32]))
+            ]
+        );
+        assert_eq!(log.topics.len(), 5);
+    }
+}
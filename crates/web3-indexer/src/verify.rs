@@ -0,0 +1,121 @@
+use crate::helper::hex;
+use crate::query::parse_h256;
+use anyhow::Result;
+use gw_common::H256;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+/// Summary of a block compared between the godwoken store and the index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedBlock {
+    pub hash: H256,
+    pub transaction_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexMismatch {
+    MissingBlock {
+        number: u64,
+    },
+    BlockHash {
+        number: u64,
+        expected: H256,
+        actual: H256,
+    },
+    TransactionCount {
+        number: u64,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+pub fn compare_block(
+    number: u64,
+    expected: &IndexedBlock,
+    actual: Option<&IndexedBlock>,
+) -> Vec<IndexMismatch> {
+    let actual = match actual {
+        Some(actual) => actual,
+        None => return vec![IndexMismatch::MissingBlock { number }],
+    };
+
+    let mut mismatches = Vec::new();
+    if expected.hash != actual.hash {
+        mismatches.push(IndexMismatch::BlockHash {
+            number,
+            expected: expected.hash,
+            actual: actual.hash,
+        });
+    }
+    if expected.transaction_count != actual.transaction_count {
+        mismatches.push(IndexMismatch::TransactionCount {
+            number,
+            expected: expected.transaction_count,
+            actual: actual.transaction_count,
+        });
+    }
+    mismatches
+}
+
+pub async fn query_indexed_block(pool: &PgPool, number: u64) -> Result<Option<IndexedBlock>> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT hash FROM blocks WHERE number = $1")
+        .bind(Decimal::from(number))
+        .fetch_optional(pool)
+        .await?;
+    let hash = match row {
+        Some((hash,)) => parse_h256(&hash)?,
+        None => return Ok(None),
+    };
+
+    let (transaction_count,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM transactions WHERE block_number = $1 AND block_hash = $2",
+    )
+    .bind(Decimal::from(number))
+    .bind(hex(hash.as_slice())?)
+    .fetch_one(pool)
+    .await?;
+    Ok(Some(IndexedBlock {
+        hash,
+        transaction_count: transaction_count as usize,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_block() {
+        let expected = IndexedBlock {
+            hash: [1u8; 32].into(),
+            transaction_count: 2,
+        };
+        assert!(compare_block(1, &expected, Some(&expected)).is_empty());
+
+        assert_eq!(
+            compare_block(1, &expected, None),
+            vec![IndexMismatch::MissingBlock { number: 1 }]
+        );
+
+        // corrupted row
+        let corrupted = IndexedBlock {
+            hash: [2u8; 32].into(),
+            transaction_count: 1,
+        };
+        assert_eq!(
+            compare_block(1, &expected, Some(&corrupted)),
+            vec![
+                IndexMismatch::BlockHash {
+                    number: 1,
+                    expected: [1u8; 32].into(),
+                    actual: [2u8; 32].into(),
+                },
+                IndexMismatch::TransactionCount {
+                    number: 1,
+                    expected: 2,
+                    actual: 1,
+                },
+            ]
+        );
+    }
+}